@@ -152,6 +152,16 @@ export type PutRequest = {
 export type UpdateRequest = {
   key: Key;
   data: UpdateData;
+  expectedSummary?: StateSummary;
+};
+
+/**
+ * Representation of the client update request operation applying several updates at once
+ * @public
+ */
+export type UpdateBatchRequest = {
+  key: Key;
+  data: UpdateData[];
 };
 
 /**
@@ -171,6 +181,15 @@ export type SubscribeRequest = {
   key: Key;
 };
 
+/**
+ * Representation of the client unsubscribe request operation
+ * @public
+ */
+export type UnsubscribeRequest = {
+  key: Key;
+  unsubscribe: true;
+};
+
 /**
  * Representation of the client disconnect request operation
  * @public
@@ -317,6 +336,15 @@ export class LocutusWsApi {
     this.ws.send(encoded);
   }
 
+  /**
+   * Sends an update request with several updates to the host through websocket
+   * @param update - The `UpdateBatchRequest` object
+   */
+  async updateBatch(update: UpdateBatchRequest): Promise<void> {
+    let encoded = this.encoder.encode(update);
+    this.ws.send(encoded);
+  }
+
   /**
    * Sends a get request to the host through websocket
    * @param get - The `GetRequest` object
@@ -335,6 +363,15 @@ export class LocutusWsApi {
     this.ws.send(encoded);
   }
 
+  /**
+   * Sends an unsubscribe request to the host through websocket
+   * @param unsubscribe - The `UnsubscribeRequest` object
+   */
+  async unsubscribe(unsubscribe: UnsubscribeRequest): Promise<void> {
+    let encoded = this.encoder.encode(unsubscribe);
+    this.ws.send(encoded);
+  }
+
   /**
    * Sends an disconnect notification to the host through websocket
    * @param disconnect - The `DisconnectRequest` object
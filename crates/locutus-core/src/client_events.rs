@@ -1,5 +1,5 @@
 use futures::future::BoxFuture;
-use locutus_runtime::{ComponentKey, ContractInstanceId};
+use locutus_runtime::{ComponentKey, ContractInstanceId, StateSummary};
 use locutus_stdlib::client_api::ClientRequest;
 use locutus_stdlib::client_api::{ClientError, HostResponse};
 use std::fmt::Debug;
@@ -98,8 +98,14 @@ pub enum RequestError {
 pub enum ComponentError {
     #[error("error while registering component: {0}")]
     RegisterError(ComponentKey),
+    #[error("error while rotating the key of component: {0}")]
+    KeyRotation(ComponentKey),
     #[error("execution error, cause: {0}")]
     ExecutionError(String),
+    #[error("no pending user input request {request_id} for component: {key}")]
+    UnknownUserInput { key: ComponentKey, request_id: u32 },
+    #[error("user input request {request_id} of component {key} timed out")]
+    UserInputTimeout { key: ComponentKey, request_id: u32 },
 }
 
 #[derive(Debug, thiserror::Error, Serialize, Deserialize, Clone)]
@@ -112,6 +118,25 @@ pub enum ContractError {
     Update { key: ContractKey, cause: String },
     #[error("missing related contract: {key}")]
     MissingRelated { key: ContractInstanceId },
+    #[error("contract {key} requested related contracts for over {rounds} rounds")]
+    RelatedRounds { key: ContractKey, rounds: usize },
+    #[error(
+        "state of contract {key} changed, current summary: {}",
+        bs58::encode(.summary).into_string()
+    )]
+    StateChanged {
+        key: ContractKey,
+        #[serde(deserialize_with = "deser_summary")]
+        summary: StateSummary<'static>,
+    },
+}
+
+fn deser_summary<'de, D>(deser: D) -> Result<StateSummary<'static>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <StateSummary as Deserialize>::deserialize(deser)?;
+    Ok(value.into_owned())
 }
 
 #[cfg(test)]
@@ -359,6 +384,7 @@ pub(crate) mod test {
             key: ContractKey::from_id("DCBi7HNZC3QUZRiZLFZDiEduv5KHgZfgBk8WwTiheGq1".to_string())
                 .unwrap(),
             data: locutus_runtime::StateDelta::from(vec![0, 1, 2]).into(),
+            expected_summary: None,
         };
         let msg: Vec<u8> = vec![
             130, 163, 107, 101, 121, 130, 168, 105, 110, 115, 116, 97, 110, 99, 101, 196, 32, 181,
@@ -372,6 +398,50 @@ pub(crate) mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_handle_update_request_with_summary() -> Result<(), Box<dyn std::error::Error>> {
+        let expected_client_request: ContractRequest = ContractRequest::Update {
+            key: ContractKey::from_id("DCBi7HNZC3QUZRiZLFZDiEduv5KHgZfgBk8WwTiheGq1".to_string())
+                .unwrap(),
+            data: locutus_runtime::StateDelta::from(vec![0, 1, 2]).into(),
+            expected_summary: Some(StateSummary::from(vec![7, 8])),
+        };
+        let msg: Vec<u8> = vec![
+            131, 163, 107, 101, 121, 130, 168, 105, 110, 115, 116, 97, 110, 99, 101, 196, 32, 181,
+            41, 189, 142, 103, 137, 251, 46, 133, 213, 21, 255, 179, 17, 3, 17, 240, 208, 191, 5,
+            215, 72, 60, 41, 194, 14, 217, 228, 225, 251, 209, 100, 164, 99, 111, 100, 101, 192,
+            164, 100, 97, 116, 97, 129, 165, 100, 101, 108, 116, 97, 196, 3, 0, 1, 2, 175, 101,
+            120, 112, 101, 99, 116, 101, 100, 83, 117, 109, 109, 97, 114, 121, 196, 2, 7, 8,
+        ];
+
+        let result_client_request: ContractRequest = ContractRequest::try_decode(&msg)?;
+        assert_eq!(result_client_request, expected_client_request);
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_update_batch_request() -> Result<(), Box<dyn std::error::Error>> {
+        let expected_client_request: ContractRequest = ContractRequest::UpdateBatch {
+            key: ContractKey::from_id("DCBi7HNZC3QUZRiZLFZDiEduv5KHgZfgBk8WwTiheGq1".to_string())
+                .unwrap(),
+            data: vec![
+                locutus_runtime::StateDelta::from(vec![0, 1, 2]).into(),
+                locutus_runtime::StateDelta::from(vec![3]).into(),
+            ],
+        };
+        let msg: Vec<u8> = vec![
+            130, 163, 107, 101, 121, 130, 168, 105, 110, 115, 116, 97, 110, 99, 101, 196, 32, 181,
+            41, 189, 142, 103, 137, 251, 46, 133, 213, 21, 255, 179, 17, 3, 17, 240, 208, 191, 5,
+            215, 72, 60, 41, 194, 14, 217, 228, 225, 251, 209, 100, 164, 99, 111, 100, 101, 192,
+            164, 100, 97, 116, 97, 146, 129, 165, 100, 101, 108, 116, 97, 196, 3, 0, 1, 2, 129,
+            165, 100, 101, 108, 116, 97, 196, 1, 3,
+        ];
+
+        let result_client_request: ContractRequest = ContractRequest::try_decode(&msg)?;
+        assert_eq!(result_client_request, expected_client_request);
+        Ok(())
+    }
+
     #[test]
     fn test_handle_get_request() -> Result<(), Box<dyn std::error::Error>> {
         let expected_client_request: ContractRequest = ContractRequest::Get {
@@ -7,10 +7,11 @@ use std::{
 };
 
 use futures::{future::BoxFuture, stream::SplitSink, SinkExt, StreamExt};
-use locutus_runtime::prelude::TryFromTsStd;
+use locutus_runtime::prelude::{TryFromTsStd, WsApiError};
 use locutus_stdlib::client_api::{ClientRequest, ContractRequest, ErrorKind, HostResponse};
+use serde::Deserialize;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use warp::{filters::BoxedFilter, Filter, Reply};
+use warp::{filters::BoxedFilter, Filter, Rejection, Reply};
 
 use super::{ClientError, ClientEventsProxy, ClientId, HostResult, OpenRequest};
 
@@ -23,6 +24,42 @@ pub struct WebSocketProxy {
 
 type NewResponseSender = Sender<Result<HostResponse, ClientError>>;
 
+/// Encoding of the requests sent through a websocket connection, chosen by the client with the
+/// `encodingProtocol` query parameter of the connection URL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingProtocol {
+    /// MessagePack serialization of the Rust client API, which covers every client request.
+    Native,
+    /// Encoding of the TypeScript client API, which only covers contract requests.
+    #[default]
+    TypeScript,
+}
+
+impl EncodingProtocol {
+    /// Extracts the encoding from the query of the connection URL, defaults to
+    /// [`EncodingProtocol::TypeScript`] if missing.
+    pub fn from_query() -> impl Filter<Extract = (Self,), Error = Rejection> + Copy {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ConnectionParams {
+            #[serde(default)]
+            encoding_protocol: EncodingProtocol,
+        }
+        warp::query::<ConnectionParams>().map(|params: ConnectionParams| params.encoding_protocol)
+    }
+
+    /// Decodes a request sent by a client.
+    pub fn decode_request(self, msg: &[u8]) -> Result<ClientRequest<'static>, WsApiError> {
+        match self {
+            Self::Native => rmp_serde::from_slice::<ClientRequest>(msg)
+                .map(ClientRequest::into_owned)
+                .map_err(|err| WsApiError::deserialization(format!("{err}"))),
+            Self::TypeScript => ContractRequest::try_decode(msg).map(Into::into),
+        }
+    }
+}
+
 impl WebSocketProxy {
     /// Starts this as an upgrade to an existing HTTP connection at the `/ws-api` URL
     pub fn as_upgrade<T>(
@@ -111,10 +148,15 @@ async fn serve(
     let req_channel = warp::any().map(move || (request_sender.clone(), new_responses.clone()));
     let request_receiver = server_config.or(warp::path("ws-api")
         .and(warp::ws())
+        .and(EncodingProtocol::from_query())
         .and(req_channel)
-        .map(|ws: warp::ws::Ws, (request_sender, new_responses)| {
-            ws.on_upgrade(move |socket| handle_socket(socket, request_sender, new_responses))
-        })
+        .map(
+            |ws: warp::ws::Ws, encoding, (request_sender, new_responses)| {
+                ws.on_upgrade(move |socket| {
+                    handle_socket(socket, encoding, request_sender, new_responses)
+                })
+            },
+        )
         .with(warp::trace::request()));
     warp::serve(request_receiver).run(socket).await;
 }
@@ -165,6 +207,7 @@ static CLIENT_ID: AtomicUsize = AtomicUsize::new(0);
 
 async fn handle_socket(
     socket: warp::ws::WebSocket,
+    encoding: EncodingProtocol,
     request_sender: Sender<StaticOpenRequest>,
     client_handler: Sender<ClientHandling>,
 ) {
@@ -182,7 +225,7 @@ async fn handle_socket(
     loop {
         tokio::select! {
             result = client_rx.next() => {
-                if new_request(&request_sender, client_id, result).await.is_err() {
+                if new_request(&request_sender, client_id, encoding, result).await.is_err() {
                     break;
                 }
             }
@@ -199,13 +242,14 @@ async fn handle_socket(
 async fn new_request(
     request_sender: &Sender<StaticOpenRequest>,
     id: ClientId,
+    encoding: EncodingProtocol,
     result: Option<Result<warp::ws::Message, warp::Error>>,
 ) -> Result<(), ()> {
     let msg = match result {
         Some(Ok(msg)) if msg.is_binary() => {
             let data = msg.into_bytes();
-            let deserialized: ClientRequest = match ContractRequest::try_decode(&data) {
-                Ok(m) => m.into(),
+            let deserialized: ClientRequest = match encoding.decode_request(&data) {
+                Ok(m) => m,
                 Err(e) => {
                     let _ = request_sender
                         .send(
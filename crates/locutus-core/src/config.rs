@@ -78,9 +78,11 @@ impl WebSocketApiConfig {
 
 #[derive(Debug)]
 pub struct ConfigPaths {
-    // TODO: Add secrets and components dir
     pub(crate) contracts_dir: PathBuf,
+    pub(crate) components_dir: PathBuf,
+    pub(crate) secrets_dir: PathBuf,
     pub(crate) db_dir: PathBuf,
+    pub(crate) module_cache_dir: PathBuf,
     app_data_dir: PathBuf,
 }
 
@@ -93,9 +95,11 @@ impl ConfigPaths {
         } else {
             project_dir.data_dir().into()
         };
-        // FIXME: Add dirs for components and secrets
         let contracts_dir = app_data_dir.join("contracts");
+        let components_dir = app_data_dir.join("components");
+        let secrets_dir = app_data_dir.join("secrets");
         let db_dir = app_data_dir.join("db");
+        let module_cache_dir = app_data_dir.join("module_cache");
 
         if !contracts_dir.exists() {
             fs::create_dir_all(&contracts_dir)?;
@@ -108,7 +112,10 @@ impl ConfigPaths {
 
         Ok(Self {
             contracts_dir,
+            components_dir,
+            secrets_dir,
             db_dir,
+            module_cache_dir,
             app_data_dir,
         })
     }
@@ -120,6 +127,18 @@ impl ConfigPaths {
     pub fn contracts_dir(&self) -> &Path {
         &self.contracts_dir
     }
+
+    pub fn components_dir(&self) -> &Path {
+        &self.components_dir
+    }
+
+    pub fn secrets_dir(&self) -> &Path {
+        &self.secrets_dir
+    }
+
+    pub fn module_cache_dir(&self) -> &Path {
+        &self.module_cache_dir
+    }
 }
 
 impl Config {
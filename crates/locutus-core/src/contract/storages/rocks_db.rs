@@ -1,5 +1,5 @@
 use rocksdb::{Options, DB};
-use std::{collections::HashMap, pin::Pin};
+use std::{collections::HashMap, pin::Pin, sync::Arc};
 
 use futures::future::BoxFuture;
 use futures::{Future, FutureExt};
@@ -15,7 +15,8 @@ use crate::{config::CONFIG, contract::test::MockRuntime, WrappedState};
 use super::super::handler::{CHListenerHalve, MAX_MEM_CACHE};
 use super::super::{ContractHandler, ContractHandlerChannel};
 
-pub struct RocksDb(DB);
+#[derive(Clone)]
+pub struct RocksDb(Arc<DB>);
 
 impl RocksDb {
     pub async fn new() -> Result<Self, rocksdb::Error> {
@@ -28,7 +29,7 @@ impl RocksDb {
 
         let db = DB::open(&opts, path).unwrap();
 
-        Ok(Self(db))
+        Ok(Self(Arc::new(db)))
     }
 }
 
@@ -323,6 +324,7 @@ mod test {
                 ContractRequest::Update {
                     key: contract.key().clone(),
                     data: delta.into(),
+                    expected_summary: None,
                 }
                 .into(),
             )
@@ -359,6 +359,7 @@ mod test {
                 ContractRequest::Update {
                     key: contract.key().clone(),
                     data: delta.into(),
+                    expected_summary: None,
                 }
                 .into(),
             )
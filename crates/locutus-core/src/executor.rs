@@ -1,22 +1,63 @@
 //! Contract executor.
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use blake2::digest::generic_array::GenericArray;
 use locutus_runtime::prelude::*;
 use locutus_stdlib::client_api::{
-    ClientError, ClientRequest, ComponentRequest, ContractRequest, ContractResponse, HostResponse,
+    ClientError, ClientRequest, ComponentRequest, ContractRequest, ContractResponse, ErrorKind,
+    HostResponse,
 };
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use crate::{
     client_events::{ComponentError as CoreComponentError, ContractError as CoreContractError},
     either::Either,
-    ClientId, DynError, HostResult, RequestError, Storage,
+    ClientId, DynError, HostResult, OpenRequest, RequestError, Storage,
 };
 
 type Response = Result<HostResponse, Either<RequestError, DynError>>;
 
+/// Max size of the compiled modules kept on disk, 1GiB.
+const MAX_MODULE_CACHE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Max size in bytes of the components kept in memory by the component store.
+const MAX_COMPONENTS_CACHE_SIZE: i64 = 10 * 1024 * 1024;
+
+/// Max rounds of notifications sent for contracts updated by components or by the changes
+/// of their related contracts, which were notified of a previous update.
+const MAX_UPDATE_ROUNDS: usize = 8;
+
+/// Time the users have to answer the input requested by a component.
+const USER_INPUT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Max rounds of related contracts fetched for a contract while validating or updating its state.
+const MAX_RELATED_ROUNDS: usize = 4;
+
+/// Time to fetch the state of a related contract from the network.
+const RELATED_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time a contract keeps receiving the changes of a related contract it subscribed to.
+const RELATED_SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+type ComponentSubscriptions = Arc<Mutex<HashMap<ContractKey, HashSet<ComponentKey>>>>;
+
+/// Components waiting for the input of the user of a client, by request id.
+type PendingUserInputs = Arc<Mutex<HashSet<(ClientId, ComponentKey, u32)>>>;
+
+/// Contracts subscribed to the changes of a related contract through
+/// [`RelatedMode::StateThenSubscribe`].
+struct RelatedSubscription {
+    /// last state of the related contract notified to the dependent contracts
+    state: WrappedState,
+    /// dependent contracts and when their subscription expires
+    dependents: HashMap<ContractKey, Instant>,
+}
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum OperationMode {
     /// Run the node in local-only mode. Useful for development purposes.
@@ -25,17 +66,85 @@ pub enum OperationMode {
     Network,
 }
 
+/// Access of the executor to the contracts of the network, used in [`OperationMode::Network`]
+/// to fetch the related contracts not available locally.
+#[async_trait::async_trait]
+pub trait NetworkContracts: Send + Sync {
+    /// Current state of the contract, if it could be found in the network.
+    async fn get_state(&self, id: ContractInstanceId) -> Result<Option<WrappedState>, DynError>;
+}
+
 /// A WASM executor which will run any contracts, components, etc. registered.
 ///
 /// This executor will monitor the store directories and databases to detect state changes.
 /// Consumers of the executor are required to poll for new changes in order to be notified
 /// of changes or can alternatively use the notification channel.
+///
+/// Requests are handled one at a time, so loading, updating and storing the state of a
+/// contract, either for a client or for a component, never interleaves with other updates.
 pub struct Executor {
     mode: OperationMode,
-    runtime: Runtime,
+    runtime: AsyncRuntime,
+    determinism: Determinism,
     contract_state: StateStore<Storage>,
     update_notifications: HashMap<ContractKey, Vec<(ClientId, UnboundedSender<HostResult>)>>,
     subscriber_summaries: HashMap<ContractKey, HashMap<ClientId, StateSummary<'static>>>,
+    component_subscriptions: ComponentSubscriptions,
+    /// contracts updated by components or related contracts, pending to notify their subscribers
+    pending_updates: Arc<Mutex<Vec<ContractKey>>>,
+    pending_user_inputs: PendingUserInputs,
+    user_input_prompts: UnboundedSender<(ClientId, HostResult)>,
+    user_input_prompts_rx: Option<UnboundedReceiver<(ClientId, HostResult)>>,
+    network: Option<Box<dyn NetworkContracts>>,
+    related_subscriptions: HashMap<ContractKey, RelatedSubscription>,
+}
+
+/// Contracts of the executor accessed by the components.
+struct ComponentContracts {
+    state: StateStore<Storage>,
+    handle: tokio::runtime::Handle,
+    subscriptions: ComponentSubscriptions,
+    updates: Arc<Mutex<Vec<ContractKey>>>,
+}
+
+impl ContractAccess for ComponentContracts {
+    fn get(
+        &mut self,
+        key: &ContractKey,
+    ) -> Result<Option<(Parameters<'static>, WrappedState)>, DynError> {
+        self.handle.block_on(async {
+            let params = match self.state.get_params(key).await {
+                Ok(params) => params,
+                Err(StateStoreError::MissingContract) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+            match self.state.get(key).await {
+                Ok(state) => Ok(Some((params, state))),
+                Err(StateStoreError::MissingContract) => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn store(&mut self, key: &ContractKey, state: WrappedState) -> Result<(), DynError> {
+        self.handle
+            .block_on(self.state.store(key.clone(), state, None))?;
+        let mut updates = self.updates.lock().unwrap();
+        if !updates.contains(key) {
+            updates.push(key.clone());
+        }
+        Ok(())
+    }
+
+    fn subscribe(&mut self, component: &ComponentKey, key: &ContractKey) -> Result<(), DynError> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .insert(component.clone());
+        Ok(())
+    }
 }
 
 impl Executor {
@@ -44,24 +153,76 @@ impl Executor {
         contract_state: StateStore<Storage>,
         ctrl_handler: impl FnOnce(),
         mode: OperationMode,
+        runtime_config: RuntimeConfig,
     ) -> Result<Self, DynError> {
         ctrl_handler();
 
+        let determinism = runtime_config.determinism;
+        let config_paths = &crate::config::CONFIG.config_paths;
+        let mut runtime = Runtime::build_with_config(
+            store,
+            ComponentStore::new(
+                config_paths.components_dir().to_owned(),
+                MAX_COMPONENTS_CACHE_SIZE,
+            )?,
+            SecretsStore::new(config_paths.secrets_dir().to_owned())?,
+            false,
+            runtime_config,
+        )?;
+        // the modules are compiled on demand if the cache can't be used
+        match runtime.enable_module_cache(config_paths.module_cache_dir(), MAX_MODULE_CACHE_SIZE) {
+            Ok(_) => match runtime.prewarm_module_cache() {
+                Ok(compiled) => tracing::debug!("compiled {compiled} contracts ahead of time"),
+                Err(err) => tracing::warn!("failed to compile contracts ahead of time: {err}"),
+            },
+            Err(err) => tracing::warn!("module cache disabled: {err}"),
+        }
+
+        let component_subscriptions = ComponentSubscriptions::default();
+        let pending_updates = Arc::new(Mutex::new(Vec::new()));
+        runtime.set_contract_access(ComponentContracts {
+            state: contract_state.clone(),
+            handle: tokio::runtime::Handle::current(),
+            subscriptions: component_subscriptions.clone(),
+            updates: pending_updates.clone(),
+        });
+
+        let (user_input_prompts, user_input_prompts_rx) = mpsc::unbounded_channel();
         Ok(Self {
             mode,
-            runtime: Runtime::build(
-                store,
-                ComponentStore::default(),
-                SecretsStore::default(),
-                false,
-            )
-            .unwrap(),
+            runtime: AsyncRuntime::new(runtime)?,
+            determinism,
             contract_state,
             update_notifications: HashMap::default(),
             subscriber_summaries: HashMap::default(),
+            component_subscriptions,
+            pending_updates,
+            pending_user_inputs: PendingUserInputs::default(),
+            user_input_prompts,
+            user_input_prompts_rx: Some(user_input_prompts_rx),
+            network: None,
+            related_subscriptions: HashMap::default(),
         })
     }
 
+    /// Sets the access to the network used to fetch related contracts in network mode.
+    pub fn set_network(&mut self, network: impl NetworkContracts + 'static) {
+        self.network = Some(Box::new(network));
+    }
+
+    /// Inputs requested by the components to the users of each client, as
+    /// [`HostResponse::UserInputRequest`], and the errors of the requests which timed out.
+    ///
+    /// Returns `None` if the receiver was already taken.
+    pub fn user_input_prompts(&mut self) -> Option<UnboundedReceiver<(ClientId, HostResult)>> {
+        self.user_input_prompts_rx.take()
+    }
+
+    /// Handle to the runtime executing the contracts and components.
+    pub fn runtime(&self) -> AsyncRuntime {
+        self.runtime.clone()
+    }
+
     pub fn register_contract_notifier(
         &mut self,
         key: ContractKey,
@@ -70,13 +231,14 @@ impl Executor {
         summary: StateSummary<'static>,
     ) -> Result<(), DynError> {
         let channels = self.update_notifications.entry(key.clone()).or_default();
-        if let Ok(i) = channels.binary_search_by_key(&&cli_id, |(p, _)| p) {
-            let (_, existing_ch) = &channels[i];
-            if !existing_ch.same_channel(&notification_ch) {
-                return Err(format!("peer {cli_id} has multiple notification channels").into());
+        match channels.binary_search_by_key(&&cli_id, |(p, _)| p) {
+            Ok(i) => {
+                let (_, existing_ch) = &channels[i];
+                if !existing_ch.same_channel(&notification_ch) {
+                    return Err(format!("peer {cli_id} has multiple notification channels").into());
+                }
             }
-        } else {
-            channels.push((cli_id, notification_ch));
+            Err(i) => channels.insert(i, (cli_id, notification_ch)),
         }
 
         if self
@@ -93,6 +255,98 @@ impl Executor {
         Ok(())
     }
 
+    /// Stops notifying the updates of the contract to the client.
+    pub fn remove_contract_notifier(&mut self, key: &ContractKey, cli_id: ClientId) {
+        if let Some(channels) = self.update_notifications.get_mut(key) {
+            channels.retain(|(client, _)| *client != cli_id);
+            if channels.is_empty() {
+                self.update_notifications.remove(key);
+            }
+        }
+        if let Some(summaries) = self.subscriber_summaries.get_mut(key) {
+            summaries.remove(&cli_id);
+            if summaries.is_empty() {
+                self.subscriber_summaries.remove(key);
+            }
+        }
+    }
+
+    /// Limits the disk space used by the code of the registered components, see
+    /// [`ComponentStore::evict`].
+    pub async fn set_component_quota(&self, quota: Option<DiskQuota>) -> Result<(), DynError> {
+        self.runtime
+            .run(move |runtime| {
+                runtime.component_store.set_quota(quota);
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Evicts registered components while the component store is over its quota. Components
+    /// subscribed to contracts, or waiting on user input, are kept along the `registered` one.
+    async fn evict_components(&mut self, registered: ComponentKey) -> Result<(), DynError> {
+        let mut referenced: HashSet<_> = self
+            .component_subscriptions
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .cloned()
+            .collect();
+        referenced.extend(
+            self.pending_user_inputs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, key, _)| key.clone()),
+        );
+        referenced.insert(registered);
+        let evicted = self
+            .runtime
+            .run(move |runtime| runtime.evict_components(|key| referenced.contains(key)))
+            .await?;
+        if !evicted.is_empty() {
+            tracing::debug!("evicted {} components from the store", evicted.len());
+        }
+        Ok(())
+    }
+
+    /// Evicts stored contracts while the contract store is over its quota. Contracts with
+    /// a stored state or subscribers are kept.
+    async fn evict_contracts(&mut self) -> Result<(), DynError> {
+        let keys = self
+            .runtime
+            .run(|runtime| {
+                let store = &runtime.contract_store;
+                let over_quota = store
+                    .quota()
+                    .map(|quota| store.disk_usage() > quota.max_size)
+                    .unwrap_or_default();
+                Ok(over_quota.then(|| store.contract_keys()))
+            })
+            .await?;
+        let Some(keys) = keys else {
+            return Ok(());
+        };
+        let mut referenced = HashSet::new();
+        for key in keys {
+            if self.update_notifications.contains_key(&key)
+                || self.contract_state.get(&key).await.is_ok()
+            {
+                referenced.insert(key);
+            }
+        }
+        let evicted = self
+            .runtime
+            .run(move |runtime| runtime.evict_contracts(|key| referenced.contains(key)))
+            .await?;
+        if !evicted.is_empty() {
+            tracing::debug!("evicted {} contracts from the store", evicted.len());
+        }
+        Ok(())
+    }
+
     pub async fn preload(
         &mut self,
         cli_id: ClientId,
@@ -126,13 +380,30 @@ impl Executor {
         req: ClientRequest<'static>,
         updates: Option<UnboundedSender<Result<HostResponse, ClientError>>>,
     ) -> Response {
+        self.fix_call_time().await?;
         match req {
             ClientRequest::ContractOp(op) => self.contract_op(op, id, updates).await,
-            ClientRequest::ComponentOp(op) => self.component_op(op),
+            ClientRequest::ComponentOp(op) => self.component_op(op, id).await,
             ClientRequest::Disconnect { cause } => {
                 if let Some(cause) = cause {
                     tracing::info!("disconnecting cause: {cause}");
                 }
+                self.pending_user_inputs
+                    .lock()
+                    .unwrap()
+                    .retain(|(client, ..)| *client != id);
+                if let Err(err) = self.runtime.close_session(SessionId(id.into())).await {
+                    tracing::warn!("failed closing the component session of {id}: {err}");
+                }
+                let subscribed = self
+                    .update_notifications
+                    .iter()
+                    .filter(|(_, channels)| channels.iter().any(|(client, _)| *client == id))
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+                for key in subscribed {
+                    self.remove_contract_notifier(&key, id);
+                }
                 Err(Either::Left(RequestError::Disconnect))
             }
             ClientRequest::GenerateRandData { bytes } => {
@@ -143,9 +414,162 @@ impl Executor {
         }
     }
 
+    /// Handles the requests in the order they were received, except for the updates of the same
+    /// contract, which are merged into a single contract execution, even if sent by different
+    /// clients, until another request concerns that contract. Updates expecting a summary are
+    /// not merged.
+    ///
+    /// If the merged updates fail, each of them is retried on its own, so a faulty update
+    /// doesn't fail the rest. Returns the responses in the order of the requests.
+    pub async fn handle_requests(
+        &mut self,
+        requests: Vec<OpenRequest<'static>>,
+    ) -> Vec<(ClientId, Response)> {
+        enum Step {
+            Request(usize, OpenRequest<'static>),
+            Updates {
+                key: ContractKey,
+                updates: Vec<(usize, ClientId, Vec<UpdateData<'static>>)>,
+            },
+        }
+
+        let total = requests.len();
+        let mut steps = Vec::with_capacity(total);
+        let mut open_updates = HashMap::new();
+        for (idx, req) in requests.into_iter().enumerate() {
+            let (key, data) = match req.request {
+                ClientRequest::ContractOp(ContractRequest::Update {
+                    key,
+                    data,
+                    expected_summary: None,
+                }) => (key, vec![data]),
+                ClientRequest::ContractOp(ContractRequest::UpdateBatch { key, data })
+                    if !data.is_empty() =>
+                {
+                    (key, data)
+                }
+                request => {
+                    let concerned = match &request {
+                        ClientRequest::ContractOp(ContractRequest::Put { contract, .. }) => {
+                            Some(contract.key())
+                        }
+                        ClientRequest::ContractOp(
+                            ContractRequest::Update { key, .. }
+                            | ContractRequest::UpdateBatch { key, .. }
+                            | ContractRequest::Get { key, .. }
+                            | ContractRequest::Subscribe { key }
+                            | ContractRequest::Unsubscribe { key },
+                        ) => Some(key.clone()),
+                        // components may update any contract
+                        _ => None,
+                    };
+                    match concerned {
+                        Some(key) => {
+                            open_updates.remove(&key);
+                        }
+                        None => open_updates.clear(),
+                    }
+                    steps.push(Step::Request(idx, OpenRequest { request, ..req }));
+                    continue;
+                }
+            };
+            match open_updates.get(&key) {
+                Some(&step) => {
+                    if let Step::Updates { updates, .. } = &mut steps[step] {
+                        updates.push((idx, req.id, data));
+                    }
+                }
+                None => {
+                    open_updates.insert(key.clone(), steps.len());
+                    steps.push(Step::Updates {
+                        key,
+                        updates: vec![(idx, req.id, data)],
+                    });
+                }
+            }
+        }
+
+        let mut responses = Vec::with_capacity(total);
+        for step in steps {
+            match step {
+                Step::Request(
+                    idx,
+                    OpenRequest {
+                        id,
+                        request,
+                        notification_channel,
+                    },
+                ) => {
+                    let res = self.handle_request(id, request, notification_channel).await;
+                    responses.push((idx, id, res));
+                }
+                Step::Updates { key, mut updates } if updates.len() == 1 => {
+                    let (idx, id, data) = updates.remove(0);
+                    let res = self.perform_client_update(key, data).await;
+                    responses.push((idx, id, res));
+                }
+                Step::Updates { key, updates } => {
+                    let merged = updates
+                        .iter()
+                        .flat_map(|(_, _, data)| data.iter().cloned())
+                        .collect();
+                    match self.perform_client_update(key.clone(), merged).await {
+                        Ok(HostResponse::ContractResponse(res)) => {
+                            for (idx, id, _) in updates {
+                                responses.push((idx, id, Ok(res.clone().into())));
+                            }
+                        }
+                        res => {
+                            if let Err(err) = res {
+                                tracing::debug!(
+                                    "retrying the merged updates of {key} one by one: {err}"
+                                );
+                            }
+                            for (idx, id, data) in updates {
+                                let res = self.perform_client_update(key.clone(), data).await;
+                                responses.push((idx, id, res));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        responses.sort_by_key(|(idx, ..)| *idx);
+        responses
+            .into_iter()
+            .map(|(_, id, res)| (id, res))
+            .collect()
+    }
+
+    /// Fixes the time observed by the contracts in the following calls, if they run with
+    /// [`Determinism::HostInputs`], so all the calls of a request see the same time.
+    async fn fix_call_time(&self) -> Result<(), Either<RequestError, DynError>> {
+        if self.determinism != Determinism::HostInputs {
+            return Ok(());
+        }
+        let now = chrono::Utc::now();
+        self.runtime
+            .run(move |runtime| {
+                runtime.set_call_time(now);
+                Ok(())
+            })
+            .await
+            .map_err(|err| Either::Right(err.into()))
+    }
+
+    /// Applies the updates sent by the clients, handled outside of [`Executor::handle_request`].
+    async fn perform_client_update(
+        &mut self,
+        key: ContractKey,
+        updates: Vec<UpdateData<'static>>,
+    ) -> Response {
+        self.fix_call_time().await?;
+        self.perform_update(key, updates, None).await
+    }
+
     async fn contract_op(
         &mut self,
-        req: ContractRequest<'_>,
+        req: ContractRequest<'static>,
         id: ClientId,
         updates: Option<UnboundedSender<Result<HostResponse, ClientError>>>,
     ) -> Response {
@@ -153,7 +577,7 @@ impl Executor {
             ContractRequest::Put {
                 contract,
                 state,
-                mut related_contracts,
+                related_contracts,
             } => {
                 // FIXME: in net node, we don't allow puts for existing contract states
                 //        if it hits a node which already has it it will get rejected
@@ -167,44 +591,18 @@ impl Executor {
                 //        you can request to several nodes and determine which node has a fresher ver
                 let key = contract.key();
                 let params = contract.params();
+                let stored = contract.clone();
                 self.runtime
-                    .contract_store
-                    .store_contract(contract.clone())
+                    .run(move |runtime| runtime.contract_store.store_contract(stored))
+                    .await
                     .map_err(Into::into)
                     .map_err(Either::Right)?;
 
                 tracing::debug!("executing with params: {:?}", params);
 
-                if self.mode == OperationMode::Local {
-                    for (id, related) in related_contracts.update() {
-                        let Ok(contract) = self.contract_state.get(&(*id).into()).await else {
-                            return Err(Either::Right(CoreContractError::MissingRelated { key: *id}.into()));
-                        };
-                        let state: &[u8] = unsafe {
-                            // Safety: this is fine since this will never scape this scope
-                            std::mem::transmute::<&[u8], &'_ [u8]>(contract.as_ref())
-                        };
-                        *related = Some(State::from(state));
-                    }
-                }
-
-                let result = self
-                    .runtime
+                let is_valid = self
                     .validate_state(&key, &params, &state, related_contracts)
-                    .map_err(Into::into)
-                    .map_err(Either::Right)?;
-                let is_valid = match result {
-                    ValidateResult::Valid => true,
-                    ValidateResult::Invalid => false,
-                    ValidateResult::RequestRelated(_related) => {
-                        if self.mode == OperationMode::Network {
-                            // FIXME: should deal with additional related contracts requested
-                            todo!()
-                        } else {
-                            unreachable!()
-                        }
-                    }
-                };
+                    .await?;
                 let res = is_valid
                     .then(|| ContractResponse::PutResponse { key: key.clone() }.into())
                     .ok_or_else(|| {
@@ -233,59 +631,28 @@ impl Executor {
                             .into(),
                         )
                     })?;
+                if let Err(err) = self.evict_contracts().await {
+                    tracing::warn!("failed to evict contracts: {err}");
+                }
+                self.notify_pending_updates().await;
                 Ok(res)
             }
-            ContractRequest::Update { key, data } => {
-                let parameters = {
-                    self.contract_state
-                        .get_params(&key)
-                        .await
-                        .map_err(|err| Either::Right(err.into()))?
-                };
-                let new_state = {
-                    let state = self
-                        .contract_state
-                        .get(&key)
-                        .await
-                        .map_err(Into::into)
-                        .map_err(Either::Right)?
-                        .clone();
-                    let update_modification = self
-                        .runtime
-                        .update_state(&key, &parameters, &state, &[data])
-                        .map_err(|err| match err {
-                            err if err.is_contract_exec_error() => Either::Left(
-                                CoreContractError::Update {
-                                    key: key.clone(),
-                                    cause: format!("{err}"),
-                                }
-                                .into(),
-                            ),
-                            other => Either::Right(other.into()),
-                        })?;
-                    if let Some(new_state) = update_modification.new_state {
-                        let new_state = WrappedState::new(new_state.into_bytes());
-                        self.contract_state
-                            .store(key.clone(), new_state.clone(), None)
-                            .await
-                            .map_err(|err| Either::Right(err.into()))?;
-                        new_state
-                    } else {
-                        todo!()
-                    }
-                };
-                // in the network impl this would be sent over the network
-                let summary = self
-                    .runtime
-                    .summarize_state(&key, &parameters, &new_state)
-                    .map_err(Into::into)
-                    .map_err(Either::Right)?;
-                self.send_update_notification(&key, &parameters, &new_state)
-                    .await?;
-                // TODO: in network mode, wait at least for one confirmation
-                //       when a node receives a delta from updates, run the update themselves
-                //       and send back confirmation
-                Ok(ContractResponse::UpdateResponse { key, summary }.into())
+            ContractRequest::Update {
+                key,
+                data,
+                expected_summary,
+            } => self.perform_update(key, vec![data], expected_summary).await,
+            ContractRequest::UpdateBatch { key, data } => {
+                if data.is_empty() {
+                    return Err(Either::Left(
+                        CoreContractError::Update {
+                            key,
+                            cause: "empty batch of updates".to_owned(),
+                        }
+                        .into(),
+                    ));
+                }
+                self.perform_update(key, data, None).await
             }
             ContractRequest::Get {
                 key,
@@ -295,40 +662,89 @@ impl Executor {
                 let updates =
                     updates.ok_or_else(|| Either::Right("missing update channel".into()))?;
                 self.register_contract_notifier(key.clone(), id, updates, [].as_ref().into())
-                    .unwrap();
+                    .map_err(Either::Right)?;
                 tracing::info!("getting contract: {}", key.encoded_contract_id());
                 // by default a subscribe op has an implicit get
                 self.perform_get(true, key).await.map_err(Either::Left)
                 // todo: in network mode, also send a subscribe to keep up to date
             }
+            ContractRequest::Unsubscribe { key } => {
+                self.remove_contract_notifier(&key, id);
+                Ok(HostResponse::Ok)
+            }
         }
     }
 
-    fn component_op(&mut self, req: ComponentRequest<'_>) -> Response {
+    async fn component_op(&mut self, req: ComponentRequest<'static>, id: ClientId) -> Response {
         match req {
             ComponentRequest::RegisterComponent {
                 component,
                 cipher,
-                nonce,
+                legacy_nonce,
             } => {
                 use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
                 let key = component.key().clone();
 
                 let arr = GenericArray::from_slice(&cipher);
                 let cipher = XChaCha20Poly1305::new(arr);
-                let nonce = GenericArray::from_slice(&nonce).to_owned();
+                let legacy_nonce = legacy_nonce.map(|nonce| *GenericArray::from_slice(&nonce));
 
-                match self.runtime.register_component(component, cipher, nonce) {
-                    Ok(_) => Ok(HostResponse::Ok),
+                let registered = key.clone();
+                match self
+                    .runtime
+                    .run(move |runtime| runtime.register_component(component, cipher, legacy_nonce))
+                    .await
+                {
+                    Ok(_) => {
+                        if let Err(err) = self.evict_components(registered).await {
+                            tracing::warn!("failed to evict components: {err}");
+                        }
+                        Ok(HostResponse::Ok)
+                    }
                     Err(err) => {
                         tracing::error!("failed registering component `{key}`: {err}");
                         Err(Either::Left(CoreComponentError::RegisterError(key).into()))
                     }
                 }
             }
-            ComponentRequest::UnregisterComponent(key) => {
-                match self.runtime.unregister_component(&key) {
+            ComponentRequest::RotateComponentKey {
+                key,
+                current_cipher,
+                cipher,
+            } => {
+                use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+                let current_cipher =
+                    XChaCha20Poly1305::new(GenericArray::from_slice(&current_cipher));
+                let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(&cipher));
+                let rotated = key.clone();
+                match self
+                    .runtime
+                    .run(move |runtime| {
+                        runtime.rotate_component_key(&rotated, current_cipher, cipher)
+                    })
+                    .await
+                {
                     Ok(_) => Ok(HostResponse::Ok),
+                    Err(err) => {
+                        tracing::error!("failed rotating the key of component `{key}`: {err}");
+                        Err(Either::Left(CoreComponentError::KeyRotation(key).into()))
+                    }
+                }
+            }
+            ComponentRequest::UnregisterComponent(key) => {
+                let unregistered = key.clone();
+                match self
+                    .runtime
+                    .run(move |runtime| runtime.unregister_component(&unregistered))
+                    .await
+                {
+                    Ok(_) => {
+                        let mut subscriptions = self.component_subscriptions.lock().unwrap();
+                        for subscribed in subscriptions.values_mut() {
+                            subscribed.remove(&key);
+                        }
+                        Ok(HostResponse::Ok)
+                    }
                     Err(err) => {
                         tracing::error!("failed unregistering component `{key}`: {err}");
                         Ok(HostResponse::Ok)
@@ -336,25 +752,382 @@ impl Executor {
                 }
             }
             ComponentRequest::ApplicationMessages { key, inbound } => {
-                match self.runtime.inbound_app_message(
-                    &key,
-                    inbound
-                        .into_iter()
-                        .map(InboundComponentMsg::into_owned)
-                        .collect(),
-                ) {
-                    Ok(values) => Ok(HostResponse::ComponentResponse { key, values }),
-                    Err(err) if err.is_component_exec_error() => {
-                        tracing::error!("failed processing messages for component `{key}`: {err}");
-                        Err(Either::Left(
-                            CoreComponentError::ExecutionError(format!("{err}")).into(),
-                        ))
+                let inbound = inbound
+                    .into_iter()
+                    .map(InboundComponentMsg::into_owned)
+                    .collect();
+                self.component_messages(id, key, inbound).await
+            }
+            ComponentRequest::UserInputResponse {
+                key,
+                request_id,
+                response,
+            } => {
+                let pending = (id, key.clone(), request_id);
+                if !self.pending_user_inputs.lock().unwrap().remove(&pending) {
+                    return Err(Either::Left(
+                        CoreComponentError::UnknownUserInput { key, request_id }.into(),
+                    ));
+                }
+                // the component is resumed with the context it left in the session
+                let inbound = vec![InboundComponentMsg::UserResponse(UserInputResponse {
+                    request_id,
+                    response: response.into_owned(),
+                    context: ComponentContext::default(),
+                })];
+                self.component_messages(id, key, inbound).await
+            }
+        }
+    }
+
+    async fn component_messages(
+        &mut self,
+        id: ClientId,
+        key: ComponentKey,
+        inbound: Vec<InboundComponentMsg<'static>>,
+    ) -> Response {
+        let result = self
+            .runtime
+            .inbound_app_message(key.clone(), SessionId(id.into()), inbound)
+            .await;
+        self.notify_pending_updates().await;
+        match result {
+            Ok(values) => {
+                let values = self.request_user_inputs(id, &key, values);
+                Ok(HostResponse::ComponentResponse { key, values })
+            }
+            Err(err) if err.is_component_exec_error() => {
+                tracing::error!("failed processing messages for component `{key}`: {err}");
+                Err(Either::Left(
+                    CoreComponentError::ExecutionError(format!("{err}")).into(),
+                ))
+            }
+            Err(err) => {
+                tracing::error!("failed executing component `{key}`: {err}");
+                Ok(HostResponse::Ok)
+            }
+        }
+    }
+
+    /// Sends the inputs requested by the component to the client, which can answer until
+    /// [`USER_INPUT_TIMEOUT`] expires. Returns the rest of the messages.
+    fn request_user_inputs(
+        &mut self,
+        id: ClientId,
+        key: &ComponentKey,
+        values: Vec<OutboundComponentMsg>,
+    ) -> Vec<OutboundComponentMsg> {
+        let mut remaining = Vec::with_capacity(values.len());
+        for msg in values {
+            let OutboundComponentMsg::RequestUserInput(request) = msg else {
+                remaining.push(msg);
+                continue;
+            };
+            let pending = (id, key.clone(), request.request_id);
+            self.pending_user_inputs
+                .lock()
+                .unwrap()
+                .insert(pending.clone());
+            let prompt = HostResponse::UserInputRequest {
+                key: key.clone(),
+                request,
+            };
+            if self.user_input_prompts.send((id, Ok(prompt))).is_err() {
+                tracing::warn!("no receiver for the user input requested by component `{key}`");
+            }
+
+            let pending_inputs = self.pending_user_inputs.clone();
+            let prompts = self.user_input_prompts.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(USER_INPUT_TIMEOUT).await;
+                if !pending_inputs.lock().unwrap().remove(&pending) {
+                    return;
+                }
+                let (client, key, request_id) = pending;
+                let err =
+                    RequestError::from(CoreComponentError::UserInputTimeout { key, request_id });
+                let _ = prompts.send((client, Err(ErrorKind::Other(format!("{err}")).into())));
+            });
+        }
+        remaining
+    }
+
+    /// Validates the state, fetching the related contracts requested by the contract for up to
+    /// [`MAX_RELATED_ROUNDS`].
+    async fn validate_state(
+        &self,
+        key: &ContractKey,
+        params: &Parameters<'static>,
+        state: &WrappedState,
+        mut related_contracts: RelatedContracts<'static>,
+    ) -> Result<bool, Either<RequestError, DynError>> {
+        for _ in 0..MAX_RELATED_ROUNDS {
+            self.fetch_related(&mut related_contracts).await?;
+            let result = self
+                .runtime
+                .validate_state(
+                    key.clone(),
+                    params.clone(),
+                    state.clone(),
+                    related_contracts.clone(),
+                )
+                .await
+                .map_err(Into::into)
+                .map_err(Either::Right)?;
+            match result {
+                ValidateResult::Valid => return Ok(true),
+                ValidateResult::Invalid => return Ok(false),
+                ValidateResult::RequestRelated(requested) => {
+                    if !related_contracts.missing(requested) {
+                        return Err(Either::Left(
+                            CoreContractError::Put {
+                                key: key.clone(),
+                                cause: "requested related contracts already provided".to_owned(),
+                            }
+                            .into(),
+                        ));
                     }
-                    Err(err) => {
-                        tracing::error!("failed executing component `{key}`: {err}");
-                        Ok(HostResponse::Ok)
+                }
+            }
+        }
+        Err(Either::Left(
+            CoreContractError::RelatedRounds {
+                key: key.clone(),
+                rounds: MAX_RELATED_ROUNDS,
+            }
+            .into(),
+        ))
+    }
+
+    /// Fills in the missing states of the related contracts.
+    async fn fetch_related(
+        &self,
+        related_contracts: &mut RelatedContracts<'static>,
+    ) -> Result<(), Either<RequestError, DynError>> {
+        for (id, related) in related_contracts.update() {
+            if related.is_none() {
+                let state = self.fetch_related_state(*id).await?;
+                *related = Some(State::from(state.as_ref().to_vec()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the state of a related contract from the local store or, in network mode,
+    /// the network, waiting for up to [`RELATED_FETCH_TIMEOUT`].
+    async fn fetch_related_state(
+        &self,
+        id: ContractInstanceId,
+    ) -> Result<WrappedState, Either<RequestError, DynError>> {
+        let state = match self.contract_state.get(&id.into()).await {
+            Ok(state) => Some(state),
+            Err(_) => match &self.network {
+                Some(network) if self.mode == OperationMode::Network => {
+                    match tokio::time::timeout(RELATED_FETCH_TIMEOUT, network.get_state(id)).await {
+                        Ok(state) => state.map_err(Either::Right)?,
+                        Err(_) => {
+                            tracing::warn!("timed out fetching related contract {id}");
+                            None
+                        }
                     }
                 }
+                _ => None,
+            },
+        };
+        state.ok_or_else(|| Either::Left(CoreContractError::MissingRelated { key: id }.into()))
+    }
+
+    /// Updates and stores the state of the contract, fetching the related contracts requested
+    /// by the contract for up to [`MAX_RELATED_ROUNDS`]. Returns the new state.
+    ///
+    /// If a summary is expected, the update is rejected if the current state has a different
+    /// summary.
+    async fn update_state(
+        &mut self,
+        key: &ContractKey,
+        params: &Parameters<'static>,
+        mut updates: Vec<UpdateData<'static>>,
+        expected_summary: Option<StateSummary<'static>>,
+    ) -> Result<WrappedState, Either<RequestError, DynError>> {
+        let update_err = |cause: String| {
+            Either::Left(
+                CoreContractError::Update {
+                    key: key.clone(),
+                    cause,
+                }
+                .into(),
+            )
+        };
+        let state = self
+            .contract_state
+            .get(key)
+            .await
+            .map_err(Into::into)
+            .map_err(Either::Right)?;
+        if let Some(expected) = expected_summary {
+            let summary = self
+                .runtime
+                .summarize_state(key.clone(), params.clone(), state.clone())
+                .await
+                .map_err(|err| Either::Right(err.into()))?;
+            if summary != expected {
+                return Err(Either::Left(
+                    CoreContractError::StateChanged {
+                        key: key.clone(),
+                        summary,
+                    }
+                    .into(),
+                ));
+            }
+        }
+        for _ in 0..MAX_RELATED_ROUNDS {
+            let modification = self
+                .runtime
+                .update_state(key.clone(), params.clone(), state.clone(), updates.clone())
+                .await
+                .map_err(|err| match err {
+                    err if err.is_contract_exec_error() => update_err(format!("{err}")),
+                    other => Either::Right(other.into()),
+                })?;
+            if let Some(new_state) = modification.new_state {
+                let new_state = WrappedState::new(new_state.into_bytes());
+                self.contract_state
+                    .store(key.clone(), new_state.clone(), None)
+                    .await
+                    .map_err(|err| Either::Right(err.into()))?;
+                return Ok(new_state);
+            }
+            if modification.related.is_empty() {
+                return Err(update_err(
+                    "neither a new state nor related contracts returned".to_owned(),
+                ));
+            }
+            let provided = updates
+                .iter()
+                .filter_map(|update| match update {
+                    UpdateData::RelatedState { related_to, .. } => Some(*related_to),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>();
+            let mut requested = false;
+            for RelatedContract {
+                contract_instance_id: id,
+                mode,
+            } in modification.related
+            {
+                if provided.contains(&id) {
+                    continue;
+                }
+                requested = true;
+                let related_state = self.fetch_related_state(id).await?;
+                if let RelatedMode::StateThenSubscribe = mode {
+                    self.subscribe_related(key, id, related_state.clone());
+                }
+                updates.push(UpdateData::RelatedState {
+                    related_to: id,
+                    state: State::from(related_state.as_ref().to_vec()),
+                });
+            }
+            if !requested {
+                return Err(update_err(
+                    "requested related contracts already provided".to_owned(),
+                ));
+            }
+        }
+        Err(Either::Left(
+            CoreContractError::RelatedRounds {
+                key: key.clone(),
+                rounds: MAX_RELATED_ROUNDS,
+            }
+            .into(),
+        ))
+    }
+
+    /// Subscribes the dependent contract to the changes of a related contract for
+    /// [`RELATED_SUBSCRIPTION_TIMEOUT`].
+    fn subscribe_related(
+        &mut self,
+        dependent: &ContractKey,
+        related: ContractInstanceId,
+        state: WrappedState,
+    ) {
+        let subscription = self
+            .related_subscriptions
+            .entry(related.into())
+            .or_insert_with(|| RelatedSubscription {
+                state: state.clone(),
+                dependents: HashMap::new(),
+            });
+        subscription.state = state;
+        subscription.dependents.insert(
+            dependent.clone(),
+            Instant::now() + RELATED_SUBSCRIPTION_TIMEOUT,
+        );
+    }
+
+    /// Updates the contracts subscribed to the changes of a related contract with the delta
+    /// of its new state. The updated contracts are pending to notify their subscribers.
+    async fn update_related_dependents(
+        &mut self,
+        key: &ContractKey,
+        params: &Parameters<'static>,
+        new_state: &WrappedState,
+    ) {
+        let Some(subscription) = self.related_subscriptions.get_mut(key) else {
+            return;
+        };
+        let now = Instant::now();
+        subscription.dependents.retain(|_, expires| *expires > now);
+        if subscription.dependents.is_empty() {
+            self.related_subscriptions.remove(key);
+            return;
+        }
+        let old_state = std::mem::replace(&mut subscription.state, new_state.clone());
+        let dependents = subscription.dependents.keys().cloned().collect::<Vec<_>>();
+
+        let delta = match self
+            .runtime
+            .summarize_state(key.clone(), params.clone(), old_state)
+            .await
+        {
+            Ok(summary) => {
+                self.runtime
+                    .get_state_delta(key.clone(), params.clone(), new_state.clone(), summary)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        let delta = match delta {
+            Ok(delta) => delta,
+            Err(err) => {
+                tracing::warn!("failed computing the delta of related contract {key}: {err}");
+                return;
+            }
+        };
+        for dependent in dependents {
+            let params = match self.contract_state.get_params(&dependent).await {
+                Ok(params) => params,
+                Err(err) => {
+                    tracing::warn!("failed loading contract {dependent}: {err}");
+                    continue;
+                }
+            };
+            let update = UpdateData::RelatedDelta {
+                related_to: *key.id(),
+                delta: delta.clone(),
+            };
+            match self
+                .update_state(&dependent, &params, vec![update], None)
+                .await
+            {
+                Ok(_) => {
+                    let mut updates = self.pending_updates.lock().unwrap();
+                    if !updates.contains(&dependent) {
+                        updates.push(dependent);
+                    }
+                }
+                Err(Either::Left(err)) => tracing::warn!("req error: {err}"),
+                Err(Either::Right(err)) => tracing::warn!("other error: {err}"),
             }
         }
     }
@@ -365,13 +1138,28 @@ impl Executor {
         params: &Parameters<'a>,
         new_state: &WrappedState,
     ) -> Result<(), Either<RequestError, DynError>> {
+        let mut closed = vec![];
         if let Some(notifiers) = self.update_notifications.get(key) {
-            let summaries = self.subscriber_summaries.get_mut(key).unwrap();
+            let summaries = self.subscriber_summaries.get(key);
             for (peer_key, notifier) in notifiers {
-                let peer_summary = summaries.get_mut(peer_key).unwrap();
+                if notifier.is_closed() {
+                    closed.push(*peer_key);
+                    continue;
+                }
+                let Some(peer_summary) = summaries.and_then(|summaries| summaries.get(peer_key))
+                else {
+                    tracing::warn!("missing the state summary of client {peer_key} for {key}");
+                    continue;
+                };
                 let update = self
                     .runtime
-                    .get_state_delta(key, params, new_state, &*peer_summary)
+                    .get_state_delta(
+                        key.clone(),
+                        params.clone().into_owned(),
+                        new_state.clone(),
+                        peer_summary.clone(),
+                    )
+                    .await
                     .map_err(|err| match err {
                         err if err.is_contract_exec_error() => Either::Left(
                             CoreContractError::Put {
@@ -382,18 +1170,116 @@ impl Executor {
                         ),
                         other => Either::Right(other.into()),
                     })?;
-                notifier
-                    .send(Ok(ContractResponse::UpdateNotification {
-                        key: key.clone(),
-                        update: update.to_owned().into(),
-                    }
-                    .into()))
-                    .unwrap();
+                let notification = ContractResponse::UpdateNotification {
+                    key: key.clone(),
+                    update: update.to_owned().into(),
+                };
+                if notifier.send(Ok(notification.into())).is_err() {
+                    closed.push(*peer_key);
+                }
+            }
+        }
+        for client in closed {
+            tracing::debug!("dropping the closed notification channel of {client} for {key}");
+            self.remove_contract_notifier(key, client);
+        }
+
+        let components = self
+            .component_subscriptions
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .unwrap_or_default();
+        for component in components {
+            let (notified, contract, state) = (component.clone(), key.clone(), new_state.clone());
+            match self
+                .runtime
+                .run(move |runtime| runtime.contract_notification(&notified, contract, state))
+                .await
+            {
+                Ok(values) if !values.is_empty() => tracing::debug!(
+                    "dropped {} messages of component `{component}` without a client to deliver them",
+                    values.len()
+                ),
+                Ok(_) => {}
+                Err(err) => tracing::warn!(
+                    "failed notifying component `{component}` of the update of contract {key}: {err}"
+                ),
             }
         }
+        self.update_related_dependents(key, &params.clone().into_owned(), new_state)
+            .await;
         Ok(())
     }
 
+    /// Notifies the subscribers of the contracts updated by components or by the changes of
+    /// their related contracts. Contracts updated in turn by those notifications are handled
+    /// for up to [`MAX_UPDATE_ROUNDS`].
+    async fn notify_pending_updates(&mut self) {
+        for _ in 0..MAX_UPDATE_ROUNDS {
+            let updates = std::mem::take(&mut *self.pending_updates.lock().unwrap());
+            if updates.is_empty() {
+                return;
+            }
+            for key in updates {
+                let (params, state) = match (
+                    self.contract_state.get_params(&key).await,
+                    self.contract_state.get(&key).await,
+                ) {
+                    (Ok(params), Ok(state)) => (params, state),
+                    (Err(err), _) | (_, Err(err)) => {
+                        tracing::warn!("failed loading updated contract {key}: {err}");
+                        continue;
+                    }
+                };
+                match self.send_update_notification(&key, &params, &state).await {
+                    Ok(_) => {}
+                    Err(Either::Left(err)) => tracing::warn!("req error: {err}"),
+                    Err(Either::Right(err)) => tracing::warn!("other error: {err}"),
+                }
+            }
+        }
+        let dropped = std::mem::take(&mut *self.pending_updates.lock().unwrap());
+        if !dropped.is_empty() {
+            tracing::warn!(
+                "dropped update notifications of {} contracts repeatedly updated",
+                dropped.len()
+            );
+        }
+    }
+
+    async fn perform_update(
+        &mut self,
+        key: ContractKey,
+        updates: Vec<UpdateData<'static>>,
+        expected_summary: Option<StateSummary<'static>>,
+    ) -> Response {
+        let parameters = {
+            self.contract_state
+                .get_params(&key)
+                .await
+                .map_err(|err| Either::Right(err.into()))?
+        };
+        let new_state = self
+            .update_state(&key, &parameters, updates, expected_summary)
+            .await?;
+        // in the network impl this would be sent over the network
+        let summary = self
+            .runtime
+            .summarize_state(key.clone(), parameters.clone(), new_state.clone())
+            .await
+            .map_err(Into::into)
+            .map_err(Either::Right)?;
+        self.send_update_notification(&key, &parameters, &new_state)
+            .await?;
+        self.notify_pending_updates().await;
+        // TODO: in network mode, wait at least for one confirmation
+        //       when a node receives a delta from updates, run the update themselves
+        //       and send back confirmation
+        Ok(ContractResponse::UpdateResponse { key, summary }.into())
+    }
+
     async fn perform_get(
         &mut self,
         contract: bool,
@@ -408,10 +1294,19 @@ impl Executor {
                     cause: "missing contract".to_owned(),
                 })
             })?;
+            let fetched = key.clone();
             let contract = self
                 .runtime
-                .contract_store
-                .fetch_contract(&key, &parameters)
+                .run(move |runtime| {
+                    Ok(runtime.contract_store.fetch_contract(&fetched, &parameters))
+                })
+                .await
+                .map_err(|err| {
+                    RequestError::from(CoreContractError::Get {
+                        key: key.clone(),
+                        cause: format!("{err}"),
+                    })
+                })?
                 .ok_or_else(|| {
                     RequestError::from(CoreContractError::Get {
                         key: key.clone(),
@@ -460,6 +1355,7 @@ mod test {
                 counter += 1;
             },
             OperationMode::Local,
+            RuntimeConfig::default(),
         )
         .await
         .expect("local node with handle");
@@ -467,4 +1363,570 @@ mod test {
         assert_eq!(counter, 1);
         Ok(())
     }
+
+    /// An executor whose stores hold the given contracts with their initial states.
+    async fn setup_executor(
+        name: &str,
+        contracts: Vec<(ContractContainer, WrappedState)>,
+        mode: OperationMode,
+        config: RuntimeConfig,
+    ) -> Result<Executor, Box<dyn std::error::Error>> {
+        const MAX_SIZE: i64 = 10 * 1024 * 1024;
+        const MAX_MEM_CACHE: u32 = 10_000_000;
+        let tmp_path = std::env::temp_dir().join("locutus-test");
+        let mut contract_store = ContractStore::new(tmp_path.join(name), MAX_SIZE)?;
+        let mut state_store = StateStore::new(Storage::new().await?, MAX_MEM_CACHE).unwrap();
+        for (contract, state) in contracts {
+            let key = contract.key();
+            let params = contract.params();
+            contract_store.store_contract(contract)?;
+            state_store.store(key, state, Some(params)).await?;
+        }
+        let executor = Executor::new(contract_store, state_store, || {}, mode, config)
+            .await
+            .expect("local node");
+        Ok(executor)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prompt_user_input() -> Result<(), Box<dyn std::error::Error>> {
+        let mut executor = setup_executor(
+            "executor-user-input",
+            vec![],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+        let mut prompts = executor.user_input_prompts().unwrap();
+
+        let key = ComponentKey::new(&[0, 1, 2]);
+        let client = ClientId::new(7);
+        let request = UserInputRequest {
+            request_id: 1,
+            message: NotificationMessage::try_from(&serde_json::json!({"question": "allow?"}))
+                .unwrap(),
+            responses: vec![ClientResponse::new(b"yes".to_vec())],
+        };
+        let values = executor.request_user_inputs(
+            client,
+            &key,
+            vec![OutboundComponentMsg::RequestUserInput(request)],
+        );
+        assert!(values.is_empty());
+        let (prompted, prompt) = prompts.recv().await.unwrap();
+        assert_eq!(prompted, client);
+        assert!(matches!(
+            prompt,
+            Ok(HostResponse::UserInputRequest { request, .. }) if request.request_id == 1
+        ));
+        assert!(executor
+            .pending_user_inputs
+            .lock()
+            .unwrap()
+            .contains(&(client, key.clone(), 1)));
+
+        let unknown = executor
+            .handle_request(
+                client,
+                ComponentRequest::UserInputResponse {
+                    key,
+                    request_id: 2,
+                    response: ClientResponse::new(b"yes".to_vec()),
+                }
+                .into(),
+                None,
+            )
+            .await;
+        assert!(matches!(
+            unknown,
+            Err(Either::Left(RequestError::ComponentError(
+                CoreComponentError::UnknownUserInput { request_id: 2, .. }
+            )))
+        ));
+        Ok(())
+    }
+
+    struct StubNetwork(ContractInstanceId);
+
+    #[async_trait::async_trait]
+    impl NetworkContracts for StubNetwork {
+        async fn get_state(
+            &self,
+            id: ContractInstanceId,
+        ) -> Result<Option<WrappedState>, DynError> {
+            Ok((id == self.0).then(|| WrappedState::new(vec![4, 5, 6])))
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetch_related_contracts() -> Result<(), Box<dyn std::error::Error>> {
+        let mut executor = setup_executor(
+            "executor-related",
+            vec![],
+            OperationMode::Network,
+            RuntimeConfig::default(),
+        )
+        .await?;
+        let contract_id = |seed: u8| {
+            let code = ContractCode::from(rand::random::<[u8; 32]>().to_vec());
+            ContractInstanceId::from((Parameters::from(vec![seed]), code))
+        };
+        let (local, remote) = (contract_id(0), contract_id(1));
+        executor
+            .contract_state
+            .store(local.into(), WrappedState::new(vec![1, 2, 3]), None)
+            .await?;
+
+        let mut related = RelatedContracts::new();
+        assert!(related.missing(vec![local, remote]));
+        assert!(!related.missing(vec![local]));
+        assert!(matches!(
+            executor.fetch_related(&mut related).await,
+            Err(Either::Left(RequestError::ContractError(
+                CoreContractError::MissingRelated { key }
+            ))) if key == remote
+        ));
+
+        executor.set_network(StubNetwork(remote));
+        executor.fetch_related(&mut related).await.expect("related");
+        let states = related.states().collect::<HashMap<_, _>>();
+        assert_eq!(
+            states[&local].as_ref().map(|s| s.to_vec()),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(
+            states[&remote].as_ref().map(|s| s.to_vec()),
+            Some(vec![4, 5, 6])
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn related_subscriptions_expire() -> Result<(), Box<dyn std::error::Error>> {
+        let mut executor = setup_executor(
+            "executor-related-subs",
+            vec![],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+
+        let contract_key = |seed: u8| {
+            let code = ContractCode::from(rand::random::<[u8; 32]>().to_vec());
+            ContractKey::from((Parameters::from(vec![seed]), code))
+        };
+        let (dependent, related) = (contract_key(0), contract_key(1));
+        executor.subscribe_related(&dependent, *related.id(), WrappedState::new(vec![1]));
+        executor.subscribe_related(&dependent, *related.id(), WrappedState::new(vec![2]));
+        let subscription = &executor.related_subscriptions[&related];
+        assert_eq!(subscription.state.as_ref(), &[2]);
+        assert_eq!(subscription.dependents.len(), 1);
+        assert!(subscription.dependents[&dependent] > Instant::now());
+
+        executor
+            .related_subscriptions
+            .get_mut(&related)
+            .unwrap()
+            .dependents
+            .insert(dependent, Instant::now());
+        executor
+            .update_related_dependents(
+                &related,
+                &Parameters::from(vec![1]),
+                &WrappedState::new(vec![3]),
+            )
+            .await;
+        assert!(executor.related_subscriptions.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn drop_contract_notifiers() -> Result<(), Box<dyn std::error::Error>> {
+        let mut executor = setup_executor(
+            "executor-notifiers",
+            vec![],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+
+        let key = ContractKey::from(ContractInstanceId::from((
+            Parameters::from(vec![0]),
+            ContractCode::from(vec![1, 2, 3]),
+        )));
+        let mut receivers = vec![];
+        for client in 0..3 {
+            let (notifier, receiver) = mpsc::unbounded_channel();
+            executor
+                .register_contract_notifier(
+                    key.clone(),
+                    ClientId::new(client),
+                    notifier,
+                    StateSummary::from(vec![]),
+                )
+                .unwrap();
+            receivers.push(receiver);
+        }
+
+        let unsubscribed = executor
+            .handle_request(
+                ClientId::new(0),
+                ContractRequest::Unsubscribe { key: key.clone() }.into(),
+                None,
+            )
+            .await;
+        assert!(matches!(unsubscribed, Ok(HostResponse::Ok)));
+        let disconnected = executor
+            .handle_request(
+                ClientId::new(1),
+                ClientRequest::Disconnect { cause: None },
+                None,
+            )
+            .await;
+        assert!(matches!(
+            disconnected,
+            Err(Either::Left(RequestError::Disconnect))
+        ));
+        let subscribed = executor.update_notifications[&key]
+            .iter()
+            .map(|(client, _)| *client)
+            .collect::<Vec<_>>();
+        assert_eq!(subscribed, vec![ClientId::new(2)]);
+
+        receivers.clear();
+        executor
+            .send_update_notification(&key, &Parameters::from(vec![0]), &WrappedState::new(vec![]))
+            .await
+            .map_err(|_| "failed sending notifications")?;
+        assert!(executor.update_notifications.is_empty());
+        assert!(executor.subscriber_summaries.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn export_node_secrets() -> Result<(), Box<dyn std::error::Error>> {
+        use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+        let executor = setup_executor(
+            "executor-secrets",
+            vec![],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+
+        let component = Component::from(rand::random::<[u8; 32]>().to_vec());
+        let key = component.key().clone();
+        let cipher = XChaCha20Poly1305::new(&rand::random::<[u8; 32]>().into());
+        let secret_id = SecretsId::new(vec![1, 2, 3]);
+        {
+            let (key, cipher, secret_id) = (key.clone(), cipher.clone(), secret_id.clone());
+            executor
+                .runtime()
+                .run(move |rt| {
+                    rt.secret_store.register_component(key.clone(), cipher)?;
+                    rt.secret_store
+                        .store_secret(&key, &secret_id, vec![4, 5, 6])
+                })
+                .await?;
+        }
+
+        // `ldt secrets` reads the secrets from the same directory as the node
+        let node_secrets =
+            SecretsStore::new(crate::config::CONFIG.config_paths.secrets_dir().to_owned())?;
+        let archive = node_secrets.export_secrets(&key, b"passphrase")?;
+        let tmp_path = std::env::temp_dir().join("locutus-test");
+        let mut imported = SecretsStore::new(tmp_path.join("executor-secrets-import"))?;
+        assert_eq!(imported.import_secrets(&archive, b"passphrase")?, key);
+        imported.register_component(key.clone(), cipher)?;
+        assert_eq!(imported.get_secret(&key, &secret_id)?, vec![4, 5, 6]);
+
+        executor
+            .runtime()
+            .run(move |rt| Ok(rt.secret_store.remove_component(&key)?))
+            .await?;
+        Ok(())
+    }
+
+    /// A contract which answers any delta request with the `b"delta"` delta.
+    fn delta_contract() -> ContractContainer {
+        let delta = bincode::serialize(&Ok::<_, locutus_stdlib::prelude::ContractError>(
+            StateDelta::from(b"delta".to_vec()),
+        ))
+        .unwrap();
+        let bytes = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("\\{b:02x}"))
+                .collect::<String>()
+        };
+        // ContractInterfaceResult { ptr: 2048, kind: StateDelta, size }
+        let mut result = 2048i64.to_le_bytes().to_vec();
+        result.extend(4i32.to_le_bytes());
+        result.extend((delta.len() as u32).to_le_bytes());
+        let wat = format!(
+            r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 1024) "{}")
+  (data (i32.const 2048) "{}")
+  (func (export "__locutus_set_id") (param i64))
+  ;; BufferBuilder {{ start: 32768, capacity: len, last_read: 544, last_write: 548 }}
+  (func (export "initiate_buffer") (param $len i32) (result i64)
+    (i64.store (i32.const 512) (i64.const 32768))
+    (i32.store (i32.const 520) (local.get $len))
+    (i64.store (i32.const 528) (i64.const 544))
+    (i64.store (i32.const 536) (i64.const 548))
+    (i64.store (i32.const 544) (i64.const 0))
+    i64.const 512)
+  (func (export "get_state_delta") (param i64 i64 i64) (result i64)
+    i64.const 1024))
+"#,
+            bytes(&result),
+            bytes(&delta)
+        );
+        let code = wasmer::wat2wasm(wat.as_bytes()).unwrap().into_owned();
+        ContractContainer::Wasm(WasmAPIVersion::V1(WrappedContract::new(
+            Arc::new(ContractCode::from(code)),
+            Parameters::from(vec![]),
+        )))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn notify_component_updates() -> Result<(), Box<dyn std::error::Error>> {
+        let contract = delta_contract();
+        let key = contract.key();
+        let mut executor = setup_executor(
+            "executor-component-updates",
+            vec![(contract, WrappedState::new(vec![1]))],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+        let (notifier, mut notifications) = mpsc::unbounded_channel();
+        executor
+            .register_contract_notifier(
+                key.clone(),
+                ClientId::new(0),
+                notifier,
+                StateSummary::from(vec![]),
+            )
+            .unwrap();
+
+        // the access of the components to the contracts, as set by the executor
+        let mut contracts = ComponentContracts {
+            state: executor.contract_state.clone(),
+            handle: tokio::runtime::Handle::current(),
+            subscriptions: executor.component_subscriptions.clone(),
+            updates: executor.pending_updates.clone(),
+        };
+        let updated = key.clone();
+        tokio::task::spawn_blocking(move || contracts.store(&updated, WrappedState::new(vec![2])))
+            .await?
+            .unwrap();
+        assert_eq!(*executor.pending_updates.lock().unwrap(), vec![key.clone()]);
+
+        executor.notify_pending_updates().await;
+        assert!(executor.pending_updates.lock().unwrap().is_empty());
+        let notification = notifications.try_recv()?;
+        assert!(matches!(
+            notification,
+            Ok(HostResponse::ContractResponse(ContractResponse::UpdateNotification {
+                key: notified,
+                update: UpdateData::Delta(delta),
+            })) if notified == key && delta.as_ref() == b"delta"
+        ));
+        Ok(())
+    }
+
+    /// A contract which updates its state to the number of updates received, failing on more
+    /// than two updates.
+    fn counting_contract(params: Parameters<'static>) -> ContractContainer {
+        let bytes = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("\\{b:02x}"))
+                .collect::<String>()
+        };
+        let results = [
+            (
+                2i32,
+                bincode::serialize(&Ok::<_, locutus_stdlib::prelude::ContractError>(
+                    UpdateModification::valid(State::from(vec![1])),
+                ))
+                .unwrap(),
+            ),
+            (
+                2i32,
+                bincode::serialize(&Ok::<_, locutus_stdlib::prelude::ContractError>(
+                    UpdateModification::valid(State::from(vec![2])),
+                ))
+                .unwrap(),
+            ),
+            (
+                2i32,
+                bincode::serialize(&Err::<UpdateModification, _>(
+                    locutus_stdlib::prelude::ContractError::InvalidUpdate,
+                ))
+                .unwrap(),
+            ),
+            (
+                3i32,
+                bincode::serialize(&Ok::<_, locutus_stdlib::prelude::ContractError>(
+                    StateSummary::from(vec![]),
+                ))
+                .unwrap(),
+            ),
+        ];
+        let mut data = String::new();
+        for (i, (kind, value)) in results.iter().enumerate() {
+            // ContractInterfaceResult { ptr, kind, size }
+            let value_ptr = 2048 + 256 * i as i64;
+            let mut result = value_ptr.to_le_bytes().to_vec();
+            result.extend(kind.to_le_bytes());
+            result.extend((value.len() as u32).to_le_bytes());
+            data += &format!(
+                "  (data (i32.const {}) \"{}\")\n  (data (i32.const {value_ptr}) \"{}\")\n",
+                1024 + 16 * i,
+                bytes(&result),
+                bytes(value)
+            );
+        }
+        // the updates are the last buffer written, starting with the number of updates
+        let wat = format!(
+            r#"
+(module
+  (memory (export "memory") 1)
+{data}
+  (func (export "__locutus_set_id") (param i64))
+  ;; BufferBuilder {{ start: 32768, capacity: len, last_read: 544, last_write: 548 }}
+  (func (export "initiate_buffer") (param $len i32) (result i64)
+    (i64.store (i32.const 512) (i64.const 32768))
+    (i32.store (i32.const 520) (local.get $len))
+    (i64.store (i32.const 528) (i64.const 544))
+    (i64.store (i32.const 536) (i64.const 548))
+    (i64.store (i32.const 544) (i64.const 0))
+    i64.const 512)
+  (func (export "update_state") (param i64 i64 i64) (result i64)
+    (select
+      (i64.add
+        (i64.const 1008)
+        (i64.mul (i64.load (i32.const 32768)) (i64.const 16)))
+      (i64.const 1056)
+      (i64.le_u (i64.load (i32.const 32768)) (i64.const 2))))
+  (func (export "summarize_state") (param i64 i64) (result i64)
+    i64.const 1072))
+"#
+        );
+        let code = wasmer::wat2wasm(wat.as_bytes()).unwrap().into_owned();
+        ContractContainer::Wasm(WasmAPIVersion::V1(WrappedContract::new(
+            Arc::new(ContractCode::from(code)),
+            params,
+        )))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn coalesce_updates() -> Result<(), Box<dyn std::error::Error>> {
+        let contract = counting_contract(Parameters::from(vec![0]));
+        let key = contract.key();
+        let mut executor = setup_executor(
+            "executor-coalesce-updates",
+            vec![(contract, WrappedState::new(vec![0]))],
+            OperationMode::Local,
+            RuntimeConfig::default(),
+        )
+        .await?;
+
+        let update = |client: usize| OpenRequest {
+            id: ClientId::new(client),
+            request: ContractRequest::Update {
+                key: key.clone(),
+                data: UpdateData::Delta(StateDelta::from(vec![])),
+                expected_summary: None,
+            }
+            .into(),
+            notification_channel: None,
+        };
+        let get = |client: usize| OpenRequest {
+            id: ClientId::new(client),
+            request: ContractRequest::Get {
+                key: key.clone(),
+                fetch_contract: false,
+            }
+            .into(),
+            notification_channel: None,
+        };
+        let is_updated = |res: &Response| {
+            matches!(
+                res,
+                Ok(HostResponse::ContractResponse(ContractResponse::UpdateResponse { key: updated, .. }))
+                    if updated == &key
+            )
+        };
+
+        // the updates of different clients are merged
+        let responses = executor.handle_requests(vec![update(0), update(1)]).await;
+        assert_eq!(responses.len(), 2);
+        assert!(
+            responses.iter().all(|(_, res)| is_updated(res)),
+            "{responses:?}"
+        );
+        assert_eq!(executor.contract_state.get(&key).await?.as_ref(), &[2]);
+
+        // until another request concerns the contract
+        let responses = executor
+            .handle_requests(vec![update(0), get(1), update(2)])
+            .await;
+        let clients = responses.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(clients, [0, 1, 2].map(ClientId::new));
+        assert!(
+            is_updated(&responses[0].1) && is_updated(&responses[2].1),
+            "{responses:?}"
+        );
+        assert_eq!(executor.contract_state.get(&key).await?.as_ref(), &[1]);
+
+        // failed merged updates are retried one by one
+        let responses = executor
+            .handle_requests(vec![update(0), update(1), update(2)])
+            .await;
+        assert!(responses.iter().all(|(_, res)| is_updated(res)));
+        assert_eq!(executor.contract_state.get(&key).await?.as_ref(), &[1]);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn host_inputs_call_time() -> Result<(), Box<dyn std::error::Error>> {
+        let contract = counting_contract(Parameters::from(vec![1]));
+        let key = contract.key();
+        let mut executor = setup_executor(
+            "executor-host-inputs",
+            vec![(contract, WrappedState::new(vec![0]))],
+            OperationMode::Local,
+            RuntimeConfig {
+                determinism: Determinism::HostInputs,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // contracts can't be called without a fixed time
+        let res = executor
+            .handle_request(
+                ClientId::new(0),
+                ContractRequest::Update {
+                    key: key.clone(),
+                    data: UpdateData::Delta(StateDelta::from(vec![])),
+                    expected_summary: None,
+                }
+                .into(),
+                None,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Ok(HostResponse::ContractResponse(
+                ContractResponse::UpdateResponse { .. }
+            ))
+        ));
+        Ok(())
+    }
 }
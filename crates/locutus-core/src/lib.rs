@@ -16,14 +16,14 @@ type DynError = Box<dyn std::error::Error + Send + Sync + 'static>;
 // exports:
 pub use crate::config::Config;
 #[cfg(feature = "websocket")]
-pub use client_events::websocket::WebSocketProxy;
+pub use client_events::websocket::{EncodingProtocol, WebSocketProxy};
 pub use client_events::{
     combinator::ClientEventsCombinator, BoxedClient, ClientEventsProxy, ClientId, HostResult,
     OpenRequest, RequestError,
 };
 pub use contract::storages::{Storage, StorageContractHandler};
 pub use either;
-pub use executor::{Executor, OperationMode};
+pub use executor::{Executor, NetworkContracts, OperationMode};
 pub use libp2p;
 pub use locutus_runtime;
 pub use node::PeerKey;
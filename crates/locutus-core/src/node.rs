@@ -15,7 +15,7 @@ use libp2p::{
     multiaddr::Protocol,
     Multiaddr, PeerId,
 };
-use locutus_stdlib::client_api::{ClientRequest, ContractRequest};
+use locutus_stdlib::client_api::{ClientRequest, ContractRequest, ErrorKind};
 
 #[cfg(test)]
 use self::in_memory_impl::NodeInMemory;
@@ -299,15 +299,20 @@ async fn client_event_handling<ClientEv, CErr>(
 {
     loop {
         // fixme: send back responses to client
-        let OpenRequest {
-            id: _id, request, ..
-        } = client_events.recv().await.unwrap(); // fixme: deal with this unwrap
+        let OpenRequest { id, request, .. } = client_events.recv().await.unwrap(); // fixme: deal with this unwrap
         if let ClientRequest::Disconnect { .. } = request {
             if let Err(err) = op_storage.notify_internal_op(NodeEvent::ShutdownNode).await {
                 tracing::error!("{}", err);
             }
             break;
         }
+        if let ClientRequest::ContractOp(ContractRequest::UpdateBatch { .. }) = request {
+            let err = ErrorKind::Other("batched updates are not supported in network mode".into());
+            if let Err(err) = client_events.send(id, Err(err.into())).await {
+                tracing::error!("{}", err);
+            }
+            continue;
+        }
 
         let op_storage_cp = op_storage.clone();
         GlobalExecutor::spawn(async move {
@@ -337,9 +342,13 @@ async fn client_event_handling<ClientEv, CErr>(
                     ContractRequest::Update {
                         key: _key,
                         data: _delta,
+                        ..
                     } => {
                         todo!()
                     }
+                    ContractRequest::UpdateBatch { .. } => {
+                        // answered as unsupported before spawning
+                    }
                     ContractRequest::Get {
                         key,
                         fetch_contract: contract,
@@ -355,6 +364,7 @@ async fn client_event_handling<ClientEv, CErr>(
                         }
                     }
                     ContractRequest::Subscribe { key, .. } => {
+                        op_storage_cp.ring.add_local_subscriber(key.clone(), id);
                         // Initialize a subscribe op.
                         loop {
                             // FIXME: this will block the event loop until the subscribe op succeeds
@@ -385,6 +395,16 @@ async fn client_event_handling<ClientEv, CErr>(
                         }
                         todo!()
                     }
+                    ContractRequest::Unsubscribe { key } => {
+                        // only stop receiving updates once no local client is subscribed
+                        if op_storage_cp.ring.remove_local_subscriber(&key, id) {
+                            if let Err(err) =
+                                subscribe::request_unsubscribe(&op_storage_cp, key).await
+                            {
+                                tracing::error!("{}", err);
+                            }
+                        }
+                    }
                 },
                 ClientRequest::ComponentOp(_op) => todo!("FIXME: component op"),
                 ClientRequest::GenerateRandData { .. } => todo!("FIXME"),
@@ -239,7 +239,7 @@ where
                         key,
                         sender.peer
                     );
-                    op_storage.ring.add_subscription(key);
+                    op_storage.ring.add_subscription(key, sender);
 
                     match self.state {
                         Some(SubscribeState::AwaitingResponse { .. }) => {
@@ -249,6 +249,31 @@ where
                         _ => return Err(OpError::InvalidStateTransition(self.id)),
                     }
                 }
+                SubscribeMsg::Unsubscribe {
+                    id,
+                    key,
+                    subscriber,
+                    target,
+                } => {
+                    if target.peer == op_storage.ring.peer_key {
+                        tracing::info!(
+                            "Peer {} unsubscribed from contract {}",
+                            subscriber.peer,
+                            key
+                        );
+                        op_storage.ring.remove_subscriber(&key, &subscriber.peer);
+                        return_msg = None;
+                    } else {
+                        // fast tracked from the request_unsubscribe func
+                        return_msg = Some(SubscribeMsg::Unsubscribe {
+                            id,
+                            key,
+                            subscriber,
+                            target,
+                        });
+                    }
+                    new_state = None;
+                }
                 _ => return Err(OpError::UnexpectedOpState),
             }
 
@@ -349,6 +374,39 @@ where
     Ok(())
 }
 
+/// Request to stop receiving the value changes of a contract from the peer providing them.
+pub(crate) async fn request_unsubscribe<CErr>(
+    op_storage: &OpManager<CErr>,
+    key: ContractKey,
+) -> Result<(), OpError<CErr>>
+where
+    CErr: std::error::Error,
+{
+    let Some(target) = op_storage.ring.remove_subscription(&key) else {
+        tracing::debug!("not subscribed to contract {key}");
+        return Ok(());
+    };
+    let id = Transaction::new(
+        <SubscribeMsg as TxType>::tx_type_id(),
+        &op_storage.ring.peer_key,
+    );
+    let msg = SubscribeMsg::Unsubscribe {
+        id,
+        key,
+        subscriber: op_storage.ring.own_location(),
+        target,
+    };
+    let op = SubscribeOp {
+        id,
+        state: None,
+        _ttl: PEER_TIMEOUT,
+    };
+    op_storage
+        .notify_op_change(msg.into(), OpEnum::Subscribe(op))
+        .await?;
+    Ok(())
+}
+
 mod messages {
     use crate::message::InnerMessage;
     use std::fmt::Display;
@@ -381,6 +439,13 @@ mod messages {
             target: PeerKeyLocation,
             subscribed: bool,
         },
+        /// The subscriber stops receiving the value changes of the contract from the target.
+        Unsubscribe {
+            id: Transaction,
+            key: ContractKey,
+            subscriber: PeerKeyLocation,
+            target: PeerKeyLocation,
+        },
     }
 
     impl InnerMessage for SubscribeMsg {
@@ -390,6 +455,7 @@ mod messages {
                 Self::FetchRouting { id, .. } => id,
                 Self::RequestSub { id, .. } => id,
                 Self::ReturnSub { id, .. } => id,
+                Self::Unsubscribe { id, .. } => id,
             }
         }
     }
@@ -401,12 +467,14 @@ mod messages {
                 Self::FetchRouting { id, .. } => id,
                 Self::RequestSub { id, .. } => id,
                 Self::ReturnSub { id, .. } => id,
+                Self::Unsubscribe { id, .. } => id,
             }
         }
 
         pub fn sender(&self) -> Option<&PeerKeyLocation> {
             match self {
                 Self::ReturnSub { sender, .. } => Some(sender),
+                Self::Unsubscribe { subscriber, .. } => Some(subscriber),
                 _ => None,
             }
         }
@@ -415,13 +483,17 @@ mod messages {
             match self {
                 Self::SeekNode { target, .. } => Some(target),
                 Self::ReturnSub { target, .. } => Some(target),
+                Self::Unsubscribe { target, .. } => Some(target),
                 _ => None,
             }
         }
 
         pub fn terminal(&self) -> bool {
             use SubscribeMsg::*;
-            matches!(self, ReturnSub { .. } | SeekNode { .. })
+            matches!(
+                self,
+                ReturnSub { .. } | SeekNode { .. } | Unsubscribe { .. }
+            )
         }
     }
 
@@ -433,6 +505,7 @@ mod messages {
                 Self::FetchRouting { .. } => write!(f, "FetchRouting(id: {id})"),
                 Self::RequestSub { .. } => write!(f, "RequestSub(id: {id})"),
                 Self::ReturnSub { .. } => write!(f, "ReturnSub(id: {id})"),
+                Self::Unsubscribe { .. } => write!(f, "Unsubscribe(id: {id})"),
             }
         }
     }
@@ -29,6 +29,7 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    client_events::ClientId,
     node::{self, PeerKey},
     NodeConfig,
 };
@@ -70,7 +71,10 @@ pub(crate) struct Ring {
     /// of subscribers more often than inserting, and anyways is a relatively short sequence
     /// then is more optimal to just use a vector for it's compact memory layout.
     subscribers: Arc<DashMap<ContractKey, Vec<PeerKeyLocation>>>,
-    subscriptions: Arc<RwLock<Vec<ContractKey>>>,
+    /// contracts this node is subscribed to and the peer providing their updates
+    subscriptions: Arc<RwLock<Vec<(ContractKey, PeerKeyLocation)>>>,
+    /// local clients subscribed to a contract through this node
+    local_subscribers: Arc<DashMap<ContractKey, Vec<ClientId>>>,
 
     // A peer which has been blacklisted to perform actions regarding a given contract.
     // todo: add blacklist
@@ -149,6 +153,7 @@ impl Ring {
             peer_key,
             subscribers: Arc::new(DashMap::new()),
             subscriptions: Arc::new(RwLock::new(Vec::new())),
+            local_subscribers: Arc::new(DashMap::new()),
             // contract_blacklist: Arc::new(DashMap::new()),
             open_connections: Arc::new(AtomicUsize::new(0)),
         };
@@ -348,9 +353,49 @@ impl Ring {
         Ok(())
     }
 
-    /// Add a new subscription for this peer.
-    pub fn add_subscription(&self, contract: ContractKey) {
-        self.subscriptions.write().push(contract);
+    /// Remove a subscriber of the contract, if present.
+    pub fn remove_subscriber(&self, contract: &ContractKey, subscriber: &PeerKey) {
+        if let Some(mut subs) = self.subscribers.get_mut(contract) {
+            subs.retain(|sub| &sub.peer != subscriber);
+        }
+        self.subscribers
+            .remove_if(contract, |_, subs| subs.is_empty());
+    }
+
+    /// Add a new subscription for this peer, with the updates provided by `provider`.
+    pub fn add_subscription(&self, contract: ContractKey, provider: PeerKeyLocation) {
+        self.subscriptions.write().push((contract, provider));
+    }
+
+    /// Remove the subscription of this peer to the contract, returning the peer which was
+    /// providing the updates.
+    pub fn remove_subscription(&self, contract: &ContractKey) -> Option<PeerKeyLocation> {
+        let subscriptions = &mut *self.subscriptions.write();
+        let pos = subscriptions.iter().position(|(key, _)| key == contract)?;
+        Some(subscriptions.swap_remove(pos).1)
+    }
+
+    /// Add a local client subscribed to the contract.
+    pub fn add_local_subscriber(&self, contract: ContractKey, client: ClientId) {
+        let mut subs = self.local_subscribers.entry(contract).or_default();
+        if !subs.contains(&client) {
+            subs.push(client);
+        }
+    }
+
+    /// Remove a local client subscribed to the contract, returning whether it was the last one.
+    pub fn remove_local_subscriber(&self, contract: &ContractKey, client: ClientId) -> bool {
+        let Some(mut subs) = self.local_subscribers.get_mut(contract) else {
+            return false;
+        };
+        let Some(pos) = subs.iter().position(|sub| sub == &client) else {
+            return false;
+        };
+        subs.swap_remove(pos);
+        drop(subs);
+        self.local_subscribers
+            .remove_if(contract, |_, subs| subs.is_empty())
+            .is_some()
     }
 
     pub fn subscribers_of(
@@ -485,6 +530,7 @@ pub(crate) enum RingError {
 mod test {
     use super::*;
     use crate::client_events::test::MemoryEventsGen;
+    use locutus_runtime::prelude::{ContractCode, Parameters};
     use tokio::sync::watch::channel;
 
     #[ignore]
@@ -556,4 +602,23 @@ mod test {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn last_local_subscriber() {
+        let peer_key: PeerKey = PeerKey::random();
+        let (_, receiver) = channel((0, peer_key));
+        let user_events = MemoryEventsGen::new(receiver, peer_key);
+        let config = NodeConfig::new([Box::new(user_events)]);
+        let ring = Ring::new(&config, &[]).unwrap();
+
+        let key = ContractKey::from((Parameters::from(vec![]), ContractCode::from(vec![0, 1, 2])));
+        ring.add_local_subscriber(key.clone(), ClientId::new(1));
+        ring.add_local_subscriber(key.clone(), ClientId::new(1));
+        ring.add_local_subscriber(key.clone(), ClientId::new(2));
+
+        assert!(!ring.remove_local_subscriber(&key, ClientId::new(3)));
+        assert!(!ring.remove_local_subscriber(&key, ClientId::new(1)));
+        assert!(ring.remove_local_subscriber(&key, ClientId::new(2)));
+        assert!(!ring.remove_local_subscriber(&key, ClientId::new(2)));
+    }
 }
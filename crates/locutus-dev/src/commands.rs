@@ -4,12 +4,13 @@ use locutus_core::{
     locutus_runtime::StateDelta, ClientId, Config, Executor, OperationMode, Storage,
 };
 use locutus_runtime::{
-    ContractContainer, ContractInstanceId, ContractStore, Parameters, StateStore,
+    ComponentKey, ContractContainer, ContractInstanceId, ContractStore, Parameters, RuntimeConfig,
+    SecretsStore, StateStore,
 };
 use locutus_stdlib::client_api::{ClientRequest, ContractRequest};
 
 use crate::{
-    config::{BaseConfig, PutConfig, UpdateConfig},
+    config::{BaseConfig, PutConfig, SecretsCliConfig, SecretsCommand, UpdateConfig},
     DynError,
 };
 
@@ -61,10 +62,39 @@ pub async fn update(config: UpdateConfig, other: BaseConfig) -> Result<(), DynEr
         File::open(&config.delta)?.read_to_end(&mut buf)?;
         StateDelta::from(buf).into()
     };
-    let request = ContractRequest::Update { key, data }.into();
+    let request = ContractRequest::Update {
+        key,
+        data,
+        expected_summary: None,
+    }
+    .into();
     execute_command(request, other).await
 }
 
+pub fn secrets(config: SecretsCliConfig) -> Result<(), DynError> {
+    let secrets_dir = config
+        .secrets_dir
+        .unwrap_or_else(|| Config::get_conf().config_paths.secrets_dir().to_owned());
+    let mut store = SecretsStore::new(secrets_dir)?;
+    match config.command {
+        SecretsCommand::Export(export) => {
+            let component = ComponentKey::decode(export.component)?;
+            let archive = store.export_secrets(&component, config.passphrase.as_bytes())?;
+            std::fs::write(&export.output, archive)?;
+            println!(
+                "Exported secrets of component {component} to {}",
+                export.output.display()
+            );
+        }
+        SecretsCommand::Import(import) => {
+            let archive = std::fs::read(&import.archive)?;
+            let component = store.import_secrets(&archive, config.passphrase.as_bytes())?;
+            println!("Imported secrets of component {component}");
+        }
+    }
+    Ok(())
+}
+
 async fn execute_command(
     request: ClientRequest<'static>,
     other: BaseConfig,
@@ -74,8 +104,14 @@ async fn execute_command(
         .unwrap_or_else(|| Config::get_conf().config_paths.local_contracts_dir());
     let contract_store = ContractStore::new(data_path, DEFAULT_MAX_CONTRACT_SIZE)?;
     let state_store = StateStore::new(Storage::new().await?, MAX_MEM_CACHE).unwrap();
-    let mut executor =
-        Executor::new(contract_store, state_store, || {}, OperationMode::Local).await?;
+    let mut executor = Executor::new(
+        contract_store,
+        state_store,
+        || {},
+        OperationMode::Local,
+        RuntimeConfig::default(),
+    )
+    .await?;
 
     executor
         .handle_request(ClientId::new(0), request, None)
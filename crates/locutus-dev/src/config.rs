@@ -31,6 +31,7 @@ pub enum SubCommand {
     New(NewPackageCliConfig),
     Publish(PutConfig),
     Execute(RunCliConfig),
+    Secrets(SecretsCliConfig),
 }
 
 /// Node CLI
@@ -84,6 +85,42 @@ pub struct PutConfig {
     pub(crate) related_contracts: Option<PathBuf>,
 }
 
+/// Backup of the secrets of a component.
+#[derive(clap::Parser, Clone)]
+pub struct SecretsCliConfig {
+    #[clap(subcommand)]
+    pub command: SecretsCommand,
+    /// Overrides the default directory where the component secrets are stored.
+    #[clap(long)]
+    pub(crate) secrets_dir: Option<PathBuf>,
+    /// Passphrase used to encrypt or decrypt the archive.
+    #[clap(long, env = "LOCUTUS_SECRETS_PASSPHRASE", hide_env_values = true)]
+    pub(crate) passphrase: String,
+}
+
+#[derive(clap::Subcommand, Clone)]
+pub enum SecretsCommand {
+    Export(ExportSecretsConfig),
+    Import(ImportSecretsConfig),
+}
+
+/// Exports the secrets of a component into a passphrase encrypted archive.
+#[derive(clap::Parser, Clone)]
+pub struct ExportSecretsConfig {
+    /// Key of the component in Base58 format.
+    pub(crate) component: String,
+    /// A path to the file where the archive is written.
+    #[clap(long)]
+    pub(crate) output: PathBuf,
+}
+
+/// Restores the secrets of a component from an exported archive.
+#[derive(clap::Parser, Clone)]
+pub struct ImportSecretsConfig {
+    /// A path to the archive file.
+    pub(crate) archive: PathBuf,
+}
+
 /// Builds and packages a contract.
 ///
 /// This tool will build the WASM contract and publish it to the network.
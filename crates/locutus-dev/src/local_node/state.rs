@@ -1,7 +1,7 @@
 use std::{fs::File, io::Write, sync::Arc};
 
 use locutus_core::{Config, Executor, OperationMode, Storage};
-use locutus_runtime::{ContractStore, StateStore};
+use locutus_runtime::{ContractStore, RuntimeConfig, StateStore};
 use tokio::sync::RwLock;
 
 use crate::{local_node::DeserializationFmt, DynError};
@@ -30,6 +30,7 @@ impl AppState {
                         locutus_core::util::set_cleanup_on_exit().unwrap();
                     },
                     OperationMode::Local,
+                    RuntimeConfig::default(),
                 )
                 .await?,
             )),
@@ -229,7 +229,12 @@ impl From<CommandInfo> for OpenRequest<'static> {
             }
             Command::Update => {
                 let data = cmd.input.unwrap().unwrap_delta().into();
-                ContractRequest::Update { key, data }.into()
+                ContractRequest::Update {
+                    key,
+                    data,
+                    expected_summary: None,
+                }
+                .into()
             }
             Command::Exit => ClientRequest::Disconnect {
                 cause: Some("shutdown".to_owned()),
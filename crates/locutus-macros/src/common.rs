@@ -6,11 +6,7 @@ pub fn set_logger() -> TokenStream {
     quote! {
         #[cfg(feature = "trace")]
         {
-            use ::locutus_stdlib::prelude::{tracing_subscriber as tra};
-            if let Err(err) = tra::fmt()
-                .with_env_filter("info,locutus_stdlib=trace")
-                .try_init()
-            {
+            if let Err(err) = ::locutus_stdlib::log::init("info,locutus_stdlib=trace") {
                 return ::locutus_stdlib::prelude::ContractInterfaceResult::from(
                     Err::<::locutus_stdlib::prelude::ValidateResult, _>(
                         ::locutus_stdlib::prelude::ContractError::Other(format!("{}", err))
@@ -74,7 +74,9 @@ fn test_web(public_key: PublicKey) -> Result<WebBundle, std::io::Error> {
 async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use locutus::HttpGateway;
     use locutus_core::{
-        libp2p::identity::ed25519::Keypair, locutus_runtime::ContractStore, Executor, OperationMode,
+        libp2p::identity::ed25519::Keypair,
+        locutus_runtime::{ContractStore, RuntimeConfig},
+        Executor, OperationMode,
     };
 
     let keypair = Keypair::generate();
@@ -98,6 +100,7 @@ async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             locutus_core::util::set_cleanup_on_exit().unwrap();
         },
         OperationMode::Local,
+        RuntimeConfig::default(),
     )
     .await?;
     let id = HttpGateway::next_client_id();
@@ -1,7 +1,7 @@
 use clap::Parser;
 use locutus_dev::{
     build::build_package,
-    commands::{put, update},
+    commands::{put, secrets, update},
     config::{Config, SubCommand},
     local_node::run_local_node_client,
     new_pckg::create_new_package,
@@ -28,5 +28,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
                 update(update_config, config.additional).await
             }
         },
+        SubCommand::Secrets(secrets_config) => secrets(secrets_config),
     }
 }
@@ -1,11 +1,15 @@
 use clap::Parser;
 use locutus_core::{
-    locutus_runtime::{ContractStore, StateStore},
+    locutus_runtime::{
+        AsyncRuntime, CompilerBackend, ContractStore, Determinism, DiskQuota, EvictionPolicy,
+        RuntimeConfig, StateStore,
+    },
     Config, Executor, OperationMode, Storage,
 };
 use std::net::SocketAddr;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
@@ -13,6 +17,7 @@ type DynError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
 const MAX_SIZE: i64 = 10 * 1024 * 1024;
 const MAX_MEM_CACHE: u32 = 10_000_000;
+const PROFILE_EXPORT_INTERVAL: Duration = Duration::from_secs(10);
 
 async fn run(config: NodeConfig) -> Result<(), DynError> {
     match config.mode {
@@ -25,8 +30,11 @@ async fn run_local(config: NodeConfig) -> Result<(), DynError> {
     let contract_dir = config
         .contract_data_dir
         .unwrap_or_else(|| Config::get_conf().config_paths.local_contracts_dir());
-    // TODO: Generate components and secrets store from config
-    let contract_store = ContractStore::new(contract_dir, MAX_SIZE)?;
+    let mut contract_store = ContractStore::new(contract_dir, MAX_SIZE)?;
+    contract_store.set_quota(config.contracts_quota.map(|max_size| DiskQuota {
+        max_size,
+        policy: config.eviction_policy,
+    }));
     let state_store = StateStore::new(Storage::new().await?, MAX_MEM_CACHE).unwrap();
     let executor = Executor::new(
         contract_store,
@@ -35,12 +43,48 @@ async fn run_local(config: NodeConfig) -> Result<(), DynError> {
             locutus_core::util::set_cleanup_on_exit().unwrap();
         },
         OperationMode::Local,
+        RuntimeConfig {
+            compiler: config.compiler,
+            determinism: config.determinism,
+            profiling: config.profile.is_some(),
+            ..Default::default()
+        },
     )
     .await?;
+    executor
+        .set_component_quota(config.components_quota.map(|max_size| DiskQuota {
+            max_size,
+            policy: config.eviction_policy,
+        }))
+        .await?;
+    if let Some(path) = config.profile {
+        tokio::spawn(export_profile(executor.runtime(), path));
+    }
     let socket: SocketAddr = (config.bind, config.port).into();
     locutus::local_node::run_local_node(executor, socket).await
 }
 
+/// Periodically writes the stats of the contract calls to the file as JSON.
+async fn export_profile(runtime: AsyncRuntime, path: PathBuf) {
+    let mut interval = tokio::time::interval(PROFILE_EXPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let profile = runtime
+            .run(|runtime| Ok(runtime.profile().map(|profile| profile.to_json())))
+            .await;
+        match profile {
+            Ok(Some(Ok(json))) => {
+                if let Err(err) = std::fs::write(&path, json) {
+                    tracing::error!("failed to write contract profile to {path:?}: {err}");
+                }
+            }
+            Ok(Some(Err(err))) => tracing::error!("failed to serialize contract profile: {err}"),
+            Ok(None) => break,
+            Err(err) => tracing::warn!("failed to get contract profile: {err}"),
+        }
+    }
+}
+
 fn main() -> Result<(), DynError> {
     tracing_subscriber::fmt()
         .with_level(true)
@@ -75,4 +119,35 @@ struct NodeConfig {
     /// Port to expose api on
     #[arg(long, short, default_value_t = 50509)]
     port: u16,
+
+    /// Compiler used for contracts, either `cranelift` or `singlepass`. Singlepass compiles
+    /// in linear time and is preferable when running untrusted contracts.
+    #[arg(long, default_value_t = CompilerBackend::default())]
+    compiler: CompilerBackend,
+
+    /// Determinism required from contracts: `relaxed`, `strict` (contracts reading
+    /// non-deterministic inputs are rejected) or `host-inputs` (contracts read the time
+    /// at which the node handles each request).
+    #[arg(long, default_value_t = Determinism::default())]
+    determinism: Determinism,
+
+    /// Records the stats of every contract call and periodically exports them as JSON
+    /// to this file.
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// Max disk space in bytes used by the code of stored contracts. Once exceeded, contracts
+    /// without a stored state or subscribers are evicted.
+    #[arg(long)]
+    contracts_quota: Option<u64>,
+
+    /// Max disk space in bytes used by the code of registered components. Once exceeded,
+    /// components not subscribed to any contract are evicted.
+    #[arg(long)]
+    components_quota: Option<u64>,
+
+    /// Order in which contracts and components are evicted once over their quota, either
+    /// `lru` (least recently used) or `lfu` (least frequently used).
+    #[arg(long, default_value_t = EvictionPolicy::default())]
+    eviction_policy: EvictionPolicy,
 }
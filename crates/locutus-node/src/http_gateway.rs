@@ -1,6 +1,5 @@
 use futures::{future::BoxFuture, stream::SplitSink, FutureExt, SinkExt, StreamExt};
 
-use locutus_core::locutus_runtime::TryFromTsStd;
 use locutus_core::*;
 use locutus_runtime::ContractKey;
 use locutus_stdlib::client_api::{
@@ -60,9 +59,10 @@ impl HttpGateway {
             .map(move || rs.clone())
             .and(warp::path::end())
             .and(warp::ws())
-            .map(|rs, ws: warp::ws::Ws| {
-                ws.on_upgrade(|ws: WebSocket| async {
-                    if let Err(e) = websocket_interface(rs, ws).await {
+            .and(EncodingProtocol::from_query())
+            .map(|rs, ws: warp::ws::Ws, encoding| {
+                ws.on_upgrade(move |ws: WebSocket| async move {
+                    if let Err(e) = websocket_interface(rs, ws, encoding).await {
                         tracing::error!("{e}");
                     }
                 })
@@ -124,6 +124,7 @@ async fn home() -> Result<impl Reply, Rejection> {
 async fn websocket_interface(
     request_sender: mpsc::Sender<ClientConnection>,
     ws: WebSocket,
+    encoding: EncodingProtocol,
 ) -> Result<(), DynError> {
     let (mut response_rx, client_id) = new_client_connection(&request_sender).await?;
     let (mut tx, mut rx) = ws.split();
@@ -165,7 +166,7 @@ async fn websocket_interface(
                 }
                 Ok(v) => v,
             };
-            process_client_request(client_id, next_msg, &request_sender).await
+            process_client_request(client_id, encoding, next_msg, &request_sender).await
         };
 
         let active_listeners = listeners.clone();
@@ -206,6 +207,7 @@ struct NewSubscription {
 
 async fn process_client_request(
     client_id: ClientId,
+    encoding: EncodingProtocol,
     msg: Result<Message, warp::Error>,
     request_sender: &mpsc::Sender<ClientConnection>,
 ) -> Result<Option<Message>, Option<DynError>> {
@@ -222,8 +224,8 @@ async fn process_client_request(
         Err(err) => return Err(Some(err.into())),
     };
     let req: ClientRequest = {
-        match ContractRequest::try_decode(&msg) {
-            Ok(r) => r.into(),
+        match encoding.decode_request(&msg) {
+            Ok(r) => r,
             Err(e) => {
                 let result_error = rmp_serde::to_vec(&Err::<HostResponse, ClientError>(
                     ErrorKind::DeserializationError {
@@ -374,3 +376,71 @@ impl ClientEventsProxy for HttpGateway {
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A get request encoded by the TypeScript client API.
+    const TS_GET_REQUEST: &[u8] = &[
+        130, 163, 107, 101, 121, 130, 168, 105, 110, 115, 116, 97, 110, 99, 101, 196, 32, 255, 17,
+        144, 159, 194, 187, 46, 33, 205, 77, 242, 70, 87, 18, 202, 62, 226, 149, 25, 151, 188, 167,
+        153, 197, 129, 25, 179, 198, 218, 99, 159, 139, 164, 99, 111, 100, 101, 192, 173, 102, 101,
+        116, 99, 104, 67, 111, 110, 116, 114, 97, 99, 116, 194,
+    ];
+
+    #[tokio::test]
+    async fn typescript_request() -> Result<(), Box<dyn std::error::Error>> {
+        let (request_sender, mut requests) = mpsc::channel(1);
+        let client_id = ClientId::new(0);
+        let response = process_client_request(
+            client_id,
+            EncodingProtocol::TypeScript,
+            Ok(Message::binary(TS_GET_REQUEST)),
+            &request_sender,
+        )
+        .await
+        .map_err(|_| "failed processing the request")?;
+        assert!(response.is_none());
+
+        let Some(ClientConnection::Request {
+            client_id: id,
+            req:
+                ClientRequest::ContractOp(ContractRequest::Get {
+                    key,
+                    fetch_contract: false,
+                }),
+        }) = requests.recv().await
+        else {
+            panic!("expected a get request");
+        };
+        assert_eq!(id, client_id);
+        assert_eq!(
+            key,
+            ContractKey::from_id("JAgVrRHt88YbBFjGQtBD3uEmRUFvZQqK7k8ypnJ8g6TC")?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn request_in_other_encoding() -> Result<(), Box<dyn std::error::Error>> {
+        let (request_sender, mut requests) = mpsc::channel(1);
+        let response = process_client_request(
+            ClientId::new(0),
+            EncodingProtocol::Native,
+            Ok(Message::binary(TS_GET_REQUEST)),
+            &request_sender,
+        )
+        .await
+        .map_err(|_| "failed processing the request")?;
+
+        let response = response.ok_or("expected an error response")?;
+        let result: HostResult = rmp_serde::from_slice(response.as_bytes())?;
+        assert!(matches!(
+            result.map_err(|err| err.kind()),
+            Err(ErrorKind::DeserializationError { .. })
+        ));
+        assert!(requests.try_recv().is_err());
+        Ok(())
+    }
+}
@@ -34,6 +34,7 @@ enum HostCallbackResult {
 pub mod local_node {
     use std::net::SocketAddr;
 
+    use futures::FutureExt;
     use locutus_core::{
         either, ClientEventsProxy, Executor, OpenRequest, RequestError, WebSocketProxy,
     };
@@ -50,43 +51,43 @@ pub mod local_node {
         // FIXME: use combinator
         // let mut all_clients =
         //    ClientEventsCombinator::new([Box::new(ws_handle), Box::new(http_handle)]);
+        let mut user_input_prompts = executor
+            .user_input_prompts()
+            .ok_or("user input prompts already taken from the executor")?;
         loop {
-            let OpenRequest {
-                id,
-                request,
-                notification_channel,
-                ..
-            } = http_handle.recv().await?;
-            tracing::debug!("client {id}, req -> {request}");
-            match executor
-                .handle_request(id, request, notification_channel)
-                .await
-            {
-                Ok(res) => {
-                    http_handle.send(id, Ok(res)).await?;
-                }
-                Err(either::Left(RequestError::Disconnect)) => {}
-                Err(either::Left(err)) => {
-                    tracing::error!("{err}");
-                    http_handle
-                        .send(
-                            id,
-                            Err(ClientError::from(ErrorKind::Other(format!("{err}")))),
-                        )
-                        .await?;
-                }
-                Err(either::Right(err)) => {
-                    tracing::error!("{err}");
-                    http_handle
-                        .send(
-                            id,
-                            Err(ErrorKind::Unhandled {
-                                cause: format!("{err}"),
-                            }
-                            .into()),
-                        )
-                        .await?;
+            let req = tokio::select! {
+                req = http_handle.recv() => req?,
+                Some((id, prompt)) = user_input_prompts.recv() => {
+                    http_handle.send(id, prompt).await?;
+                    continue;
                 }
+            };
+            // the requests already received are handled together, so the executor can merge
+            // the updates of the same contract
+            let mut requests: Vec<OpenRequest<'static>> = vec![req];
+            while let Some(req) = http_handle.recv().now_or_never() {
+                requests.push(req?);
+            }
+            for req in &requests {
+                tracing::debug!("client {}, req -> {}", req.id, req.request);
+            }
+            for (id, result) in executor.handle_requests(requests).await {
+                let result = match result {
+                    Ok(res) => Ok(res),
+                    Err(either::Left(RequestError::Disconnect)) => continue,
+                    Err(either::Left(err)) => {
+                        tracing::error!("{err}");
+                        Err(ClientError::from(ErrorKind::Other(format!("{err}"))))
+                    }
+                    Err(either::Right(err)) => {
+                        tracing::error!("{err}");
+                        Err(ErrorKind::Unhandled {
+                            cause: format!("{err}"),
+                        }
+                        .into())
+                    }
+                };
+                http_handle.send(id, result).await?;
             }
         }
     }
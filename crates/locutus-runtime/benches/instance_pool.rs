@@ -0,0 +1,80 @@
+//! Compares back-to-back calls to the same contract with and without instance pooling.
+//!
+//! Run with `cargo bench -p locutus-runtime --bench instance_pool`.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use locutus_runtime::prelude::*;
+
+/// A contract which accepts any delta.
+const CONTRACT: &str = include_str!("../src/tests/accepting_contract.wat");
+
+/// Number of consecutive calls, as when notifying several subscribers of an update.
+const CALLS: usize = 32;
+
+fn setup_runtime(bench_dir: &Path, pool_size: usize) -> (Runtime, ContractKey) {
+    static RUNTIME_NO: AtomicUsize = AtomicUsize::new(0);
+    let contracts_dir = bench_dir.join(RUNTIME_NO.fetch_add(1, Ordering::SeqCst).to_string());
+    std::fs::create_dir_all(&contracts_dir).unwrap();
+    let mut store = ContractStore::new(contracts_dir, 10_000).unwrap();
+    let code = wasmer::wat2wasm(CONTRACT.as_bytes()).unwrap().into_owned();
+    let contract = ContractContainer::Wasm(WasmAPIVersion::V1(WrappedContract::new(
+        Arc::new(ContractCode::from(code)),
+        vec![].into(),
+    )));
+    let key = contract.key();
+    store.store_contract(contract).unwrap();
+    let mut runtime = Runtime::build_with_config(
+        store,
+        ComponentStore::default(),
+        SecretsStore::default(),
+        false,
+        RuntimeConfig {
+            instance_pool_size: pool_size,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    // compile the module ahead of the measured calls
+    assert!(runtime
+        .validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))
+        .unwrap());
+    (runtime, key)
+}
+
+fn consecutive_calls(c: &mut Criterion) {
+    let bench_dir = std::env::temp_dir()
+        .join("locutus-bench")
+        .join("instance-pool");
+    let _ = std::fs::remove_dir_all(&bench_dir);
+
+    let mut group = c.benchmark_group("consecutive_calls");
+    group.sample_size(20);
+    let params = Parameters::from(vec![]);
+    let delta = StateDelta::from(vec![]);
+    for pool_size in [0, RuntimeConfig::DEFAULT_INSTANCE_POOL_SIZE] {
+        group.bench_function(BenchmarkId::new("pool_size", pool_size), |b| {
+            // every instance lives as long as the runtime, so use a fresh one for each iteration
+            b.iter_batched_ref(
+                || setup_runtime(&bench_dir, pool_size),
+                |(runtime, key)| {
+                    for _ in 0..CALLS {
+                        assert!(runtime.validate_delta(key, &params, &delta).unwrap());
+                    }
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, consecutive_calls);
+criterion_main!(benches);
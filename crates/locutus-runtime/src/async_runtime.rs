@@ -0,0 +1,176 @@
+//! Asynchronous interface to a [`Runtime`], running contracts and components on a dedicated
+//! thread so slow calls don't block the tasks of the async executor.
+
+use std::{thread, time::Duration};
+
+use locutus_stdlib::prelude::*;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    component::{ComponentRuntimeInterface, SessionId},
+    contract::ContractRuntimeInterface,
+    error::RuntimeInnerError,
+    Runtime, RuntimeResult,
+};
+
+type Job = Box<dyn FnOnce(&mut Runtime) + Send>;
+
+/// Handle to a [`Runtime`] owned by a dedicated worker thread.
+///
+/// Calls are queued in a bounded channel of [`RuntimeConfig::call_queue_size`] entries and
+/// executed in order by the worker, once the queue is full callers wait until there is room.
+/// Each call, including the time spent in the queue, fails with a timeout error after
+/// [`RuntimeConfig::call_timeout`].
+///
+/// A call which timed out while running is not interrupted, it keeps the worker busy until
+/// it returns or runs out of gas. Calls which timed out while queued are skipped.
+///
+/// The worker thread stops once every handle has been dropped.
+///
+/// [`RuntimeConfig::call_queue_size`]: crate::RuntimeConfig::call_queue_size
+/// [`RuntimeConfig::call_timeout`]: crate::RuntimeConfig::call_timeout
+#[derive(Clone)]
+pub struct AsyncRuntime {
+    jobs: mpsc::Sender<Job>,
+    call_timeout: Duration,
+}
+
+impl AsyncRuntime {
+    pub fn new(mut runtime: Runtime) -> RuntimeResult<Self> {
+        let call_timeout = runtime.config.call_timeout;
+        let (jobs, mut queue) = mpsc::channel::<Job>(runtime.config.call_queue_size.max(1));
+        thread::Builder::new()
+            .name("locutus-runtime".to_owned())
+            .spawn(move || {
+                while let Some(job) = queue.blocking_recv() {
+                    job(&mut runtime);
+                }
+                tracing::debug!("runtime worker stopped");
+            })?;
+        Ok(Self { jobs, call_timeout })
+    }
+
+    /// Runs `f` on the worker thread with exclusive access to the runtime.
+    pub async fn run<F, T>(&self, f: F) -> RuntimeResult<T>
+    where
+        F: FnOnce(&mut Runtime) -> RuntimeResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move |runtime| {
+            if tx.is_closed() {
+                // the caller is gone, most likely timed out while the job was queued
+                return;
+            }
+            let _ = tx.send(f(runtime));
+        });
+        let call = async {
+            self.jobs
+                .send(job)
+                .await
+                .map_err(|_| RuntimeInnerError::WorkerStopped)?;
+            rx.await.map_err(|_| RuntimeInnerError::WorkerStopped)?
+        };
+        tokio::time::timeout(self.call_timeout, call)
+            .await
+            .map_err(|_| RuntimeInnerError::Timeout(self.call_timeout))?
+    }
+
+    pub async fn validate_state(
+        &self,
+        key: ContractKey,
+        parameters: Parameters<'static>,
+        state: WrappedState,
+        related: RelatedContracts<'static>,
+    ) -> RuntimeResult<ValidateResult> {
+        self.run(move |runtime| {
+            let result = runtime.validate_state(&key, &parameters, &state, related);
+            tracing::debug!(gas = runtime.gas_used(), "validated state for {key}");
+            result
+        })
+        .await
+    }
+
+    pub async fn validate_delta(
+        &self,
+        key: ContractKey,
+        parameters: Parameters<'static>,
+        delta: StateDelta<'static>,
+    ) -> RuntimeResult<bool> {
+        self.run(move |runtime| {
+            let result = runtime.validate_delta(&key, &parameters, &delta);
+            tracing::debug!(gas = runtime.gas_used(), "validated delta for {key}");
+            result
+        })
+        .await
+    }
+
+    pub async fn update_state(
+        &self,
+        key: ContractKey,
+        parameters: Parameters<'static>,
+        state: WrappedState,
+        data: Vec<UpdateData<'static>>,
+    ) -> RuntimeResult<UpdateModification<'static>> {
+        self.run(move |runtime| {
+            let result = runtime.update_state(&key, &parameters, &state, &data);
+            tracing::debug!(gas = runtime.gas_used(), "updated state for {key}");
+            result
+        })
+        .await
+    }
+
+    pub async fn summarize_state(
+        &self,
+        key: ContractKey,
+        parameters: Parameters<'static>,
+        state: WrappedState,
+    ) -> RuntimeResult<StateSummary<'static>> {
+        self.run(move |runtime| {
+            let result = runtime.summarize_state(&key, &parameters, &state);
+            tracing::debug!(gas = runtime.gas_used(), "summarized state for {key}");
+            result
+        })
+        .await
+    }
+
+    pub async fn get_state_delta(
+        &self,
+        key: ContractKey,
+        parameters: Parameters<'static>,
+        state: WrappedState,
+        summary: StateSummary<'static>,
+    ) -> RuntimeResult<StateDelta<'static>> {
+        self.run(move |runtime| {
+            let result = runtime.get_state_delta(&key, &parameters, &state, &summary);
+            tracing::debug!(gas = runtime.gas_used(), "computed state delta for {key}");
+            result
+        })
+        .await
+    }
+
+    pub async fn inbound_app_message(
+        &self,
+        key: ComponentKey,
+        session: SessionId,
+        inbound: Vec<InboundComponentMsg<'static>>,
+    ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+        self.run(move |runtime| {
+            let result = runtime.inbound_app_message(&key, session, inbound);
+            tracing::debug!(
+                gas = runtime.gas_used(),
+                "processed messages for component `{key}`"
+            );
+            result
+        })
+        .await
+    }
+
+    pub async fn close_session(&self, session: SessionId) -> RuntimeResult<()> {
+        self.run(move |runtime| {
+            runtime.close_session(session);
+            Ok(())
+        })
+        .await
+    }
+}
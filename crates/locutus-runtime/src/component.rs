@@ -1,8 +1,13 @@
-use crate::{util, ContractError, Runtime, RuntimeResult};
+use crate::{
+    contract::ContractRuntimeInterface, runtime::RunningInstance, util, ContractError, DynError,
+    Runtime, RuntimeResult,
+};
 use locutus_stdlib::prelude::{
     ApplicationMessage, Component, ComponentContext, ComponentError, ComponentInterfaceResult,
-    ComponentKey, GetSecretRequest, GetSecretResponse, InboundComponentMsg, OutboundComponentMsg,
-    SetSecretRequest,
+    ComponentKey, ContractKey, GetContractRequest, GetContractResponse, GetSecretRequest,
+    GetSecretResponse, InboundComponentMsg, ListSecretsRequest, ListSecretsResponse,
+    OutboundComponentMsg, Parameters, RelatedContracts, SetSecretRequest, SubscribeContractRequest,
+    UpdateContractRequest, ValidateResult, WrappedState,
 };
 
 use crate::error::RuntimeInnerError;
@@ -17,23 +22,96 @@ pub enum ComponentExecError {
 
     #[error("Received an unexpected message from the client apps: {0}")]
     UnexpectedMessage(&'static str),
+
+    #[error("component execution ran out of gas (limit: {0} units)")]
+    OutOfGas(u64),
+
+    #[error("contract {0} requested by the component, but the host doesn't provide contracts")]
+    MissingContractAccess(ContractKey),
+
+    #[error("component requested {requested} random bytes, the limit is {max}")]
+    RandomBytesLimit { requested: usize, max: usize },
+
+    #[error("component exceeded the maximum of {max} attempts to {request}")]
+    ExceededAttempts { request: &'static str, max: usize },
+}
+
+/// Access of the components to the contracts of the host, set with
+/// [`Runtime::set_contract_access`].
+///
+/// Called from the thread running the components, so implementations must not wait on calls
+/// to the same runtime.
+pub trait ContractAccess: Send {
+    /// Parameters and current state of the contract, if the host has them.
+    fn get(
+        &mut self,
+        key: &ContractKey,
+    ) -> Result<Option<(Parameters<'static>, WrappedState)>, DynError>;
+
+    /// Stores the state of the contract after a component updated it.
+    fn store(&mut self, key: &ContractKey, state: WrappedState) -> Result<(), DynError>;
+
+    /// Registers the component for the updates of the contract.
+    fn subscribe(&mut self, component: &ComponentKey, key: &ContractKey) -> Result<(), DynError>;
 }
 
+/// Client session a component processes messages for. The host keeps a [`ComponentContext`]
+/// for each component and session, see [`ComponentContext::current`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SessionId(pub usize);
+
 pub trait ComponentRuntimeInterface {
+    /// Processes the messages with the component, starting from the context it left in the
+    /// session. The context is kept only if all the messages were processed.
     fn inbound_app_message(
         &mut self,
         key: &ComponentKey,
+        session: SessionId,
         inbound: Vec<InboundComponentMsg>,
     ) -> RuntimeResult<Vec<OutboundComponentMsg>>;
 
+    /// Drops the contexts the components kept for the session.
+    fn close_session(&mut self, session: SessionId);
+
+    /// Registers the component with the cipher encrypting its secrets. Secrets stored in the
+    /// legacy format are migrated if the nonce they were encrypted with is given.
     fn register_component(
         &mut self,
         component: Component<'_>,
         cipher: XChaCha20Poly1305,
-        nonce: XNonce,
+        legacy_nonce: Option<XNonce>,
+    ) -> RuntimeResult<()>;
+
+    /// Re-encrypts the secrets of the component with a new cipher, `current_cipher` must be
+    /// the cipher the component was registered with.
+    fn rotate_component_key(
+        &mut self,
+        key: &ComponentKey,
+        current_cipher: XChaCha20Poly1305,
+        cipher: XChaCha20Poly1305,
     ) -> RuntimeResult<()>;
 
     fn unregister_component(&mut self, key: &ComponentKey) -> RuntimeResult<()>;
+
+    /// Notifies a component subscribed to the contract of its new state. Notifications don't
+    /// belong to a session, so the component starts from an empty context.
+    fn contract_notification(
+        &mut self,
+        key: &ComponentKey,
+        contract: ContractKey,
+        state: WrappedState,
+    ) -> RuntimeResult<Vec<OutboundComponentMsg>>;
+}
+
+/// Max requests of a component served while processing a message.
+const MAX_ITERATIONS: usize = 100;
+
+fn exceeded_attempts(request: &'static str) -> ContractError {
+    ComponentExecError::ExceededAttempts {
+        request,
+        max: MAX_ITERATIONS,
+    }
+    .into()
 }
 
 impl Runtime {
@@ -43,13 +121,18 @@ impl Runtime {
         process_func: &TypedFunction<i64, i64>,
         instance: &Instance,
     ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+        self.reset_gas(instance);
         let msg_ptr = {
             let msg = bincode::serialize(msg)?;
             let mut msg_buf = self.init_buf(instance, &msg)?;
             msg_buf.write(msg)?;
             msg_buf.ptr()
         };
-        let res = process_func.call(&mut self.wasm_store, msg_ptr as i64)?;
+        let res = process_func.call(&mut self.wasm_store, msg_ptr as i64);
+        if self.consume_gas(instance) {
+            return Err(ComponentExecError::OutOfGas(self.config.max_gas).into());
+        }
+        let res = res?;
         let linear_mem = self.linear_mem(instance)?;
         let outbound = unsafe {
             ComponentInterfaceResult::from_raw(res, &linear_mem)
@@ -59,57 +142,183 @@ impl Runtime {
         Ok(outbound)
     }
 
-    // FIXME: modify the context atomically from the components, requires some changes to handle function calls with envs
+    fn contract_access(&mut self, key: &ContractKey) -> RuntimeResult<&mut dyn ContractAccess> {
+        match self.contract_access.as_deref_mut() {
+            Some(access) => Ok(access),
+            None => Err(ComponentExecError::MissingContractAccess(key.clone()).into()),
+        }
+    }
+
+    /// Context of the session the running component is processing messages for.
+    fn context(&self, running: &RunningInstance) -> ComponentContext {
+        running
+            .env
+            .as_ref(&self.wasm_store)
+            .component_context
+            .clone()
+            .unwrap_or_default()
+    }
+
+    fn set_context(&mut self, running: &RunningInstance, context: ComponentContext) {
+        running.env.as_mut(&mut self.wasm_store).component_context = Some(context);
+    }
+
+    /// Whether the component sets its context through the host, instead of passing it along
+    /// the messages it returns.
+    fn host_managed_context(running: &RunningInstance) -> bool {
+        running
+            .instance
+            .module()
+            .imports()
+            .any(|import| import.module() == "locutus_component" && import.name() == "set_context")
+    }
+
+    /// Passes the message to the component along the context of the session, then updates
+    /// the context with the one returned by the component.
+    fn exec_with_context(
+        &mut self,
+        mut inbound: InboundComponentMsg<'_>,
+        process_func: &TypedFunction<i64, i64>,
+        running: &RunningInstance,
+    ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+        if let Some(context) = inbound.get_mut_context() {
+            *context = self.context(running);
+        }
+        let outbound = self.exec_inbound(&inbound, process_func, &running.instance)?;
+        if !Self::host_managed_context(running) {
+            if let Some(context) = outbound.last().and_then(|msg| msg.get_context()) {
+                self.set_context(running, context.clone());
+            }
+        }
+        let mut pending = Vec::with_capacity(outbound.len());
+        for msg in outbound {
+            match msg {
+                OutboundComponentMsg::ContextUpdated(context) => self.set_context(running, context),
+                msg => pending.push(msg),
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Serves the requests of the component until it returns a message for the client.
     fn get_outbound(
         &mut self,
         component_key: &ComponentKey,
-        instance: &Instance,
+        running: &RunningInstance,
         process_func: &TypedFunction<i64, i64>,
         outbound_msgs: &mut VecDeque<OutboundComponentMsg>,
         results: &mut Vec<OutboundComponentMsg>,
-    ) -> RuntimeResult<ComponentContext> {
-        const MAX_ITERATIONS: usize = 100;
-
+    ) -> RuntimeResult<()> {
         let mut retries = 0;
-        let Some(mut last_context) = outbound_msgs.back().and_then(|m| m.get_context().cloned()) else {
-            return Ok(ComponentContext::default());
-        };
         while let Some(outbound) = outbound_msgs.pop_front() {
             match outbound {
                 OutboundComponentMsg::GetSecretRequest(GetSecretRequest {
-                    key,
-                    context,
-                    processed,
+                    key, processed, ..
                 }) if !processed => {
+                    if retries >= MAX_ITERATIONS {
+                        return Err(exceeded_attempts("get the secret"));
+                    }
                     let secret = self.secret_store.get_secret(component_key, &key)?;
                     let inbound = InboundComponentMsg::GetSecretResponse(GetSecretResponse {
                         key,
                         value: Some(secret),
-                        context,
+                        context: ComponentContext::default(),
                     });
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
+                    retries += 1;
+                }
+                OutboundComponentMsg::ListSecretsRequest(ListSecretsRequest {
+                    processed, ..
+                }) if !processed => {
                     if retries >= MAX_ITERATIONS {
-                        return Err(ContractError::from(RuntimeInnerError::ComponentExecError(ComponentError::Other("The maximum number of attempts to get the secret has been exceeded".to_string()).into())));
+                        return Err(exceeded_attempts("list the secrets"));
                     }
-                    let new_msgs = self.exec_inbound(&inbound, process_func, instance)?;
+                    let secrets = self.secret_store.list_secrets(component_key)?;
+                    let inbound = InboundComponentMsg::ListSecretsResponse(ListSecretsResponse {
+                        secrets,
+                        context: ComponentContext::default(),
+                    });
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
                     retries += 1;
-                    let Some(last_msg) = new_msgs.last() else {
-                        return Err(ContractError::from(RuntimeInnerError::ComponentExecError(ComponentError::Other("Error trying to update the context from the secret".to_string()).into())));
+                }
+                OutboundComponentMsg::GetContractRequest(GetContractRequest {
+                    key,
+                    processed,
+                    ..
+                }) if !processed => {
+                    if retries >= MAX_ITERATIONS {
+                        return Err(exceeded_attempts("get the contract"));
+                    }
+                    let state = self
+                        .contract_access(&key)?
+                        .get(&key)?
+                        .map(|(_, state)| state);
+                    let inbound = InboundComponentMsg::GetContractResponse(GetContractResponse {
+                        key,
+                        state,
+                        context: ComponentContext::default(),
+                    });
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
+                    retries += 1;
+                }
+                OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+                    key,
+                    data,
+                }) => {
+                    if retries >= MAX_ITERATIONS {
+                        return Err(exceeded_attempts("update the contract"));
+                    }
+                    let Some((parameters, state)) = self.contract_access(&key)?.get(&key)? else {
+                        return Err(RuntimeInnerError::ContractNotFound(key).into());
                     };
-                    let Some(new_last_context) = last_msg.get_context() else {
-                        return Err(ContractError::from(RuntimeInnerError::ComponentExecError(ComponentError::Other("Last messsage ".to_string()).into())));
+                    let modification = self.update_state(&key, &parameters, &state, &[data])?;
+                    let Some(new_state) = modification.new_state else {
+                        return Err(ComponentExecError::ComponentError(ComponentError::Other(
+                            format!("the update of contract {key} requires related contracts"),
+                        ))
+                        .into());
                     };
-                    last_context = new_last_context.clone();
-                    for mut pending in new_msgs {
-                        if let Some(ctx) = pending.get_mut_context() {
-                            *ctx = last_context.clone();
-                        };
-                        if !pending.processed() {
-                            outbound_msgs.push_back(pending);
-                        }
+                    let new_state = WrappedState::new(new_state.into_bytes());
+                    // related contracts aren't fetched for components
+                    let validation = self.validate_state(
+                        &key,
+                        &parameters,
+                        &new_state,
+                        RelatedContracts::new(),
+                    )?;
+                    if validation != ValidateResult::Valid {
+                        return Err(ComponentExecError::ComponentError(ComponentError::Other(
+                            format!("the update of contract {key} results in an invalid state"),
+                        ))
+                        .into());
                     }
+                    self.contract_access(&key)?.store(&key, new_state)?;
+                    retries += 1;
                 }
-                OutboundComponentMsg::GetSecretRequest(GetSecretRequest { context, .. }) => {
-                    last_context = context;
+                OutboundComponentMsg::SubscribeContractRequest(SubscribeContractRequest {
+                    key,
+                    processed,
+                    ..
+                }) if !processed => {
+                    if retries >= MAX_ITERATIONS {
+                        return Err(exceeded_attempts("subscribe to the contract"));
+                    }
+                    let access = self.contract_access(&key)?;
+                    access.subscribe(component_key, &key)?;
+                    let state = access.get(&key)?.map(|(_, state)| state);
+                    let inbound = InboundComponentMsg::GetContractResponse(GetContractResponse {
+                        key,
+                        state,
+                        context: ComponentContext::default(),
+                    });
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
+                    retries += 1;
+                }
+                OutboundComponentMsg::GetSecretRequest(_)
+                | OutboundComponentMsg::ListSecretsRequest(_)
+                | OutboundComponentMsg::GetContractRequest(_)
+                | OutboundComponentMsg::SubscribeContractRequest(_) => {
+                    // processed already, only returned to pass the context along
                 }
                 OutboundComponentMsg::SetSecretRequest(SetSecretRequest { key, value }) => {
                     if let Some(plaintext) = value {
@@ -121,20 +330,15 @@ impl Runtime {
                 }
                 OutboundComponentMsg::ApplicationMessage(msg) if !msg.processed => {
                     if retries >= MAX_ITERATIONS {
-                        panic!();
+                        return Err(exceeded_attempts("process the application message"));
                     }
-                    let outbound = self.exec_inbound(
-                        &InboundComponentMsg::ApplicationMessage(
-                            ApplicationMessage::new(msg.app, msg.payload, msg.processed)
-                                .with_context(last_context.clone()),
-                        ),
-                        process_func,
-                        instance,
-                    )?;
+                    let inbound = InboundComponentMsg::ApplicationMessage(ApplicationMessage::new(
+                        msg.app,
+                        msg.payload,
+                        msg.processed,
+                    ));
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
                     retries += 1;
-                    for m in outbound {
-                        outbound_msgs.push_back(m);
-                    }
                 }
                 OutboundComponentMsg::ApplicationMessage(mut msg) => {
                     msg.context = ComponentContext::default();
@@ -142,140 +346,168 @@ impl Runtime {
                     break;
                 }
                 OutboundComponentMsg::RequestUserInput(req) => {
+                    // resumed in the same session once the user answers
                     results.push(OutboundComponentMsg::RequestUserInput(req));
                     break;
                 }
-                OutboundComponentMsg::RandomBytesRequest(bytes) => {
-                    let mut bytes = vec![0; bytes];
+                OutboundComponentMsg::RandomBytesRequest(requested) => {
+                    if retries >= MAX_ITERATIONS {
+                        return Err(exceeded_attempts("generate random bytes"));
+                    }
+                    let max = self.config.max_random_bytes;
+                    if requested > max {
+                        return Err(ComponentExecError::RandomBytesLimit { requested, max }.into());
+                    }
+                    let mut bytes = vec![0; requested];
                     util::generate_random_bytes(&mut bytes);
                     let inbound = InboundComponentMsg::RandomBytes(bytes);
-                    let new_outbound_msgs = self.exec_inbound(&inbound, process_func, instance)?;
-                    for msg in new_outbound_msgs.into_iter() {
-                        outbound_msgs.push_back(msg);
-                    }
+                    outbound_msgs.extend(self.exec_with_context(inbound, process_func, running)?);
+                    retries += 1;
                 }
                 OutboundComponentMsg::ContextUpdated(context) => {
-                    last_context = context;
+                    self.set_context(running, context);
                 }
             }
         }
-        Ok(last_context)
+        Ok(())
     }
-}
 
-impl ComponentRuntimeInterface for Runtime {
-    fn inbound_app_message(
+    /// Runs the component with the context until it processed the messages, returns the
+    /// results and the context left by the component.
+    fn process_messages(
         &mut self,
         key: &ComponentKey,
+        context: ComponentContext,
         inbound: Vec<InboundComponentMsg>,
-    ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+    ) -> RuntimeResult<(Vec<OutboundComponentMsg>, ComponentContext)> {
         let mut results = Vec::with_capacity(inbound.len());
-        if inbound.is_empty() {
-            return Ok(results);
-        }
         let running = self.prepare_component_call(key, 4096)?;
         let process_func: TypedFunction<i64, i64> = running
             .instance
             .exports
             .get_typed_function(&self.wasm_store, "process")?;
-
-        // Initialize the shared context with the first message context
-        let mut last_context = match inbound.first() {
-            Some(msg) => {
-                if let Some(context) = msg.get_context() {
-                    context.clone()
-                } else {
-                    ComponentContext::default()
-                }
-            }
-            _ => ComponentContext::default(),
-        };
+        self.set_context(&running, context);
 
         for msg in inbound {
+            let mut outbound =
+                VecDeque::from(self.exec_with_context(msg, &process_func, &running)?);
+            self.get_outbound(key, &running, &process_func, &mut outbound, &mut results)?;
+        }
+        let context = running
+            .env
+            .as_mut(&mut self.wasm_store)
+            .component_context
+            .take()
+            .unwrap_or_default();
+        self.release_component_instance(key, running);
+        Ok((results, context))
+    }
+}
+
+impl ComponentRuntimeInterface for Runtime {
+    fn inbound_app_message(
+        &mut self,
+        key: &ComponentKey,
+        session: SessionId,
+        inbound: Vec<InboundComponentMsg>,
+    ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+        if inbound.is_empty() {
+            return Ok(vec![]);
+        }
+        for msg in &inbound {
             match msg {
-                InboundComponentMsg::ApplicationMessage(ApplicationMessage {
-                    app,
-                    payload,
-                    processed,
-                    ..
-                }) => {
-                    let mut outbound = VecDeque::from(
-                        self.exec_inbound(
-                            &InboundComponentMsg::ApplicationMessage(
-                                ApplicationMessage::new(app, payload, processed)
-                                    .with_context(last_context.clone()),
-                            ),
-                            &process_func,
-                            &running.instance,
-                        )?,
-                    );
-
-                    // Update the shared context for next messages
-                    last_context = self.get_outbound(
-                        key,
-                        &running.instance,
-                        &process_func,
-                        &mut outbound,
-                        &mut results,
-                    )?;
-                }
-                InboundComponentMsg::UserResponse(response) => {
-                    let mut outbound = VecDeque::from(self.exec_inbound(
-                        &InboundComponentMsg::UserResponse(response),
-                        &process_func,
-                        &running.instance,
-                    )?);
-                    self.get_outbound(
-                        key,
-                        &running.instance,
-                        &process_func,
-                        &mut outbound,
-                        &mut results,
-                    )?;
-                }
+                InboundComponentMsg::ApplicationMessage(_)
+                | InboundComponentMsg::UserResponse(_)
+                | InboundComponentMsg::RandomBytes(_) => {}
                 InboundComponentMsg::GetSecretResponse(_) => {
                     return Err(ComponentExecError::UnexpectedMessage("get secret response").into())
                 }
-                InboundComponentMsg::RandomBytes(bytes) => {
-                    let mut outbound = VecDeque::from(self.exec_inbound(
-                        &InboundComponentMsg::RandomBytes(bytes),
-                        &process_func,
-                        &running.instance,
-                    )?);
-                    self.get_outbound(
-                        key,
-                        &running.instance,
-                        &process_func,
-                        &mut outbound,
-                        &mut results,
-                    )?;
+                InboundComponentMsg::ListSecretsResponse(_) => {
+                    return Err(
+                        ComponentExecError::UnexpectedMessage("list secrets response").into(),
+                    )
+                }
+                InboundComponentMsg::GetContractResponse(_) => {
+                    return Err(
+                        ComponentExecError::UnexpectedMessage("get contract response").into(),
+                    )
                 }
             }
         }
+        let session_key = (key.clone(), session);
+        let context = self
+            .component_contexts
+            .get(&session_key)
+            .cloned()
+            .unwrap_or_default();
+        let (results, context) = self.process_messages(key, context, inbound)?;
+        self.component_contexts.insert(session_key, context);
         Ok(results)
     }
 
+    fn close_session(&mut self, session: SessionId) {
+        self.component_contexts.retain(|(_, s), _| *s != session);
+    }
+
     #[inline]
     fn register_component(
         &mut self,
         component: Component<'_>,
         cipher: XChaCha20Poly1305,
-        nonce: XNonce,
+        legacy_nonce: Option<XNonce>,
     ) -> RuntimeResult<()> {
         self.secret_store
-            .register_component(component.key().clone(), cipher, nonce)?;
+            .register_component(component.key().clone(), cipher)?;
+        if let Some(nonce) = legacy_nonce {
+            let key = component.key();
+            let migrated = self.secret_store.migrate_legacy_secrets(key, &nonce)?;
+            tracing::debug!("migrated {migrated} secrets of component {key}");
+        }
         self.component_store.store_component(component)
     }
 
+    #[inline]
+    fn rotate_component_key(
+        &mut self,
+        key: &ComponentKey,
+        current_cipher: XChaCha20Poly1305,
+        cipher: XChaCha20Poly1305,
+    ) -> RuntimeResult<()> {
+        Ok(self
+            .secret_store
+            .rotate_component_key(key, &current_cipher, cipher)?)
+    }
+
     #[inline]
     fn unregister_component(&mut self, key: &ComponentKey) -> RuntimeResult<()> {
+        self.component_modules.remove(key);
+        self.component_instances.remove(key);
+        self.component_contexts
+            .retain(|(component, _), _| component != key);
+        self.secret_store.remove_component(key)?;
         self.component_store.remove_component(key)
     }
+
+    fn contract_notification(
+        &mut self,
+        key: &ComponentKey,
+        contract: ContractKey,
+        state: WrappedState,
+    ) -> RuntimeResult<Vec<OutboundComponentMsg>> {
+        let inbound = InboundComponentMsg::GetContractResponse(GetContractResponse {
+            key: contract,
+            state: Some(state),
+            context: ComponentContext::default(),
+        });
+        let (results, _) =
+            self.process_messages(key, ComponentContext::default(), vec![inbound])?;
+        Ok(results)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use chacha20poly1305::aead::{AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::aead::{KeyInit, OsRng};
     use locutus_stdlib::prelude::{ContractCode, ContractInstanceId, Parameters};
     use serde::{Deserialize, Serialize};
     use std::sync::Arc;
@@ -284,6 +516,7 @@ mod test {
     use crate::{component_store::ComponentStore, ContractStore, SecretsStore, WrappedContract};
 
     const TEST_COMPONENT_1: &str = "test_component_1";
+    const TEST_COMPONENT_2: &str = "test_component_2";
 
     #[derive(Debug, Serialize, Deserialize)]
     struct SecretsContext {
@@ -302,6 +535,16 @@ mod test {
         MessageSigned(Vec<u8>),
     }
 
+    #[derive(Debug, Serialize, Deserialize)]
+    enum RandomBytesAppMessage {
+        RandomBytes(usize),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    enum RandomBytesAppResponse {
+        RandomBytes(Vec<u8>),
+    }
+
     fn setup_runtime(name: &str) -> Result<(Component, Runtime), Box<dyn std::error::Error>> {
         const TEST_PREFIX: &str = "component-api";
         let _ = tracing_subscriber::fmt().with_env_filter("info").try_init();
@@ -324,10 +567,9 @@ mod test {
 
         let key = XChaCha20Poly1305::generate_key(&mut OsRng);
         let cipher = XChaCha20Poly1305::new(&key);
-        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
         let _ = runtime
             .secret_store
-            .register_component(component.key().clone(), cipher, nonce);
+            .register_component(component.key().clone(), cipher);
 
         runtime.enable_wasi = true; // ENABLE FOR DEBUGGING; requires building for wasi
         Ok((component, runtime))
@@ -347,7 +589,7 @@ mod test {
         let create_inbox_request_msg = ApplicationMessage::new(app, payload, false);
 
         let inbound = InboundComponentMsg::ApplicationMessage(create_inbox_request_msg);
-        let outbound = runtime.inbound_app_message(component.key(), vec![inbound])?;
+        let outbound = runtime.inbound_app_message(component.key(), SessionId(0), vec![inbound])?;
         let expected_payload =
             bincode::serialize(&OutboundAppMessage::CreateInboxResponse(vec![1])).unwrap();
 
@@ -363,7 +605,7 @@ mod test {
         let please_sign_message_msg = ApplicationMessage::new(app, payload, false);
 
         let inbound = InboundComponentMsg::ApplicationMessage(please_sign_message_msg);
-        let outbound = runtime.inbound_app_message(component.key(), vec![inbound])?;
+        let outbound = runtime.inbound_app_message(component.key(), SessionId(0), vec![inbound])?;
         let expected_payload =
             bincode::serialize(&OutboundAppMessage::MessageSigned(vec![4, 5, 2])).unwrap();
         assert_eq!(outbound.len(), 1);
@@ -373,4 +615,31 @@ mod test {
         ));
         Ok(())
     }
+
+    #[test]
+    fn random_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let (component, mut runtime) = setup_runtime(TEST_COMPONENT_2)?;
+        let app = ContractInstanceId::try_from(['a'; 32].into_iter().collect::<String>()).unwrap();
+
+        let payload = bincode::serialize(&RandomBytesAppMessage::RandomBytes(32)).unwrap();
+        let inbound =
+            InboundComponentMsg::ApplicationMessage(ApplicationMessage::new(app, payload, false));
+        let outbound = runtime.inbound_app_message(component.key(), SessionId(0), vec![inbound])?;
+        assert_eq!(outbound.len(), 1);
+        let Some(OutboundComponentMsg::ApplicationMessage(msg)) = outbound.first() else {
+            return Err("Not expected output".into());
+        };
+        let RandomBytesAppResponse::RandomBytes(bytes) = bincode::deserialize(&msg.payload)?;
+        assert_eq!(bytes.len(), 32);
+
+        // requests over the limit are rejected
+        let max = runtime.config.max_random_bytes;
+        let payload = bincode::serialize(&RandomBytesAppMessage::RandomBytes(max + 1)).unwrap();
+        let inbound =
+            InboundComponentMsg::ApplicationMessage(ApplicationMessage::new(app, payload, false));
+        assert!(runtime
+            .inbound_app_message(component.key(), SessionId(0), vec![inbound])
+            .is_err());
+        Ok(())
+    }
 }
@@ -1,9 +1,14 @@
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Write, path::PathBuf, sync::Arc};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use stretto::Cache;
 
-use crate::store::{StoreEntriesContainer, StoreFsManagement};
+use crate::store::{BlobUsage, DiskQuota, StoreEntriesContainer, StoreFsManagement};
 use crate::RuntimeResult;
 use locutus_stdlib::prelude::{Component, ComponentKey};
 
@@ -32,6 +37,14 @@ impl StoreEntriesContainer for KeyToCodeMap {
     fn insert(container: &mut Self::MemContainer, key: Self::Key, value: Self::Value) {
         container.insert(key, value);
     }
+
+    fn remove(container: &mut Self::MemContainer, key: &Self::Key) {
+        container.remove(key);
+    }
+
+    fn into_entries(self) -> Vec<(Self::Key, Self::Value)> {
+        self.0
+    }
 }
 
 impl From<&DashMap<ComponentKey, ComponentCodeKey>> for KeyToCodeMap {
@@ -48,12 +61,31 @@ pub struct ComponentStore {
     components_dir: PathBuf,
     component_cache: Cache<ComponentCodeKey, Component<'static>>,
     key_to_component_part: Arc<DashMap<ComponentKey, ComponentCodeKey>>,
+    key_file: PathBuf,
+    lock_file: PathBuf,
+    usage: BlobUsage,
+    quota: Option<DiskQuota>,
 }
 
-static LOCK_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
-static KEY_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
-
-impl StoreFsManagement<KeyToCodeMap> for ComponentStore {}
+impl StoreFsManagement<KeyToCodeMap> for ComponentStore {
+    fn scan_blobs(store_dir: &Path) -> RuntimeResult<Vec<(ComponentKey, ComponentCodeKey)>> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(store_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("wasm") {
+                continue;
+            }
+            match Component::try_from(path.as_path()) {
+                Ok(component) => {
+                    let key = component.key().clone();
+                    entries.push((key.clone(), *key.code_hash()));
+                }
+                Err(err) => tracing::warn!("failed to read component {path:?}: {err}"),
+            }
+        }
+        Ok(entries)
+    }
+}
 
 impl ComponentStore {
     /// # Arguments
@@ -61,43 +93,42 @@ impl ComponentStore {
     pub fn new(components_dir: PathBuf, max_size: i64) -> RuntimeResult<Self> {
         const ERR: &str = "failed to build mem cache";
         let key_to_component_part;
-        let _ = LOCK_FILE_PATH.try_insert(components_dir.join("__LOCK"));
-        let key_file = match KEY_FILE_PATH
-            .try_insert(components_dir.join("KEY_DATA"))
-            .map_err(|(e, _)| e)
-        {
-            Ok(f) => f,
-            Err(f) => f,
-        };
+        let key_file = components_dir.join("KEY_DATA");
+        let lock_file = components_dir.join("__LOCK");
         if !key_file.exists() {
             std::fs::create_dir_all(&components_dir).map_err(|err| {
                 tracing::error!("error creating component dir: {err}");
                 err
             })?;
             key_to_component_part = Arc::new(DashMap::new());
-            File::create(components_dir.join("KEY_DATA"))?;
+            File::create(&key_file)?;
         } else {
-            let map = Self::load_from_file(
-                KEY_FILE_PATH.get().unwrap().as_path(),
-                LOCK_FILE_PATH.get().unwrap().as_path(),
-            )?;
+            let map = Self::load_or_recover(&key_file, &lock_file)?;
             key_to_component_part = Arc::new(DashMap::from_iter(map.0));
         }
-        Self::watch_changes(
-            key_to_component_part.clone(),
-            KEY_FILE_PATH.get().unwrap().as_path(),
-            LOCK_FILE_PATH.get().unwrap().as_path(),
-        )?;
+        Self::watch_changes(key_to_component_part.clone(), &key_file, &lock_file)?;
+        let usage = BlobUsage::default();
+        for entry in key_to_component_part.iter() {
+            let path = components_dir
+                .join(entry.key().encode())
+                .with_extension("wasm");
+            usage.track(*entry.value(), &path);
+        }
         Ok(Self {
             component_cache: Cache::new(100, max_size).expect(ERR),
             components_dir,
             key_to_component_part,
+            key_file,
+            lock_file,
+            usage,
+            quota: None,
         })
     }
 
     // Returns a copy of the component bytes if available, none otherwise.
     pub fn fetch_component(&self, key: &ComponentKey) -> Option<Component<'static>> {
         if let Some(component) = self.component_cache.get(key.code_hash()) {
+            self.usage.touch(key.code_hash());
             return Some(component.value().clone());
         }
         self.key_to_component_part.get(key).and_then(|_| {
@@ -109,6 +140,7 @@ impl ComponentStore {
             let size = component.as_ref().len() as i64;
             self.component_cache
                 .insert(*key.code_hash(), component.clone(), size);
+            self.usage.touch(key.code_hash());
             Some(component)
         })
     }
@@ -118,6 +150,7 @@ impl ComponentStore {
         let component_hash = key.code_hash();
 
         if self.component_cache.get(component_hash).is_some() {
+            self.usage.touch(component_hash);
             return Ok(());
         }
 
@@ -125,8 +158,8 @@ impl ComponentStore {
             &mut self.key_to_component_part,
             key.clone(),
             *component_hash,
-            KEY_FILE_PATH.get().unwrap(),
-            LOCK_FILE_PATH.get().unwrap().as_path(),
+            &self.key_file,
+            &self.lock_file,
         )?;
 
         let component_path = self
@@ -137,6 +170,8 @@ impl ComponentStore {
             let size = component.as_ref().len() as i64;
             self.component_cache
                 .insert(*component_hash, component.clone(), size);
+            self.usage.track(*component_hash, &component_path);
+            self.usage.touch(component_hash);
             return Ok(());
         }
 
@@ -148,14 +183,26 @@ impl ComponentStore {
 
         let mut output: Vec<u8> = Vec::with_capacity(code_size as usize);
         output.append(&mut component.as_ref().to_vec());
-        let mut file = File::create(component_path)?;
+        let mut file = File::create(&component_path)?;
         file.write_all(output.as_slice())?;
+        self.usage.track(*component_hash, &component_path);
+        self.usage.touch(component_hash);
 
         Ok(())
     }
 
+    /// Removes the component from the index and deletes its code.
     pub fn remove_component(&mut self, key: &ComponentKey) -> RuntimeResult<()> {
+        if self.key_to_component_part.contains_key(key) {
+            Self::remove(
+                &mut self.key_to_component_part,
+                key,
+                &self.key_file,
+                &self.lock_file,
+            )?;
+        }
         self.component_cache.remove(key.code_hash());
+        self.usage.remove(key.code_hash());
         let cmp_path = self
             .components_dir
             .join(key.encode())
@@ -175,6 +222,53 @@ impl ComponentStore {
     pub fn code_hash_from_key(&self, key: &ComponentKey) -> Option<ComponentCodeKey> {
         self.key_to_component_part.get(key).map(|r| *r.value())
     }
+
+    pub fn set_quota(&mut self, quota: Option<DiskQuota>) {
+        self.quota = quota;
+    }
+
+    pub fn quota(&self) -> Option<DiskQuota> {
+        self.quota
+    }
+
+    /// Size in bytes of the component code in the store.
+    pub fn disk_usage(&self) -> u64 {
+        self.usage.total_size()
+    }
+
+    /// Evicts components, in the order set by the quota policy, until their code fits in the
+    /// quota. Components for which `is_referenced` holds are never evicted.
+    ///
+    /// Returns the keys of the evicted components.
+    pub fn evict(
+        &mut self,
+        is_referenced: impl Fn(&ComponentKey) -> bool,
+    ) -> RuntimeResult<Vec<ComponentKey>> {
+        let Some(quota) = self.quota else {
+            return Ok(vec![]);
+        };
+        let mut evicted = vec![];
+        for code_hash in self.usage.eviction_order(quota.policy) {
+            if self.disk_usage() <= quota.max_size {
+                break;
+            }
+            let Some(key) = self
+                .key_to_component_part
+                .iter()
+                .find(|r| *r.value() == code_hash)
+                .map(|r| r.key().clone())
+            else {
+                self.usage.remove(&code_hash);
+                continue;
+            };
+            if is_referenced(&key) {
+                continue;
+            }
+            self.remove_component(&key)?;
+            evicted.push(key);
+        }
+        Ok(evicted)
+    }
 }
 
 impl Default for ComponentStore {
@@ -183,6 +277,10 @@ impl Default for ComponentStore {
             components_dir: Default::default(),
             component_cache: Cache::new(100, DEFAULT_MAX_SIZE).unwrap(),
             key_to_component_part: Arc::new(DashMap::new()),
+            key_file: Default::default(),
+            lock_file: Default::default(),
+            usage: BlobUsage::default(),
+            quota: None,
         }
     }
 }
@@ -204,4 +302,56 @@ mod test {
         assert!(f.is_some());
         Ok(())
     }
+
+    #[test]
+    fn independent_stores() -> Result<(), Box<dyn std::error::Error>> {
+        let first_dir = crate::tests::test_dir("component-store-first");
+        let second_dir = crate::tests::test_dir("component-store-second");
+        let mut first = ComponentStore::new(first_dir.clone(), 10_000)?;
+        let mut second = ComponentStore::new(second_dir.clone(), 10_000)?;
+        let first_component = Component::from(vec![0, 1, 2]);
+        let second_component = Component::from(vec![3, 4, 5]);
+        first.store_component(first_component.clone())?;
+        second.store_component(second_component.clone())?;
+        second.remove_component(second_component.key())?;
+
+        let first = ComponentStore::new(first_dir, 10_000)?;
+        let second = ComponentStore::new(second_dir, 10_000)?;
+        assert!(first.code_hash_from_key(first_component.key()).is_some());
+        assert!(first.code_hash_from_key(second_component.key()).is_none());
+        assert!(second.code_hash_from_key(second_component.key()).is_none());
+        assert!(second.fetch_component(first_component.key()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn evict_unreferenced() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::store::EvictionPolicy;
+
+        let mut store = ComponentStore::new(crate::tests::test_dir("component-evict"), 10_000)?;
+        let components: Vec<_> = (0..3u8).map(|i| Component::from(vec![i; 64])).collect();
+        for component in &components {
+            store.store_component(component.clone())?;
+        }
+        let usage = store.disk_usage();
+        assert!(store.evict(|_| false)?.is_empty());
+
+        // the least recently used component is referenced, so the next one is evicted
+        store.set_quota(Some(DiskQuota {
+            max_size: usage - 1,
+            policy: EvictionPolicy::LeastRecentlyUsed,
+        }));
+        let referenced = components[0].key().clone();
+        let evicted = store.evict(|key| key == &referenced)?;
+        assert_eq!(evicted, vec![components[1].key().clone()]);
+        assert!(store.disk_usage() < usage);
+        assert!(store.fetch_component(components[1].key()).is_none());
+        assert!(!store
+            .components_dir
+            .join(components[1].key().encode())
+            .with_extension("wasm")
+            .exists());
+        assert!(store.fetch_component(components[0].key()).is_some());
+        Ok(())
+    }
 }
@@ -1,10 +1,10 @@
 use locutus_stdlib::prelude::{
-    ContractInterfaceResult, ContractKey, Parameters, RelatedContracts, StateDelta, StateSummary,
-    UpdateData, UpdateModification, ValidateResult, WrappedState,
+    ContractKey, Parameters, RelatedContracts, StateDelta, StateSummary, UpdateData,
+    UpdateModification, ValidateResult, WrappedState,
 };
 use wasmer::TypedFunction;
 
-use crate::{ContractExecError, RuntimeResult};
+use crate::{ContractExecError, ContractFunction, RuntimeResult};
 
 type FfiReturnTy = i64;
 
@@ -100,19 +100,23 @@ impl ContractRuntimeInterface for crate::Runtime {
                 .instance
                 .exports
                 .get_typed_function(&self.wasm_store, "validate_state")?;
+        let call_res = validate_func.call(
+            &mut self.wasm_store,
+            param_buf_ptr as i64,
+            state_buf_ptr as i64,
+            related_buf_ptr as i64,
+        );
         let is_valid = unsafe {
-            ContractInterfaceResult::from_raw(
-                validate_func.call(
-                    &mut self.wasm_store,
-                    param_buf_ptr as i64,
-                    state_buf_ptr as i64,
-                    related_buf_ptr as i64,
-                )?,
+            self.contract_call_result(
+                &running.instance,
+                ContractFunction::ValidateState,
+                call_res,
                 &linear_mem,
-            )
+            )?
             .unwrap_validate_state_res(linear_mem)
             .map_err(Into::<ContractExecError>::into)?
         };
+        self.release_contract_instance(key, running);
         Ok(is_valid)
     }
 
@@ -142,18 +146,22 @@ impl ContractRuntimeInterface for crate::Runtime {
             .instance
             .exports
             .get_typed_function(&self.wasm_store, "validate_delta")?;
+        let call_res = validate_func.call(
+            &mut self.wasm_store,
+            param_buf_ptr as i64,
+            delta_buf_ptr as i64,
+        );
         let is_valid = unsafe {
-            ContractInterfaceResult::from_raw(
-                validate_func.call(
-                    &mut self.wasm_store,
-                    param_buf_ptr as i64,
-                    delta_buf_ptr as i64,
-                )?,
+            self.contract_call_result(
+                &running.instance,
+                ContractFunction::ValidateDelta,
+                call_res,
                 &linear_mem,
-            )
+            )?
             .unwrap_validate_delta_res(linear_mem)
             .map_err(Into::<ContractExecError>::into)?
         };
+        self.release_contract_instance(key, running);
         Ok(is_valid)
     }
 
@@ -194,19 +202,23 @@ impl ContractRuntimeInterface for crate::Runtime {
                 .instance
                 .exports
                 .get_typed_function(&self.wasm_store, "update_state")?;
+        let call_res = validate_func.call(
+            &mut self.wasm_store,
+            param_buf_ptr as i64,
+            state_buf_ptr as i64,
+            update_data_buf_ptr as i64,
+        );
         let update_res = unsafe {
-            ContractInterfaceResult::from_raw(
-                validate_func.call(
-                    &mut self.wasm_store,
-                    param_buf_ptr as i64,
-                    state_buf_ptr as i64,
-                    update_data_buf_ptr as i64,
-                )?,
+            self.contract_call_result(
+                &running.instance,
+                ContractFunction::UpdateState,
+                call_res,
                 &linear_mem,
-            )
+            )?
             .unwrap_update_state(linear_mem)
             .map_err(Into::<ContractExecError>::into)?
         };
+        self.release_contract_instance(key, running);
         Ok(update_res)
     }
 
@@ -236,19 +248,23 @@ impl ContractRuntimeInterface for crate::Runtime {
             .exports
             .get_typed_function(&self.wasm_store, "summarize_state")?;
 
+        let call_res = summary_func.call(
+            &mut self.wasm_store,
+            param_buf_ptr as i64,
+            state_buf_ptr as i64,
+        );
         let result = unsafe {
-            let int_res = ContractInterfaceResult::from_raw(
-                summary_func.call(
-                    &mut self.wasm_store,
-                    param_buf_ptr as i64,
-                    state_buf_ptr as i64,
-                )?,
+            let int_res = self.contract_call_result(
+                &running.instance,
+                ContractFunction::SummarizeState,
+                call_res,
                 &linear_mem,
-            );
+            )?;
             int_res
                 .unwrap_summarize_state(linear_mem)
                 .map_err(Into::<ContractExecError>::into)?
         };
+        self.release_contract_instance(key, running);
         Ok(result)
     }
 
@@ -284,22 +300,24 @@ impl ContractRuntimeInterface for crate::Runtime {
             .exports
             .get_typed_function(&self.wasm_store, "get_state_delta")?;
 
+        let call_res = get_state_delta_func.call(
+            &mut self.wasm_store,
+            param_buf_ptr as i64,
+            state_buf_ptr as i64,
+            summary_buf_ptr as i64,
+        );
         let result = unsafe {
-            let int_res = {
-                ContractInterfaceResult::from_raw(
-                    get_state_delta_func.call(
-                        &mut self.wasm_store,
-                        param_buf_ptr as i64,
-                        state_buf_ptr as i64,
-                        summary_buf_ptr as i64,
-                    )?,
-                    &linear_mem,
-                )
-            };
+            let int_res = self.contract_call_result(
+                &running.instance,
+                ContractFunction::GetStateDelta,
+                call_res,
+                &linear_mem,
+            )?;
             int_res
                 .unwrap_get_state_delta(linear_mem)
                 .map_err(Into::<ContractExecError>::into)?
         };
+        self.release_contract_instance(key, running);
         Ok(result)
     }
 }
@@ -1,4 +1,10 @@
-use std::{fs::File, io::Write, iter::FromIterator, path::PathBuf, sync::Arc};
+use std::{
+    fs::File,
+    io::Write,
+    iter::FromIterator,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use byteorder::{BigEndian, WriteBytesExt};
 use dashmap::DashMap;
@@ -7,7 +13,7 @@ use semver::Version;
 use serde::{Deserialize, Serialize};
 use stretto::Cache;
 
-use crate::store::{StoreEntriesContainer, StoreFsManagement};
+use crate::store::{BlobUsage, DiskQuota, StoreEntriesContainer, StoreFsManagement};
 use crate::{error::RuntimeInnerError, ContractContainer, RuntimeResult, WasmAPIVersion};
 
 use super::ContractKey;
@@ -35,6 +41,14 @@ impl StoreEntriesContainer for KeyToCodeMap {
     fn insert(container: &mut Self::MemContainer, key: Self::Key, value: Self::Value) {
         container.insert(key, value);
     }
+
+    fn remove(container: &mut Self::MemContainer, key: &Self::Key) {
+        container.remove(key);
+    }
+
+    fn into_entries(self) -> Vec<(Self::Key, Self::Value)> {
+        self.0
+    }
 }
 
 impl From<&DashMap<ContractKey, ContractCodeKey>> for KeyToCodeMap {
@@ -48,17 +62,21 @@ impl From<&DashMap<ContractKey, ContractCodeKey>> for KeyToCodeMap {
 }
 
 /// Handle contract blob storage on the file system.
+///
+/// Optionally the disk space used by the code blobs can be limited with a [`DiskQuota`],
+/// see [`ContractStore::evict`].
 pub struct ContractStore {
     contracts_dir: PathBuf,
     contract_cache: Cache<ContractCodeKey, Arc<ContractCode<'static>>>,
     key_to_code_part: Arc<DashMap<ContractKey, ContractCodeKey>>,
+    key_file: PathBuf,
+    lock_file: PathBuf,
+    usage: BlobUsage,
+    quota: Option<DiskQuota>,
 }
-// TODO: add functionality to delete old contracts which have not been used for a while
-//       to keep the total speed used under a configured threshold
-
-static LOCK_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
-static KEY_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
 
+/// Contract keys depend on the parameters of each contract instance, which are not stored
+/// along the code, so a damaged index can't be rebuilt from the code blobs.
 impl StoreFsManagement<KeyToCodeMap> for ContractStore {}
 
 impl ContractStore {
@@ -67,42 +85,46 @@ impl ContractStore {
     pub fn new(contracts_dir: PathBuf, max_size: i64) -> RuntimeResult<Self> {
         const ERR: &str = "failed to build mem cache";
         let key_to_code_part;
-        let _ = LOCK_FILE_PATH.try_insert(contracts_dir.join("__LOCK"));
-        // if the lock file exists is from a previous execution so is safe to delete it
-        let _ = std::fs::remove_file(LOCK_FILE_PATH.get().unwrap().as_path());
-        let key_file = match KEY_FILE_PATH
-            .try_insert(contracts_dir.join("KEY_DATA"))
-            .map_err(|(e, _)| e)
-        {
-            Ok(f) => f,
-            Err(f) => f,
-        };
+        let key_file = contracts_dir.join("KEY_DATA");
+        let lock_file = contracts_dir.join("__LOCK");
         if !key_file.exists() {
             std::fs::create_dir_all(&contracts_dir).map_err(|err| {
                 tracing::error!("error creating contract dir: {err}");
                 err
             })?;
             key_to_code_part = Arc::new(DashMap::new());
-            File::create(contracts_dir.join("KEY_DATA"))?;
+            File::create(&key_file)?;
         } else {
-            let map = Self::load_from_file(
-                KEY_FILE_PATH.get().unwrap().as_path(),
-                LOCK_FILE_PATH.get().unwrap().as_path(),
-            )?;
+            let map = Self::load_or_recover(&key_file, &lock_file)?;
             key_to_code_part = Arc::new(DashMap::from_iter(map.0));
         }
-        Self::watch_changes(
-            key_to_code_part.clone(),
-            KEY_FILE_PATH.get().unwrap().as_path(),
-            LOCK_FILE_PATH.get().unwrap().as_path(),
-        )?;
+        Self::watch_changes(key_to_code_part.clone(), &key_file, &lock_file)?;
+        let usage = BlobUsage::default();
+        for entry in key_to_code_part.iter() {
+            usage.track(
+                *entry.value(),
+                &Self::code_path(&contracts_dir, entry.value()),
+            );
+        }
         Ok(Self {
             contract_cache: Cache::new(100, max_size).expect(ERR),
             contracts_dir,
             key_to_code_part,
+            key_file,
+            lock_file,
+            usage,
+            quota: None,
         })
     }
 
+    fn code_path(contracts_dir: &Path, code_hash: &ContractCodeKey) -> PathBuf {
+        let file_name = bs58::encode(code_hash)
+            .with_alphabet(bs58::Alphabet::BITCOIN)
+            .into_string()
+            .to_lowercase();
+        contracts_dir.join(file_name).with_extension("wasm")
+    }
+
     /// Returns a copy of the contract bytes if available, none otherwise.
     pub fn fetch_contract(
         &self,
@@ -120,16 +142,13 @@ impl ContractStore {
             })
             .flatten();
         if result.is_some() {
+            self.usage.touch(key.code_hash()?);
             return result;
         }
 
         self.key_to_code_part.get(key).and_then(|key| {
             let code_hash = key.value();
-            let path = bs58::encode(code_hash.as_slice())
-                .with_alphabet(bs58::Alphabet::BITCOIN)
-                .into_string()
-                .to_lowercase();
-            let key_path = self.contracts_dir.join(path).with_extension("wasm");
+            let key_path = Self::code_path(&self.contracts_dir, code_hash);
             let ContractContainer::Wasm(WasmAPIVersion::V1(WrappedContract {
                 data, params, ..
             })) = ContractContainer::try_from((&*key_path, params.clone().into_owned()))
@@ -141,6 +160,7 @@ impl ContractStore {
             // add back the contract part to the mem store
             let size = data.data().len() as i64;
             self.contract_cache.insert(*code_hash, data.clone(), size);
+            self.usage.touch(code_hash);
             Some(ContractContainer::Wasm(WasmAPIVersion::V1(
                 WrappedContract::new(data, params),
             )))
@@ -159,6 +179,7 @@ impl ContractStore {
             RuntimeInnerError::UnwrapContract
         })?;
         if self.contract_cache.get(contract_hash).is_some() {
+            self.usage.touch(contract_hash);
             return Ok(());
         }
 
@@ -166,19 +187,17 @@ impl ContractStore {
             &mut self.key_to_code_part,
             key.clone(),
             *contract_hash,
-            KEY_FILE_PATH.get().unwrap(),
-            LOCK_FILE_PATH.get().unwrap().as_path(),
+            &self.key_file,
+            &self.lock_file,
         )?;
 
-        let key_path = bs58::encode(contract_hash)
-            .with_alphabet(bs58::Alphabet::BITCOIN)
-            .into_string()
-            .to_lowercase();
-        let key_path = self.contracts_dir.join(key_path).with_extension("wasm");
+        let key_path = Self::code_path(&self.contracts_dir, contract_hash);
         if let Ok(code) = WrappedContract::get_data_from_fs(&key_path) {
             let size = code.data().len() as i64;
             self.contract_cache
                 .insert(*contract_hash, Arc::new(code), size);
+            self.usage.track(*contract_hash, &key_path);
+            self.usage.touch(contract_hash);
             return Ok(());
         }
 
@@ -199,12 +218,97 @@ impl ContractStore {
         output.append(&mut serialized_version);
         output.append(&mut code.data().to_vec());
 
-        let mut file = File::create(key_path)?;
+        let mut file = File::create(&key_path)?;
         file.write_all(output.as_slice())?;
+        self.usage.track(*contract_hash, &key_path);
+        self.usage.touch(contract_hash);
 
         Ok(())
     }
 
+    /// Removes the contract from the store, the code is deleted once no other contract
+    /// instance shares it.
+    pub fn remove_contract(&mut self, key: &ContractKey) -> RuntimeResult<()> {
+        let Some(code_hash) = self.code_hash_from_key(key) else {
+            return Ok(());
+        };
+        Self::remove(
+            &mut self.key_to_code_part,
+            key,
+            &self.key_file,
+            &self.lock_file,
+        )?;
+        if self
+            .key_to_code_part
+            .iter()
+            .any(|r| *r.value() == code_hash)
+        {
+            return Ok(());
+        }
+        self.remove_code(&code_hash)
+    }
+
+    fn remove_code(&mut self, code_hash: &ContractCodeKey) -> RuntimeResult<()> {
+        self.contract_cache.remove(code_hash);
+        self.usage.remove(code_hash);
+        match std::fs::remove_file(Self::code_path(&self.contracts_dir, code_hash)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_quota(&mut self, quota: Option<DiskQuota>) {
+        self.quota = quota;
+    }
+
+    pub fn quota(&self) -> Option<DiskQuota> {
+        self.quota
+    }
+
+    /// Size in bytes of the code blobs in the store.
+    pub fn disk_usage(&self) -> u64 {
+        self.usage.total_size()
+    }
+
+    /// Evicts contracts, in the order set by the quota policy, until the code blobs fit in the
+    /// quota. Code shared with any contract for which `is_referenced` holds is never evicted.
+    ///
+    /// Returns the keys of the evicted contracts.
+    pub fn evict(
+        &mut self,
+        is_referenced: impl Fn(&ContractKey) -> bool,
+    ) -> RuntimeResult<Vec<ContractKey>> {
+        let Some(quota) = self.quota else {
+            return Ok(vec![]);
+        };
+        let mut evicted = vec![];
+        for code_hash in self.usage.eviction_order(quota.policy) {
+            if self.disk_usage() <= quota.max_size {
+                break;
+            }
+            let keys: Vec<_> = self
+                .key_to_code_part
+                .iter()
+                .filter(|r| *r.value() == code_hash)
+                .map(|r| r.key().clone())
+                .collect();
+            if keys.iter().any(&is_referenced) {
+                continue;
+            }
+            for key in &keys {
+                Self::remove(
+                    &mut self.key_to_code_part,
+                    key,
+                    &self.key_file,
+                    &self.lock_file,
+                )?;
+            }
+            self.remove_code(&code_hash)?;
+            evicted.extend(keys);
+        }
+        Ok(evicted)
+    }
+
     pub fn get_contract_path(&mut self, key: &ContractKey) -> RuntimeResult<PathBuf> {
         let contract_hash = match key.code_hash() {
             Some(k) => *k,
@@ -214,21 +318,26 @@ impl ContractStore {
             })?,
         };
 
-        let key_path = bs58::encode(contract_hash)
-            .with_alphabet(bs58::Alphabet::BITCOIN)
-            .into_string()
-            .to_lowercase();
-        Ok(self.contracts_dir.join(key_path).with_extension("wasm"))
+        Ok(Self::code_path(&self.contracts_dir, &contract_hash))
     }
 
     pub fn code_hash_from_key(&self, key: &ContractKey) -> Option<ContractCodeKey> {
         self.key_to_code_part.get(key).map(|r| *r.value())
     }
+
+    /// Keys of all the contracts in the store.
+    pub fn contract_keys(&self) -> Vec<ContractKey> {
+        self.key_to_code_part
+            .iter()
+            .map(|r| r.key().clone())
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::store::EvictionPolicy;
 
     #[test]
     fn store_and_load() -> Result<(), Box<dyn std::error::Error>> {
@@ -247,4 +356,73 @@ mod test {
         assert!(f.is_some());
         Ok(())
     }
+
+    #[test]
+    fn independent_stores() -> Result<(), Box<dyn std::error::Error>> {
+        let first_dir = crate::tests::test_dir("contract-store-first");
+        let second_dir = crate::tests::test_dir("contract-store-second");
+        let mut first = ContractStore::new(first_dir.clone(), 10_000)?;
+        let mut second = ContractStore::new(second_dir.clone(), 10_000)?;
+        let contracts: Vec<_> = (0..2u8)
+            .map(|i| {
+                WrappedContract::new(
+                    Arc::new(ContractCode::from(vec![i; 8])),
+                    Parameters::from(vec![i]),
+                )
+            })
+            .collect();
+        first.store_contract(ContractContainer::Wasm(WasmAPIVersion::V1(
+            contracts[0].clone(),
+        )))?;
+        second.store_contract(ContractContainer::Wasm(WasmAPIVersion::V1(
+            contracts[1].clone(),
+        )))?;
+
+        let first = ContractStore::new(first_dir, 10_000)?;
+        let second = ContractStore::new(second_dir, 10_000)?;
+        assert_eq!(first.contract_keys(), vec![contracts[0].key().clone()]);
+        assert_eq!(second.contract_keys(), vec![contracts[1].key().clone()]);
+        Ok(())
+    }
+
+    #[test]
+    fn evict_unreferenced() -> Result<(), Box<dyn std::error::Error>> {
+        let mut store = ContractStore::new(crate::tests::test_dir("contract-evict"), 10_000)?;
+        let contracts: Vec<_> = (0..3u8)
+            .map(|i| {
+                WrappedContract::new(
+                    Arc::new(ContractCode::from(vec![i; 64])),
+                    Parameters::from(vec![i]),
+                )
+            })
+            .collect();
+        for contract in &contracts {
+            store.store_contract(ContractContainer::Wasm(WasmAPIVersion::V1(
+                contract.clone(),
+            )))?;
+        }
+        let usage = store.disk_usage();
+        assert!(store.evict(|_| false)?.is_empty());
+
+        // the least recently used contract is referenced, so the next one is evicted
+        store.set_quota(Some(DiskQuota {
+            max_size: usage - 1,
+            policy: EvictionPolicy::LeastRecentlyUsed,
+        }));
+        let referenced = contracts[0].key().clone();
+        let evicted = store.evict(|key| key == &referenced)?;
+        assert_eq!(evicted, vec![contracts[1].key().clone()]);
+        assert!(store.disk_usage() < usage);
+        assert!(store
+            .fetch_contract(contracts[1].key(), contracts[1].params())
+            .is_none());
+        assert!(!store.get_contract_path(contracts[1].key())?.exists());
+
+        store.remove_contract(contracts[2].key())?;
+        assert!(store.code_hash_from_key(contracts[2].key()).is_none());
+        assert!(store
+            .fetch_contract(contracts[0].key(), contracts[0].params())
+            .is_some());
+        Ok(())
+    }
 }
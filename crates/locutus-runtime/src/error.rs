@@ -17,6 +17,10 @@ impl ContractError {
     pub fn is_component_exec_error(&self) -> bool {
         matches!(&*self.0, RuntimeInnerError::ComponentExecError(_))
     }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(&*self.0, RuntimeInnerError::Timeout(_))
+    }
 }
 
 impl Display for ContractError {
@@ -55,10 +59,13 @@ impl_err!(wasmer_wasi::WasiStateCreationError);
 #[cfg(test)]
 impl_err!(wasmer_wasi::WasiError);
 impl_err!(wasmer::CompileError);
+impl_err!(wasmer::DeserializeError);
 impl_err!(wasmer::ExportError);
 impl_err!(wasmer::InstantiationError);
+impl_err!(wasmer::MemoryAccessError);
 impl_err!(wasmer::MemoryError);
 impl_err!(wasmer::RuntimeError);
+impl_err!(wasmer::SerializeError);
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum RuntimeInnerError {
@@ -94,7 +101,17 @@ pub(crate) enum RuntimeInnerError {
     #[error("failed while unwrapping contract to raw bytes")]
     UnwrapContract,
 
+    // async runtime errors
+    #[error("call timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("runtime worker stopped")]
+    WorkerStopped,
+
     // wasm runtime errors
+    #[error("compiler backend {0} is not enabled in this build")]
+    UnsupportedCompiler(runtime::CompilerBackend),
+
     #[cfg(test)]
     #[error(transparent)]
     WasiEnvError(#[from] wasmer_wasi::WasiStateCreationError),
@@ -106,15 +123,24 @@ pub(crate) enum RuntimeInnerError {
     #[error(transparent)]
     WasmCompileError(#[from] wasmer::CompileError),
 
+    #[error(transparent)]
+    WasmDeserializationError(#[from] wasmer::DeserializeError),
+
     #[error(transparent)]
     WasmExportError(#[from] wasmer::ExportError),
 
     #[error(transparent)]
     WasmInstantiationError(#[from] wasmer::InstantiationError),
 
+    #[error(transparent)]
+    WasmMemAccessError(#[from] wasmer::MemoryAccessError),
+
     #[error(transparent)]
     WasmMemError(#[from] wasmer::MemoryError),
 
     #[error(transparent)]
     WasmRtError(#[from] wasmer::RuntimeError),
+
+    #[error(transparent)]
+    WasmSerializationError(#[from] wasmer::SerializeError),
 }
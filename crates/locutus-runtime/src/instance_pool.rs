@@ -0,0 +1,155 @@
+//! Pool of warm WASM instances which can be reused across calls to the same module.
+
+use std::{collections::HashMap, hash::Hash};
+
+use wasmer::{FunctionEnv, Instance, Mutability, Store, Value};
+
+use crate::{native_api::HostEnv, RuntimeResult};
+
+/// Keeps up to `max_idle` idle instances per module, identified by `K`.
+///
+/// Before being returned to the pool, an instance is reset to the state it had right after
+/// instantiation: its linear memory and any exported mutable globals are restored from a
+/// snapshot taken when the first instance of the module was created. Memory grown during a
+/// call can't be released, so it is kept zeroed for the next call instead.
+pub(crate) struct InstancePool<K> {
+    max_idle: usize,
+    modules: HashMap<K, PooledModule>,
+}
+
+struct PooledModule {
+    snapshot: InstanceSnapshot,
+    idle: Vec<(Instance, FunctionEnv<HostEnv>)>,
+}
+
+struct InstanceSnapshot {
+    memory_size: usize,
+    /// memory contents split in chunks, zeroed chunks are not stored
+    memory: Vec<Option<Box<[u8]>>>,
+    globals: Vec<(String, Value)>,
+}
+
+impl<K> InstancePool<K>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            max_idle,
+            modules: HashMap::new(),
+        }
+    }
+
+    /// Takes an idle instance of the module and its host environment, if any.
+    pub fn take(&mut self, key: &K) -> Option<(Instance, FunctionEnv<HostEnv>)> {
+        self.modules.get_mut(key)?.idle.pop()
+    }
+
+    /// Registers a freshly created instance of the module so later instances can be pooled.
+    ///
+    /// Must be called before the instance executes any code.
+    pub fn track(&mut self, store: &mut Store, key: &K, instance: &Instance) -> RuntimeResult<()> {
+        if self.max_idle == 0 || self.modules.contains_key(key) {
+            return Ok(());
+        }
+        let snapshot = InstanceSnapshot::new(store, instance)?;
+        self.modules.insert(
+            key.clone(),
+            PooledModule {
+                snapshot,
+                idle: Vec::with_capacity(self.max_idle),
+            },
+        );
+        Ok(())
+    }
+
+    /// Drops the idle instances and snapshot of the module.
+    pub fn remove(&mut self, key: &K) {
+        self.modules.remove(key);
+    }
+
+    /// Resets the instance and returns it to the pool, the instance is discarded if the pool
+    /// is full or it can't be reset.
+    pub fn release(
+        &mut self,
+        store: &mut Store,
+        key: &K,
+        instance: Instance,
+        env: FunctionEnv<HostEnv>,
+    ) {
+        let Some(module) = self.modules.get_mut(key) else {
+            return;
+        };
+        if module.idle.len() >= self.max_idle {
+            return;
+        }
+        match module.snapshot.restore(store, &instance) {
+            Ok(true) => module.idle.push((instance, env)),
+            Ok(false) => {}
+            Err(err) => tracing::debug!("failed to reset instance: {err}"),
+        }
+    }
+}
+
+impl InstanceSnapshot {
+    /// Granularity at which memory is compared and restored.
+    const CHUNK_SIZE: usize = 4096;
+
+    fn new(store: &mut Store, instance: &Instance) -> RuntimeResult<Self> {
+        let view = instance.exports.get_memory("memory")?.view(store);
+        let mut data = vec![0; view.data_size() as usize];
+        view.read(0, &mut data)?;
+        let memory = data
+            .chunks(Self::CHUNK_SIZE)
+            .map(|chunk| (!is_zeroed(chunk)).then(|| chunk.into()))
+            .collect();
+
+        let mut globals = Vec::new();
+        for (name, global) in instance.exports.iter().globals() {
+            if global.ty(store).mutability == Mutability::Var {
+                globals.push((name.clone(), global.get(store)));
+            }
+        }
+        Ok(Self {
+            memory_size: data.len(),
+            memory,
+            globals,
+        })
+    }
+
+    /// Restores the instance to the snapshot state, returns whether it could be restored.
+    ///
+    /// Only the chunks of memory which changed are written back, memory grown past the
+    /// snapshot is zeroed.
+    fn restore(&self, store: &mut Store, instance: &Instance) -> RuntimeResult<bool> {
+        let view = instance.exports.get_memory("memory")?.view(store);
+        let memory_size = view.data_size() as usize;
+        if memory_size < self.memory_size {
+            return Ok(false);
+        }
+        // Safety: no other reference to the memory is alive while the instance is not running
+        let memory = unsafe { std::slice::from_raw_parts_mut(view.data_ptr(), memory_size) };
+        let (initial, grown) = memory.split_at_mut(self.memory_size);
+        for (chunk, original) in initial.chunks_mut(Self::CHUNK_SIZE).zip(&self.memory) {
+            match original {
+                Some(original) if **original != *chunk => chunk.copy_from_slice(original),
+                None if !is_zeroed(chunk) => chunk.fill(0),
+                _ => {}
+            }
+        }
+        for chunk in grown.chunks_mut(Self::CHUNK_SIZE) {
+            if !is_zeroed(chunk) {
+                chunk.fill(0);
+            }
+        }
+        for (name, value) in &self.globals {
+            let global = instance.exports.get_global(name)?;
+            global.set(store, value.clone())?;
+        }
+        Ok(true)
+    }
+}
+
+fn is_zeroed(chunk: &[u8]) -> bool {
+    chunk.iter().fold(0, |acc, b| acc | b) == 0
+}
@@ -1,17 +1,22 @@
 extern crate core;
 
+mod async_runtime;
 mod component;
 mod component_store;
 mod contract;
 mod contract_store;
 pub(crate) mod error;
+mod instance_pool;
+mod module_cache;
 mod native_api;
+mod profiling;
 mod runtime;
 mod secrets_store;
 mod state_store;
 mod store;
 #[cfg(test)]
 pub(crate) mod tests;
+mod tunables;
 pub mod util;
 
 type DynError = Box<dyn std::error::Error + Send + Sync>;
@@ -20,14 +25,21 @@ pub use locutus_stdlib;
 pub use prelude::*;
 
 pub mod prelude {
-    pub use super::component::{ComponentExecError, ComponentRuntimeInterface};
+    pub use super::async_runtime::AsyncRuntime;
+    pub use super::component::{
+        ComponentExecError, ComponentRuntimeInterface, ContractAccess, SessionId,
+    };
     pub use super::component_store::ComponentStore;
     pub use super::contract::ContractRuntimeInterface;
     pub use super::contract_store::ContractStore;
     pub use super::error::ContractError;
     pub use super::error::RuntimeResult;
-    pub use super::runtime::{ContractExecError, Runtime};
+    pub use super::profiling::{ContractFunction, ContractProfile, FunctionStats};
+    pub use super::runtime::{
+        CompilerBackend, ContractExecError, Determinism, Runtime, RuntimeConfig,
+    };
     pub use super::secrets_store::SecretsStore;
     pub use super::state_store::{StateStorage, StateStore, StateStoreError};
+    pub use super::store::{DiskQuota, EvictionPolicy};
     pub use locutus_stdlib::prelude::*;
 }
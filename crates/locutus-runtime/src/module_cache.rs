@@ -0,0 +1,296 @@
+//! On-disk cache of compiled WASM modules.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::RuntimeResult;
+
+/// Persists compiled module artifacts in a directory, so modules don't need to be compiled
+/// again after a restart.
+///
+/// The artifacts are stored in a subdirectory named after the fingerprint of the runtime
+/// which compiled them, so runtimes with different versions or configurations can share the
+/// cache directory. The artifacts of other fingerprints are kept, unless unused for
+/// [`STALE_FINGERPRINT_AGE`] or, oldest first, once the cache is over the size budget. After
+/// those the least recently used artifacts are evicted.
+pub(crate) struct ModuleCache {
+    dir: PathBuf,
+    max_size: u64,
+    size: u64,
+    entries: HashMap<String, CacheEntry>,
+    /// artifacts of other fingerprints, oldest first
+    others: VecDeque<OtherFingerprint>,
+}
+
+struct CacheEntry {
+    size: u64,
+    last_used: SystemTime,
+}
+
+struct OtherFingerprint {
+    dir: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+const ARTIFACT_EXT: &str = "bin";
+
+/// Time after which the artifacts of other fingerprints are removed from the cache.
+const STALE_FINGERPRINT_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+impl ModuleCache {
+    /// # Arguments
+    /// - cache_dir: root directory of the cache, shared by all runtime versions
+    /// - fingerprint: identifies the runtime and configuration artifacts are compiled with
+    /// - max_size: max size in bytes of the artifacts kept in the cache
+    pub fn open(cache_dir: &Path, fingerprint: &str, max_size: u64) -> RuntimeResult<Self> {
+        fs::create_dir_all(cache_dir)?;
+        let mut others = vec![];
+        for entry in fs::read_dir(cache_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() || entry.file_name() == fingerprint {
+                continue;
+            }
+            let path = entry.path();
+            let (size, last_used) = dir_usage(&path);
+            let unused = SystemTime::now()
+                .duration_since(last_used)
+                .unwrap_or_default();
+            if unused < STALE_FINGERPRINT_AGE {
+                others.push(OtherFingerprint {
+                    dir: path,
+                    size,
+                    last_used,
+                });
+                continue;
+            }
+            tracing::debug!("removing stale module cache at {path:?}");
+            if let Err(err) = fs::remove_dir_all(&path) {
+                tracing::warn!("failed to remove stale module cache at {path:?}: {err}");
+            }
+        }
+        others.sort_by_key(|other| other.last_used);
+
+        let dir = cache_dir.join(fingerprint);
+        fs::create_dir_all(&dir)?;
+        let mut entries = HashMap::new();
+        let mut size = others.iter().map(|other| other.size).sum();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ARTIFACT_EXT) {
+                // leftovers from interrupted writes
+                if let Err(err) = fs::remove_file(&path) {
+                    tracing::debug!("failed to remove {path:?} from the module cache: {err}");
+                }
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            size += metadata.len();
+            entries.insert(
+                key.to_owned(),
+                CacheEntry {
+                    size: metadata.len(),
+                    last_used: last_used(&metadata),
+                },
+            );
+        }
+
+        let mut cache = Self {
+            dir,
+            max_size,
+            size,
+            entries,
+            others: others.into(),
+        };
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// Returns the artifact for the module, if cached.
+    pub fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        let entry = self.entries.get_mut(key)?;
+        match fs::read(self.dir.join(key).with_extension(ARTIFACT_EXT)) {
+            Ok(artifact) => {
+                entry.last_used = SystemTime::now();
+                Some(artifact)
+            }
+            Err(err) => {
+                tracing::debug!("failed to read cached module {key}: {err}");
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: &str, artifact: &[u8]) -> RuntimeResult<()> {
+        self.remove(key);
+        let path = self.dir.join(key).with_extension(ARTIFACT_EXT);
+        // write to a temporary file first so a crash never leaves a truncated artifact behind
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(artifact)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        let size = artifact.len() as u64;
+        self.size += size;
+        self.entries.insert(
+            key.to_owned(),
+            CacheEntry {
+                size,
+                last_used: SystemTime::now(),
+            },
+        );
+        self.evict();
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size -= entry.size;
+            let _ = fs::remove_file(self.dir.join(key).with_extension(ARTIFACT_EXT));
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            let Some(other) = self.others.pop_front() else {
+                break;
+            };
+            tracing::debug!("evicting module cache at {:?}", other.dir);
+            self.size -= other.size;
+            if let Err(err) = fs::remove_dir_all(&other.dir) {
+                tracing::warn!("failed to remove module cache at {:?}: {err}", other.dir);
+            }
+        }
+        while self.size > self.max_size {
+            let Some(lru) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            tracing::debug!("evicting module {lru} from cache");
+            self.remove(&lru);
+        }
+    }
+}
+
+fn last_used(metadata: &fs::Metadata) -> SystemTime {
+    metadata
+        .accessed()
+        .or_else(|_| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Size of the files in the directory and when the most recently used one was last used.
+fn dir_usage(dir: &Path) -> (u64, SystemTime) {
+    let mut usage = (0, SystemTime::UNIX_EPOCH);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return usage;
+    };
+    for metadata in entries.filter_map(|entry| entry.ok()?.metadata().ok()) {
+        if metadata.is_file() {
+            usage.0 += metadata.len();
+            usage.1 = usage.1.max(last_used(&metadata));
+        }
+    }
+    usage
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evict_least_recently_used() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir = crate::tests::test_dir("module-cache");
+        let mut cache = ModuleCache::open(&cache_dir, "v1", 10)?;
+        cache.insert("a", &[0; 4])?;
+        cache.insert("b", &[1; 4])?;
+        assert_eq!(cache.get("a"), Some(vec![0; 4]));
+        cache.insert("c", &[2; 4])?;
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+        assert!(cache.contains("c"));
+
+        let cache = ModuleCache::open(&cache_dir, "v1", 10)?;
+        assert!(cache.contains("a"));
+        assert!(cache.contains("c"));
+        Ok(())
+    }
+
+    #[test]
+    fn keep_other_versions() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir = crate::tests::test_dir("module-cache");
+        let mut cache = ModuleCache::open(&cache_dir, "v1", 1024)?;
+        cache.insert("a", &[0; 4])?;
+        let mut cache = ModuleCache::open(&cache_dir, "v2", 1024)?;
+        cache.insert("b", &[1; 4])?;
+        let cache = ModuleCache::open(&cache_dir, "v1", 1024)?;
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("b"));
+
+        // other versions are evicted first once over the size budget
+        let mut cache = ModuleCache::open(&cache_dir, "v3", 10)?;
+        assert!(cache_dir.join("v1").exists());
+        assert!(cache_dir.join("v2").exists());
+        cache.insert("c", &[2; 4])?;
+        assert!(!cache_dir.join("v1").exists());
+        assert!(cache_dir.join("v2").exists());
+        assert!(cache.contains("c"));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_stale_versions() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir = crate::tests::test_dir("module-cache");
+        let mut cache = ModuleCache::open(&cache_dir, "v1", 1024)?;
+        cache.insert("a", &[0; 4])?;
+        let unused_since = SystemTime::now() - STALE_FINGERPRINT_AGE - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(cache_dir.join("v1").join("a").with_extension(ARTIFACT_EXT))?
+            .set_times(
+                fs::FileTimes::new()
+                    .set_accessed(unused_since)
+                    .set_modified(unused_since),
+            )?;
+        ModuleCache::open(&cache_dir, "v2", 1024)?;
+        assert!(!cache_dir.join("v1").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn skip_unknown_entries() -> Result<(), Box<dyn std::error::Error>> {
+        let cache_dir = crate::tests::test_dir("module-cache");
+        let mut cache = ModuleCache::open(&cache_dir, "v1", 1024)?;
+        cache.insert("a", &[0; 4])?;
+        fs::write(cache_dir.join("readme"), "")?;
+        fs::create_dir(cache_dir.join("v1").join("subdir"))?;
+        fs::write(cache_dir.join("v1").join("b.tmp"), [1; 4])?;
+        let cache = ModuleCache::open(&cache_dir, "v1", 1024)?;
+        assert!(cache.contains("a"));
+        assert!(!cache.contains("subdir"));
+        assert!(!cache_dir.join("v1").join("b.tmp").exists());
+        Ok(())
+    }
+}
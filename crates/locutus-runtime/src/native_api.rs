@@ -1,45 +1,298 @@
 //! Implementation of native API's exported and available in the WASM modules.
 
-use dashmap::DashMap;
-use once_cell::sync::Lazy;
+use chrono::{DateTime, Utc};
+use locutus_stdlib::prelude::ComponentContext;
+use wasmer::{FunctionEnv, Imports, Memory, RuntimeError, Store};
 
-/// This is a map of starting addresses of the instance memory space.
+use self::log::LogSource;
+
+/// Host functions whose results are not determined by the inputs of a contract call.
+pub(crate) const NON_DETERMINISTIC_IMPORTS: &[(&str, &str)] = &[("locutus_time", "utc_now")];
+
+/// State of an instance accessible from the host functions it imports.
 ///
-/// A hackish way of having the information necessary to compute the address
-/// at which bytes must be written when calling host functions from the WASM modules.
-pub(crate) static MEM_ADDR: Lazy<DashMap<InstanceId, i64>> = Lazy::new(DashMap::default);
+/// Every instance has its own environment, so host functions only ever access the memory of
+/// the instance calling them.
+#[derive(Default)]
+pub(crate) struct HostEnv {
+    /// linear memory of the instance, set right after instantiation
+    memory: Option<Memory>,
+    /// time returned by `utc_now` instead of the current time
+    pub fixed_time: Option<DateTime<Utc>>,
+    /// contract or component which emitted the logged events
+    pub log_source: Option<LogSource>,
+    /// context of the client session a component is processing messages for
+    pub component_context: Option<ComponentContext>,
+}
+
+impl HostEnv {
+    pub fn set_memory(&mut self, memory: Memory) {
+        self.memory = Some(memory);
+    }
+
+    /// Returns the memory of the instance, any access through its views is bounds-checked.
+    fn memory(&self) -> Result<Memory, RuntimeError> {
+        self.memory
+            .clone()
+            .ok_or_else(|| RuntimeError::new("instance memory not set"))
+    }
+}
 
-type InstanceId = i64;
+/// Creates the host functions imported by an instance, bound to its environment.
+pub(crate) fn imports(store: &mut Store, env: &FunctionEnv<HostEnv>) -> Imports {
+    let mut imports = Imports::new();
+    time::prepare_export(store, env, &mut imports);
+    log::prepare_export(store, env, &mut imports);
+    component::prepare_export(store, env, &mut imports);
+    imports
+}
 
-#[inline(always)]
-fn compute_ptr<T>(ptr: i64, start_ptr: i64) -> *mut T {
-    (start_ptr + ptr) as _
+fn out_of_bounds(ptr: i64, len: usize) -> RuntimeError {
+    RuntimeError::new(format!(
+        "access to {len} bytes at {ptr} out of the instance memory bounds"
+    ))
 }
 
 pub(crate) mod time {
     use super::*;
-    use chrono::{DateTime, Utc as UtcOriginal};
-    use wasmer::{Function, Imports};
+    use wasmer::{Function, FunctionEnvMut};
 
-    pub(crate) fn prepare_export(store: &mut wasmer::Store, imports: &mut Imports) {
-        let utc_now = Function::new_typed(store, utc_now);
+    pub(crate) fn prepare_export(
+        store: &mut Store,
+        env: &FunctionEnv<HostEnv>,
+        imports: &mut Imports,
+    ) {
+        let utc_now = Function::new_typed_with_env(store, env, utc_now);
         imports.register_namespace("locutus_time", [("utc_now".to_owned(), utc_now.into())]);
     }
 
-    fn utc_now(id: i64, ptr: i64) {
-        if id == -1 {
-            panic!("unset module id");
-        }
-        let start_ptr = *MEM_ADDR
-            .get(&id)
-            .expect("instance mem space not recorded")
-            .value();
-        let now = UtcOriginal::now();
-        let ptr = compute_ptr::<DateTime<UtcOriginal>>(ptr, start_ptr);
-        // eprintln!("{ptr:p} ({}) outside", ptr as i64);
-        unsafe {
-            let ptr = ptr as *mut DateTime<UtcOriginal>;
-            ptr.write(now);
+    fn utc_now(env: FunctionEnvMut<HostEnv>, _id: i64, ptr: i64) -> Result<(), RuntimeError> {
+        let now = env.data().fixed_time.unwrap_or_else(Utc::now);
+        // the guest reads back the in-memory representation of the value
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &now as *const DateTime<Utc> as *const u8,
+                std::mem::size_of::<DateTime<Utc>>(),
+            )
         };
+        // a new view is created on every call, so it always reflects the current memory size
+        env.data()
+            .memory()?
+            .view(&env)
+            .write(ptr as u64, bytes)
+            .map_err(|_| out_of_bounds(ptr, bytes.len()))
+    }
+}
+
+pub(crate) mod component {
+    use super::*;
+    use wasmer::{Function, FunctionEnvMut};
+
+    pub(crate) fn prepare_export(
+        store: &mut Store,
+        env: &FunctionEnv<HostEnv>,
+        imports: &mut Imports,
+    ) {
+        let get_context = Function::new_typed_with_env(store, env, get_context);
+        let set_context = Function::new_typed_with_env(store, env, set_context);
+        imports.register_namespace(
+            "locutus_component",
+            [
+                ("get_context".to_owned(), get_context.into()),
+                ("set_context".to_owned(), set_context.into()),
+            ],
+        );
+    }
+
+    /// Writes up to `len` bytes of the context at `ptr`, returns the length of the context.
+    fn get_context(
+        env: FunctionEnvMut<HostEnv>,
+        _id: i64,
+        ptr: i64,
+        len: i32,
+    ) -> Result<i32, RuntimeError> {
+        let Some(context) = &env.data().component_context else {
+            return Err(RuntimeError::new(
+                "no component context outside of a component call",
+            ));
+        };
+        let len = usize::try_from(len).map_err(|_| out_of_bounds(ptr, 0))?;
+        let bytes = &context.0[..len.min(context.0.len())];
+        env.data()
+            .memory()?
+            .view(&env)
+            .write(ptr as u64, bytes)
+            .map_err(|_| out_of_bounds(ptr, bytes.len()))?;
+        Ok(context.0.len() as i32)
+    }
+
+    fn set_context(
+        mut env: FunctionEnvMut<HostEnv>,
+        _id: i64,
+        ptr: i64,
+        len: i32,
+    ) -> Result<(), RuntimeError> {
+        if env.data().component_context.is_none() {
+            return Err(RuntimeError::new(
+                "no component context outside of a component call",
+            ));
+        }
+        let len = usize::try_from(len).map_err(|_| out_of_bounds(ptr, 0))?;
+        if len >= ComponentContext::MAX_SIZE {
+            return Err(RuntimeError::new(format!(
+                "component context of {len} bytes over the limit of {} bytes",
+                ComponentContext::MAX_SIZE
+            )));
+        }
+        let mut bytes = vec![0; len];
+        env.data()
+            .memory()?
+            .view(&env)
+            .read(ptr as u64, &mut bytes)
+            .map_err(|_| out_of_bounds(ptr, len))?;
+        env.data_mut().component_context = Some(ComponentContext::new(bytes));
+        Ok(())
+    }
+}
+
+pub(crate) mod log {
+    use std::{
+        fmt::Display,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use locutus_stdlib::{
+        log::{LogEvent, LogLevel},
+        prelude::{ComponentKey, ContractKey},
+    };
+    use wasmer::{Function, FunctionEnvMut};
+
+    use super::*;
+
+    pub(crate) struct LogSource {
+        pub tag: LogTag,
+        pub limit: Arc<Mutex<RateLimit>>,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub(crate) enum LogTag {
+        Contract(ContractKey),
+        Component(ComponentKey),
+    }
+
+    impl Display for LogTag {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                LogTag::Contract(key) => write!(f, "contract {key}"),
+                LogTag::Component(key) => write!(f, "component {key}"),
+            }
+        }
+    }
+
+    /// Caps the number of events logged per second, the rest are dropped.
+    pub(crate) struct RateLimit {
+        max_events: u32,
+        window_start: Instant,
+        events: u32,
+        pub dropped: u32,
+    }
+
+    impl RateLimit {
+        const WINDOW: Duration = Duration::from_secs(1);
+
+        pub fn new(max_events: u32) -> Self {
+            Self {
+                max_events,
+                window_start: Instant::now(),
+                events: 0,
+                dropped: 0,
+            }
+        }
+
+        /// Returns whether another event can be logged, and the number of events dropped
+        /// during the previous window if it just elapsed.
+        fn acquire(&mut self) -> (bool, u32) {
+            let mut dropped = 0;
+            if self.window_start.elapsed() >= Self::WINDOW {
+                dropped = std::mem::take(&mut self.dropped);
+                self.window_start = Instant::now();
+                self.events = 0;
+            }
+            if self.events < self.max_events {
+                self.events += 1;
+                (true, dropped)
+            } else {
+                self.dropped += 1;
+                (false, dropped)
+            }
+        }
+    }
+
+    pub(crate) fn prepare_export(
+        store: &mut Store,
+        env: &FunctionEnv<HostEnv>,
+        imports: &mut Imports,
+    ) {
+        let log_event = Function::new_typed_with_env(store, env, log_event);
+        imports.register_namespace("locutus_log", [("log_event".to_owned(), log_event.into())]);
+    }
+
+    fn log_event(
+        env: FunctionEnvMut<HostEnv>,
+        _id: i64,
+        ptr: i64,
+        len: i32,
+    ) -> Result<(), RuntimeError> {
+        let Some(source) = &env.data().log_source else {
+            return Ok(());
+        };
+        let (allowed, dropped) = source.limit.lock().unwrap().acquire();
+        if dropped > 0 {
+            tracing::warn!(source = %source.tag, "dropped {dropped} log events over the rate limit");
+        }
+        if !allowed {
+            return Ok(());
+        }
+        let len = usize::try_from(len).map_err(|_| out_of_bounds(ptr, 0))?;
+        let mut bytes = vec![0; len];
+        env.data()
+            .memory()?
+            .view(&env)
+            .read(ptr as u64, &mut bytes)
+            .map_err(|_| out_of_bounds(ptr, len))?;
+        match bincode::deserialize::<LogEvent>(&bytes) {
+            Ok(event) => emit(&source.tag, event),
+            Err(err) => tracing::debug!(source = %source.tag, "invalid log event: {err}"),
+        }
+        Ok(())
+    }
+
+    fn emit(tag: &LogTag, event: LogEvent) {
+        let fields = event
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    $level,
+                    source = %tag,
+                    target = %event.target,
+                    fields,
+                    "{}",
+                    event.message
+                )
+            };
+        }
+        match event.level {
+            LogLevel::Trace => emit!(tracing::Level::TRACE),
+            LogLevel::Debug => emit!(tracing::Level::DEBUG),
+            LogLevel::Info => emit!(tracing::Level::INFO),
+            LogLevel::Warn => emit!(tracing::Level::WARN),
+            LogLevel::Error => emit!(tracing::Level::ERROR),
+        }
     }
 }
@@ -0,0 +1,140 @@
+//! Opt-in profiling of contract calls, enabled through [`RuntimeConfig::profiling`].
+//!
+//! [`RuntimeConfig::profiling`]: crate::RuntimeConfig::profiling
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use locutus_stdlib::prelude::ContractKey;
+use serde::{Serialize, Serializer};
+
+/// Function of the contract interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractFunction {
+    ValidateState,
+    ValidateDelta,
+    UpdateState,
+    SummarizeState,
+    GetStateDelta,
+}
+
+impl Display for ContractFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ContractFunction::ValidateState => "validate_state",
+            ContractFunction::ValidateDelta => "validate_delta",
+            ContractFunction::UpdateState => "update_state",
+            ContractFunction::SummarizeState => "summarize_state",
+            ContractFunction::GetStateDelta => "get_state_delta",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Aggregated measurements of the calls to a contract function.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FunctionStats {
+    pub calls: u64,
+    /// calls which trapped or ran out of gas
+    pub failed_calls: u64,
+    /// time from preparing the instance to getting the call result
+    #[serde(rename = "wall_time_us", serialize_with = "as_micros")]
+    pub wall_time: Duration,
+    #[serde(rename = "max_wall_time_us", serialize_with = "as_micros")]
+    pub max_wall_time: Duration,
+    /// calls which couldn't reuse a pooled instance
+    pub instantiations: u64,
+    #[serde(rename = "instantiation_time_us", serialize_with = "as_micros")]
+    pub instantiation_time: Duration,
+    /// bytes copied into the instance buffers
+    pub bytes_in: u64,
+    /// bytes of the results read from the instance
+    pub bytes_out: u64,
+    /// max linear memory, in WASM pages, used by an instance at the end of a call
+    pub max_memory_pages: u32,
+    /// gas consumed, each operation is metered as an instruction
+    pub gas: u64,
+}
+
+fn as_micros<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_micros())
+}
+
+/// Measurements of a single call to a contract, taken while it runs.
+pub(crate) struct CallSample {
+    pub key: ContractKey,
+    pub start: Instant,
+    pub instantiation_time: Option<Duration>,
+    pub bytes_in: u64,
+}
+
+/// Stats of the calls to every contract run since profiling was enabled.
+#[derive(Debug, Default)]
+pub struct ContractProfile {
+    stats: HashMap<ContractKey, HashMap<ContractFunction, FunctionStats>>,
+}
+
+impl ContractProfile {
+    pub fn get(&self, key: &ContractKey, function: ContractFunction) -> Option<&FunctionStats> {
+        self.stats.get(key)?.get(&function)
+    }
+
+    /// Stats of every function of the contract called at least once.
+    pub fn contract(
+        &self,
+        key: &ContractKey,
+    ) -> impl Iterator<Item = (ContractFunction, &FunctionStats)> {
+        self.stats
+            .get(key)
+            .into_iter()
+            .flat_map(|functions| functions.iter().map(|(f, stats)| (*f, stats)))
+    }
+
+    pub fn clear(&mut self) {
+        self.stats.clear();
+    }
+
+    /// Exports the stats as a JSON object keyed by contract key and function name.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let stats: BTreeMap<_, BTreeMap<_, _>> = self
+            .stats
+            .iter()
+            .map(|(key, functions)| (key.to_string(), functions.iter().collect()))
+            .collect();
+        serde_json::to_string_pretty(&stats)
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        sample: CallSample,
+        function: ContractFunction,
+        failed: bool,
+        bytes_out: u64,
+        memory_pages: u32,
+        gas: u64,
+    ) {
+        let wall_time = sample.start.elapsed();
+        let stats = self
+            .stats
+            .entry(sample.key)
+            .or_default()
+            .entry(function)
+            .or_default();
+        stats.calls += 1;
+        stats.failed_calls += failed as u64;
+        stats.wall_time += wall_time;
+        stats.max_wall_time = stats.max_wall_time.max(wall_time);
+        if let Some(instantiation_time) = sample.instantiation_time {
+            stats.instantiations += 1;
+            stats.instantiation_time += instantiation_time;
+        }
+        stats.bytes_in += sample.bytes_in;
+        stats.bytes_out += bytes_out;
+        stats.max_memory_pages = stats.max_memory_pages.max(memory_pages);
+        stats.gas += gas;
+    }
+}
@@ -1,46 +1,51 @@
-use std::{collections::HashMap, sync::atomic::AtomicI64};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use blake2::{Blake2s256, Digest};
+use chrono::{DateTime, Utc};
 
 use locutus_stdlib::{
     buf::{BufferBuilder, BufferMut},
     prelude::*,
 };
-use wasmer::{imports, Bytes, Imports, Instance, Memory, MemoryType, Module, Store, TypedFunction};
+use wasmer::{
+    namespace, wasmparser::Operator, BaseTunables, Bytes, CompilerConfig, EngineBuilder,
+    FunctionEnv, Imports, Instance, Memory, MemoryType, Module, Pages, RuntimeError, Store, Target,
+    TypedFunction,
+};
+use wasmer_middlewares::{
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    Metering,
+};
 
 use crate::{
-    component_store::ComponentStore, contract_store::ContractStore, error::RuntimeInnerError,
-    native_api, secrets_store::SecretsStore, RuntimeResult,
+    component::{ContractAccess, SessionId},
+    component_store::ComponentStore,
+    contract_store::ContractStore,
+    error::RuntimeInnerError,
+    instance_pool::InstancePool,
+    module_cache::ModuleCache,
+    native_api::{
+        self,
+        log::{LogSource, LogTag, RateLimit},
+        HostEnv,
+    },
+    profiling::{CallSample, ContractFunction, ContractProfile},
+    secrets_store::SecretsStore,
+    tunables::LimitingTunables,
+    RuntimeResult,
 };
 
-static INSTANCE_ID: AtomicI64 = AtomicI64::new(0);
-
 pub(crate) struct RunningInstance {
-    pub id: i64,
     pub instance: Instance,
-}
-
-impl Drop for RunningInstance {
-    fn drop(&mut self) {
-        let _ = native_api::MEM_ADDR.remove(&self.id);
-    }
-}
-
-impl RunningInstance {
-    fn new(rt: &mut Runtime, instance: Instance) -> RuntimeResult<Self> {
-        let memory = rt
-            .host_memory
-            .as_ref()
-            .map(Ok)
-            .unwrap_or_else(|| instance.exports.get_memory("memory"))?;
-        let set_id: TypedFunction<i64, ()> = instance
-            .exports
-            .get_typed_function(&rt.wasm_store, "__locutus_set_id")
-            .unwrap();
-        let id = INSTANCE_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        set_id.call(&mut rt.wasm_store, id).unwrap();
-        let ptr = memory.view(&rt.wasm_store).data_ptr() as i64;
-        native_api::MEM_ADDR.insert(id, ptr);
-        Ok(Self { instance, id })
-    }
+    /// environment of the host functions imported by the instance
+    pub env: FunctionEnv<HostEnv>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -54,6 +59,15 @@ pub enum ContractExecError {
     #[error("insufficient memory, needed {req} bytes but had {free} bytes")]
     InsufficientMemory { req: usize, free: usize },
 
+    #[error("contract execution ran out of gas (limit: {0} units)")]
+    OutOfGas(u64),
+
+    #[error("contract imports non-deterministic host function {module}.{name}")]
+    NonDeterministicImport { module: String, name: String },
+
+    #[error("deterministic execution requires the host to set the time of the call")]
+    MissingCallTime,
+
     #[error("could not cast array length of {0} to max size (i32::MAX)")]
     InvalidArrayLength(usize),
 
@@ -61,25 +75,241 @@ pub enum ContractExecError {
     UnexpectedResult,
 }
 
+/// Compiler used to translate contract and component modules to native code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompilerBackend {
+    /// Optimizing compiler, generates faster code but compilation time can grow
+    /// non-linearly with the size of the module.
+    Cranelift,
+    /// Compiles in linear time, preferable when running untrusted modules.
+    Singlepass,
+}
+
+impl CompilerBackend {
+    fn compiler_config(self, canonicalize_nans: bool) -> RuntimeResult<Box<dyn CompilerConfig>> {
+        match self {
+            #[cfg(feature = "cranelift")]
+            Self::Cranelift => {
+                let mut compiler = wasmer::Cranelift::new();
+                compiler.canonicalize_nans(canonicalize_nans);
+                Ok(Box::new(compiler))
+            }
+            #[cfg(feature = "singlepass")]
+            Self::Singlepass => {
+                let mut compiler = wasmer::Singlepass::new();
+                compiler.canonicalize_nans(canonicalize_nans);
+                Ok(Box::new(compiler))
+            }
+            #[allow(unreachable_patterns)]
+            backend => Err(RuntimeInnerError::UnsupportedCompiler(backend).into()),
+        }
+    }
+}
+
+impl Default for CompilerBackend {
+    fn default() -> Self {
+        if cfg!(feature = "cranelift") {
+            Self::Cranelift
+        } else {
+            Self::Singlepass
+        }
+    }
+}
+
+impl Display for CompilerBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cranelift => write!(f, "cranelift"),
+            Self::Singlepass => write!(f, "singlepass"),
+        }
+    }
+}
+
+impl FromStr for CompilerBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cranelift" => Ok(Self::Cranelift),
+            "singlepass" => Ok(Self::Singlepass),
+            other => Err(format!("unknown compiler backend: {other}")),
+        }
+    }
+}
+
+/// Whether contracts are required to execute deterministically, so replicas applying
+/// the same deltas converge to the same state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Determinism {
+    /// Contracts can read non-deterministic inputs, like the current time.
+    #[default]
+    Relaxed,
+    /// NaNs are canonicalized and contracts importing non-deterministic host functions
+    /// are rejected when loaded.
+    Strict,
+    /// NaNs are canonicalized and non-deterministic host functions return the inputs fixed
+    /// by the host, see [`Runtime::set_call_time`].
+    HostInputs,
+}
+
+impl Determinism {
+    fn canonicalize_nans(self) -> bool {
+        self != Self::Relaxed
+    }
+}
+
+impl Display for Determinism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Relaxed => write!(f, "relaxed"),
+            Self::Strict => write!(f, "strict"),
+            Self::HostInputs => write!(f, "host-inputs"),
+        }
+    }
+}
+
+impl FromStr for Determinism {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relaxed" => Ok(Self::Relaxed),
+            "strict" => Ok(Self::Strict),
+            "host-inputs" => Ok(Self::HostInputs),
+            other => Err(format!("unknown determinism mode: {other}")),
+        }
+    }
+}
+
+/// Compiler settings and execution limits for a [`Runtime`].
+#[derive(Clone, Copy, Debug)]
+pub struct RuntimeConfig {
+    /// Compiler used for contract and component modules.
+    pub compiler: CompilerBackend,
+    /// Determinism guarantees enforced on contract calls. Components always run in
+    /// relaxed mode, but share the NaN canonicalization setting.
+    pub determinism: Determinism,
+    /// Max gas (compute units) a single call to a contract or component can consume
+    /// before being aborted.
+    pub max_gas: u64,
+    /// Max linear memory, in WASM pages of 64KiB, a single contract or component instance
+    /// can allocate.
+    pub max_memory_pages: u32,
+    /// Max idle instances kept per contract or component module to be reused by later calls,
+    /// `0` disables instance pooling.
+    pub instance_pool_size: usize,
+    /// Max events per second a contract or component can log, any further events are dropped.
+    pub max_log_events: u32,
+    /// Max time a call through an [`AsyncRuntime`](crate::AsyncRuntime) can take,
+    /// including the time spent waiting in the queue.
+    pub call_timeout: Duration,
+    /// Max calls waiting to be executed by an [`AsyncRuntime`](crate::AsyncRuntime),
+    /// further calls wait until there is room in the queue.
+    pub call_queue_size: usize,
+    /// Max random bytes a component can request at once through
+    /// [`OutboundComponentMsg::RandomBytesRequest`](locutus_stdlib::prelude::OutboundComponentMsg::RandomBytesRequest).
+    pub max_random_bytes: usize,
+    /// Records the stats of every contract call, see [`Runtime::profile`].
+    pub profiling: bool,
+}
+
+impl RuntimeConfig {
+    pub const DEFAULT_MAX_GAS: u64 = 10_000_000_000;
+    /// 256MiB
+    pub const DEFAULT_MAX_MEMORY_PAGES: u32 = 4096;
+    pub const DEFAULT_INSTANCE_POOL_SIZE: usize = 4;
+    pub const DEFAULT_MAX_LOG_EVENTS: u32 = 100;
+    pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_CALL_QUEUE_SIZE: usize = 128;
+    /// 64KiB
+    pub const DEFAULT_MAX_RANDOM_BYTES: usize = 64 * 1024;
+
+    fn max_memory_bytes(&self) -> usize {
+        self.max_memory_pages as usize * wasmer::WASM_PAGE_SIZE
+    }
+
+    /// Identifies the runtime version and the settings modules are compiled with.
+    fn compilation_fingerprint(&self) -> String {
+        let mut hasher = Blake2s256::new();
+        hasher.update(self.compiler.to_string());
+        hasher.update([self.determinism.canonicalize_nans() as u8]);
+        hasher.update(self.max_gas.to_le_bytes());
+        hasher.update(self.max_memory_pages.to_le_bytes());
+        let settings = bs58::encode(&hasher.finalize()[..8])
+            .with_alphabet(bs58::Alphabet::BITCOIN)
+            .into_string();
+        format!(
+            "locutus-runtime-{}-wasmer-{}-{settings}",
+            env!("CARGO_PKG_VERSION"),
+            wasmer::VERSION
+        )
+    }
+
+    fn tunables(&self) -> LimitingTunables<BaseTunables> {
+        LimitingTunables::new(
+            BaseTunables::for_target(&Target::default()),
+            Pages(self.max_memory_pages),
+        )
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            compiler: CompilerBackend::default(),
+            determinism: Determinism::default(),
+            max_gas: Self::DEFAULT_MAX_GAS,
+            max_memory_pages: Self::DEFAULT_MAX_MEMORY_PAGES,
+            instance_pool_size: Self::DEFAULT_INSTANCE_POOL_SIZE,
+            max_log_events: Self::DEFAULT_MAX_LOG_EVENTS,
+            call_timeout: Self::DEFAULT_CALL_TIMEOUT,
+            call_queue_size: Self::DEFAULT_CALL_QUEUE_SIZE,
+            max_random_bytes: Self::DEFAULT_MAX_RANDOM_BYTES,
+            profiling: false,
+        }
+    }
+}
+
 pub struct Runtime {
     /// Working memory store used by the inner engine
     pub(crate) wasm_store: Store,
-    /// includes all the necessary imports to interact with the native runtime environment
-    pub(crate) top_level_imports: Imports,
+    pub(crate) config: RuntimeConfig,
+    /// gas consumed by the last call to a contract or component
+    pub(crate) gas_used: u64,
+    /// time returned to contracts when running with [`Determinism::HostInputs`]
+    pub(crate) call_time: Option<DateTime<Utc>>,
+    /// log rate limits of each contract and component
+    pub(crate) log_limits: HashMap<LogTag, Arc<Mutex<RateLimit>>>,
+    /// stats of the contract calls, if profiling is enabled
+    pub(crate) profile: Option<ContractProfile>,
+    /// measurements of the contract call in progress, if profiling is enabled
+    pub(crate) current_call: Option<CallSample>,
     /// assigned growable host memory
     pub(crate) host_memory: Option<Memory>,
     #[cfg(test)]
     pub(crate) enable_wasi: bool,
 
-    pub(crate) secret_store: SecretsStore,
-    pub(crate) component_store: ComponentStore,
+    /// Local secrets storage.
+    pub secret_store: SecretsStore,
+    /// Local component storage.
+    pub component_store: ComponentStore,
+    /// compiled modules persisted across restarts
+    pub(crate) module_cache: Option<ModuleCache>,
     /// loaded component modules
     pub(crate) component_modules: HashMap<ComponentKey, Module>,
+    /// idle component instances ready to be reused
+    pub(crate) component_instances: InstancePool<ComponentKey>,
+    /// contexts of the components kept for each client session
+    pub(crate) component_contexts: HashMap<(ComponentKey, SessionId), ComponentContext>,
+    /// host contracts the components can read and update
+    pub(crate) contract_access: Option<Box<dyn ContractAccess>>,
 
     /// Local contract storage.
     pub contract_store: ContractStore,
     /// loaded contract modules
     pub(crate) contract_modules: HashMap<ContractKey, Module>,
+    /// idle contract instances ready to be reused
+    pub(crate) contract_instances: InstancePool<ContractKey>,
 }
 
 impl Runtime {
@@ -89,41 +319,178 @@ impl Runtime {
         secret_store: SecretsStore,
         host_mem: bool,
     ) -> RuntimeResult<Self> {
-        let mut store = Self::instance_store();
-        let (host_memory, mut top_level_imports) = if host_mem {
-            let mem = Self::instance_host_mem(&mut store)?;
-            let imports = imports! {
-                "env" => {
-                    "memory" =>  mem.clone(),
-                },
-            };
-            (Some(mem), imports)
+        Self::build_with_config(
+            contract_store,
+            component_store,
+            secret_store,
+            host_mem,
+            RuntimeConfig::default(),
+        )
+    }
+
+    pub fn build_with_config(
+        contract_store: ContractStore,
+        component_store: ComponentStore,
+        secret_store: SecretsStore,
+        host_mem: bool,
+        config: RuntimeConfig,
+    ) -> RuntimeResult<Self> {
+        let mut store = Self::instance_store(&config)?;
+        let host_memory = if host_mem {
+            Some(Self::instance_host_mem(&mut store, &config)?)
         } else {
-            (None, imports! {})
+            None
+        };
+        // instances sharing the host memory can't be reset independently
+        let pool_size = if host_mem {
+            0
+        } else {
+            config.instance_pool_size
         };
-        native_api::time::prepare_export(&mut store, &mut top_level_imports);
 
         Ok(Self {
             wasm_store: store,
-            top_level_imports,
+            config,
+            gas_used: 0,
+            call_time: None,
+            log_limits: HashMap::new(),
+            profile: config.profiling.then(ContractProfile::default),
+            current_call: None,
             host_memory,
             #[cfg(test)]
             enable_wasi: false,
 
             secret_store,
             component_store,
-            contract_modules: HashMap::new(),
+            module_cache: None,
+            component_modules: HashMap::new(),
+            component_instances: InstancePool::new(pool_size),
+            component_contexts: HashMap::new(),
+            contract_access: None,
 
             contract_store,
-            component_modules: HashMap::new(),
+            contract_modules: HashMap::new(),
+            contract_instances: InstancePool::new(pool_size),
         })
     }
 
+    /// Persists compiled modules in the cache directory, so they are not compiled again
+    /// after a restart.
+    ///
+    /// # Arguments
+    /// - max_size: max size in bytes of the compiled modules kept in the cache
+    pub fn enable_module_cache(&mut self, cache_dir: &Path, max_size: u64) -> RuntimeResult<()> {
+        let fingerprint = self.config.compilation_fingerprint();
+        self.module_cache = Some(ModuleCache::open(cache_dir, &fingerprint, max_size)?);
+        Ok(())
+    }
+
+    /// Compiles all the contracts in the contract store which are not in the module cache yet,
+    /// returns the number of newly cached contracts.
+    pub fn prewarm_module_cache(&mut self) -> RuntimeResult<usize> {
+        if self.module_cache.is_none() {
+            return Ok(0);
+        }
+        let mut compiled = 0;
+        for key in self.contract_store.contract_keys() {
+            let Some(ContractContainer::Wasm(WasmAPIVersion::V1(contract))) = self
+                .contract_store
+                .fetch_contract(&key, &Parameters::from(vec![]))
+            else {
+                continue;
+            };
+            let code = contract.code();
+            let cache_key = Self::module_cache_key(code.hash());
+            if self
+                .module_cache
+                .as_ref()
+                .map(|cache| cache.contains(&cache_key))
+                .unwrap_or_default()
+            {
+                continue;
+            }
+            match self.compile_artifact(code.data()) {
+                Ok(artifact) => {
+                    self.cache_artifact(&cache_key, &artifact);
+                    compiled += 1;
+                }
+                Err(err) => tracing::warn!("failed to compile contract {key}: {err}"),
+            }
+        }
+        Ok(compiled)
+    }
+
+    /// Lets the components get, update and subscribe to the contracts through `access`.
+    pub fn set_contract_access(&mut self, access: impl ContractAccess + 'static) {
+        self.contract_access = Some(Box::new(access));
+    }
+
+    /// Removes the contract from the contract store along its loaded module and instances.
+    pub fn remove_contract(&mut self, key: &ContractKey) -> RuntimeResult<()> {
+        self.contract_store.remove_contract(key)?;
+        self.contract_modules.remove(key);
+        self.contract_instances.remove(key);
+        Ok(())
+    }
+
+    /// Evicts contracts from the contract store until it fits in its quota, see
+    /// [`ContractStore::evict`]. Returns the keys of the evicted contracts.
+    pub fn evict_contracts(
+        &mut self,
+        is_referenced: impl Fn(&ContractKey) -> bool,
+    ) -> RuntimeResult<Vec<ContractKey>> {
+        let evicted = self.contract_store.evict(is_referenced)?;
+        for key in &evicted {
+            self.contract_modules.remove(key);
+            self.contract_instances.remove(key);
+        }
+        Ok(evicted)
+    }
+
+    /// Evicts components from the component store until it fits in its quota, see
+    /// [`ComponentStore::evict`]. Returns the keys of the evicted components.
+    pub fn evict_components(
+        &mut self,
+        is_referenced: impl Fn(&ComponentKey) -> bool,
+    ) -> RuntimeResult<Vec<ComponentKey>> {
+        let evicted = self.component_store.evict(is_referenced)?;
+        for key in &evicted {
+            self.component_modules.remove(key);
+            self.component_instances.remove(key);
+            self.component_contexts
+                .retain(|(component, _), _| component != key);
+        }
+        Ok(evicted)
+    }
+
+    /// Gas consumed by the last call to a contract or component.
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    /// Stats of the contract calls, if enabled through [`RuntimeConfig::profiling`].
+    pub fn profile(&self) -> Option<&ContractProfile> {
+        self.profile.as_ref()
+    }
+
+    pub fn profile_mut(&mut self) -> Option<&mut ContractProfile> {
+        self.profile.as_mut()
+    }
+
+    /// Sets the time observed by contracts running with [`Determinism::HostInputs`],
+    /// it applies to all the following calls until changed.
+    pub fn set_call_time(&mut self, time: DateTime<Utc>) {
+        self.call_time = Some(time);
+    }
+
     pub(crate) fn init_buf<T>(&mut self, instance: &Instance, data: T) -> RuntimeResult<BufferMut>
     where
         T: AsRef<[u8]>,
     {
         let data = data.as_ref();
+        if let Some(call) = &mut self.current_call {
+            call.bytes_in += data.len() as u64;
+        }
         let initiate_buffer: TypedFunction<u32, i64> = instance
             .exports
             .get_typed_function(&self.wasm_store, "initiate_buffer")?;
@@ -156,6 +523,57 @@ impl Runtime {
         parameters: &Parameters,
         req_bytes: usize,
     ) -> RuntimeResult<RunningInstance> {
+        let fixed_time = match self.config.determinism {
+            Determinism::HostInputs => {
+                Some(self.call_time.ok_or(ContractExecError::MissingCallTime)?)
+            }
+            Determinism::Relaxed | Determinism::Strict => None,
+        };
+        let start = Instant::now();
+        let mut instantiation_time = None;
+        let (instance, env) = if let Some(pooled) = self.contract_instances.take(key) {
+            pooled
+        } else {
+            let module = self.contract_module(key, parameters)?;
+            let instantiation_start = Instant::now();
+            let (instance, env) = self.prepare_instance(&module)?;
+            self.contract_instances
+                .track(&mut self.wasm_store, key, &instance)?;
+            instantiation_time = Some(instantiation_start.elapsed());
+            (instance, env)
+        };
+        self.current_call = self.profile.is_some().then(|| CallSample {
+            key: key.clone(),
+            start,
+            instantiation_time,
+            bytes_in: 0,
+        });
+        self.set_instance_mem(req_bytes, &instance)?;
+        let log_source = self.log_source(LogTag::Contract(key.clone()));
+        let host_env = env.as_mut(&mut self.wasm_store);
+        host_env.fixed_time = fixed_time;
+        host_env.log_source = Some(log_source);
+        let running = RunningInstance { instance, env };
+        self.reset_gas(&running.instance);
+        self.gas_used = 0;
+        Ok(running)
+    }
+
+    /// Returns the instance to the pool once a call to the contract completed successfully.
+    pub(crate) fn release_contract_instance(
+        &mut self,
+        key: &ContractKey,
+        running: RunningInstance,
+    ) {
+        self.contract_instances
+            .release(&mut self.wasm_store, key, running.instance, running.env);
+    }
+
+    fn contract_module(
+        &mut self,
+        key: &ContractKey,
+        parameters: &Parameters,
+    ) -> RuntimeResult<Module> {
         let module = if let Some(module) = self.contract_modules.get(key) {
             module
         } else {
@@ -165,16 +583,42 @@ impl Runtime {
                 .ok_or_else(|| RuntimeInnerError::ContractNotFound(key.clone()))?;
             let module = match contract {
                 ContractContainer::Wasm(WasmAPIVersion::V1(contract_v1)) => {
-                    Module::new(&self.wasm_store, contract_v1.code().data())?
+                    let code = contract_v1.code();
+                    self.load_module(code.hash(), code.data())?
                 }
             };
+            if self.config.determinism == Determinism::Strict {
+                Self::check_deterministic_imports(&module)?;
+            }
             self.contract_modules.insert(key.clone(), module);
             self.contract_modules.get(key).unwrap()
         }
         .clone();
-        let instance = self.prepare_instance(&module)?;
-        self.set_instance_mem(req_bytes, &instance)?;
-        RunningInstance::new(self, instance)
+        Ok(module)
+    }
+
+    /// Tags the events logged by an instance with the emitting contract or component.
+    fn log_source(&mut self, tag: LogTag) -> LogSource {
+        let max_events = self.config.max_log_events;
+        let limit = self
+            .log_limits
+            .entry(tag.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(RateLimit::new(max_events))))
+            .clone();
+        LogSource { tag, limit }
+    }
+
+    fn check_deterministic_imports(module: &Module) -> RuntimeResult<()> {
+        for import in module.imports() {
+            if native_api::NON_DETERMINISTIC_IMPORTS.contains(&(import.module(), import.name())) {
+                return Err(ContractExecError::NonDeterministicImport {
+                    module: import.module().to_owned(),
+                    name: import.name().to_owned(),
+                }
+                .into());
+            }
+        }
+        Ok(())
     }
 
     pub(crate) fn prepare_component_call(
@@ -182,6 +626,37 @@ impl Runtime {
         key: &ComponentKey,
         req_bytes: usize,
     ) -> RuntimeResult<RunningInstance> {
+        self.current_call = None;
+        let (instance, env) = if let Some(pooled) = self.component_instances.take(key) {
+            pooled
+        } else {
+            let module = self.component_module(key)?;
+            let (instance, env) = self.prepare_instance(&module)?;
+            self.component_instances
+                .track(&mut self.wasm_store, key, &instance)?;
+            (instance, env)
+        };
+        self.set_instance_mem(req_bytes, &instance)?;
+        let log_source = self.log_source(LogTag::Component(key.clone()));
+        let host_env = env.as_mut(&mut self.wasm_store);
+        host_env.fixed_time = None;
+        host_env.log_source = Some(log_source);
+        let running = RunningInstance { instance, env };
+        self.gas_used = 0;
+        Ok(running)
+    }
+
+    /// Returns the instance to the pool once a call to the component completed successfully.
+    pub(crate) fn release_component_instance(
+        &mut self,
+        key: &ComponentKey,
+        running: RunningInstance,
+    ) {
+        self.component_instances
+            .release(&mut self.wasm_store, key, running.instance, running.env);
+    }
+
+    fn component_module(&mut self, key: &ComponentKey) -> RuntimeResult<Module> {
         let module = if let Some(module) = self.component_modules.get(key) {
             module
         } else {
@@ -189,14 +664,136 @@ impl Runtime {
                 .component_store
                 .fetch_component(key)
                 .ok_or_else(|| RuntimeInnerError::ComponentNotFound(key.clone()))?;
-            let module = Module::new(&self.wasm_store, contract.as_ref())?;
+            let module = self.load_module(key.code_hash(), contract.as_ref())?;
             self.component_modules.insert(key.clone(), module);
             self.component_modules.get(key).unwrap()
         }
         .clone();
-        let instance = self.prepare_instance(&module)?;
-        self.set_instance_mem(req_bytes, &instance)?;
-        RunningInstance::new(self, instance)
+        Ok(module)
+    }
+
+    /// Loads the module in the working store, compiling it if is not in the module cache.
+    fn load_module(&mut self, code_hash: &[u8; 32], code: &[u8]) -> RuntimeResult<Module> {
+        let cache_key = Self::module_cache_key(code_hash);
+        if let Some(artifact) = self
+            .module_cache
+            .as_mut()
+            .and_then(|cache| cache.get(&cache_key))
+        {
+            // Safety: the cache only holds artifacts compiled by this runtime version
+            match unsafe { Module::deserialize(&self.wasm_store, artifact) } {
+                Ok(module) => return Ok(module),
+                Err(err) => {
+                    tracing::warn!("failed to load cached module {cache_key}: {err}");
+                    if let Some(cache) = &mut self.module_cache {
+                        cache.remove(&cache_key);
+                    }
+                }
+            }
+        }
+        let artifact = self.compile_artifact(code)?;
+        self.cache_artifact(&cache_key, &artifact);
+        // Safety: the artifact was just compiled by this runtime
+        let module = unsafe { Module::deserialize(&self.wasm_store, artifact)? };
+        Ok(module)
+    }
+
+    /// Compiles the module with gas metering.
+    ///
+    /// The metering middleware keeps module specific state, so a fresh compiler is used for every
+    /// module and the resulting artifact is then loaded into the engine of the working store.
+    fn compile_artifact(&self, code: &[u8]) -> RuntimeResult<Vec<u8>> {
+        let mut compiler = self
+            .config
+            .compiler
+            .compiler_config(self.config.determinism.canonicalize_nans())?;
+        compiler.push_middleware(Arc::new(Metering::new(self.config.max_gas, op_cost)));
+        let mut engine = EngineBuilder::new(compiler).engine();
+        engine.set_tunables(self.config.tunables());
+        Ok(Module::new(&engine, code)?.serialize()?.to_vec())
+    }
+
+    fn cache_artifact(&mut self, cache_key: &str, artifact: &[u8]) {
+        if let Some(cache) = &mut self.module_cache {
+            if let Err(err) = cache.insert(cache_key, artifact) {
+                tracing::warn!("failed to cache module {cache_key}: {err}");
+            }
+        }
+    }
+
+    fn module_cache_key(code_hash: &[u8; 32]) -> String {
+        bs58::encode(code_hash)
+            .with_alphabet(bs58::Alphabet::BITCOIN)
+            .into_string()
+            .to_lowercase()
+    }
+
+    /// Resets the gas budget for the next call to the instance.
+    pub(crate) fn reset_gas(&mut self, instance: &Instance) {
+        set_remaining_points(&mut self.wasm_store, instance, self.config.max_gas);
+    }
+
+    /// Accounts for the gas consumed since the last reset, returns whether the instance
+    /// ran out of gas.
+    pub(crate) fn consume_gas(&mut self, instance: &Instance) -> bool {
+        match get_remaining_points(&mut self.wasm_store, instance) {
+            MeteringPoints::Remaining(left) => {
+                self.gas_used += self.config.max_gas - left;
+                false
+            }
+            MeteringPoints::Exhausted => {
+                self.gas_used += self.config.max_gas;
+                true
+            }
+        }
+    }
+
+    /// Checks the result of a call to a contract function, accounting for the consumed gas.
+    ///
+    /// # Safety
+    /// The linear memory must belong to the instance which returned the result.
+    pub(crate) unsafe fn contract_call_result(
+        &mut self,
+        instance: &Instance,
+        function: ContractFunction,
+        result: Result<i64, RuntimeError>,
+        linear_mem: &WasmLinearMem,
+    ) -> RuntimeResult<ContractInterfaceResult> {
+        let result = if self.consume_gas(instance) {
+            Err(ContractExecError::OutOfGas(self.config.max_gas).into())
+        } else {
+            result
+                .map(|ptr| ContractInterfaceResult::from_raw(ptr, linear_mem))
+                .map_err(Into::into)
+        };
+        self.record_contract_call(instance, function, result.as_ref().ok());
+        result
+    }
+
+    fn record_contract_call(
+        &mut self,
+        instance: &Instance,
+        function: ContractFunction,
+        result: Option<&ContractInterfaceResult>,
+    ) {
+        let Some(sample) = self.current_call.take() else {
+            return;
+        };
+        let memory_pages = self
+            .linear_mem(instance)
+            .map(|mem| (mem.size / wasmer::WASM_PAGE_SIZE as u64) as u32)
+            .unwrap_or_default();
+        let gas = self.gas_used;
+        if let Some(profile) = &mut self.profile {
+            profile.record(
+                sample,
+                function,
+                result.is_none(),
+                result.map(|res| res.size() as u64).unwrap_or_default(),
+                memory_pages,
+                gas,
+            );
+        }
     }
 
     fn set_instance_mem(&mut self, req_bytes: usize, instance: &Instance) -> RuntimeResult<()> {
@@ -205,9 +802,19 @@ impl Runtime {
             .as_ref()
             .map(Ok)
             .unwrap_or_else(|| instance.exports.get_memory("memory"))?;
-        let req_pages = Bytes::from(req_bytes).try_into().unwrap();
-        if memory.view(&self.wasm_store).size() < req_pages {
-            if let Err(err) = memory.grow(&mut self.wasm_store, req_pages) {
+        let max_bytes = self.config.max_memory_bytes();
+        if req_bytes > max_bytes {
+            tracing::error!("requested {req_bytes} bytes over the instance memory limit");
+            return Err(ContractExecError::InsufficientMemory {
+                req: req_bytes,
+                free: max_bytes,
+            }
+            .into());
+        }
+        let req_pages: Pages = Bytes::from(req_bytes).try_into().unwrap();
+        let current_pages = memory.view(&self.wasm_store).size();
+        if current_pages < req_pages {
+            if let Err(err) = memory.grow(&mut self.wasm_store, req_pages - current_pages) {
                 tracing::error!("wasm runtime failed with memory error: {err}");
                 return Err(ContractExecError::InsufficientMemory {
                     req: (req_pages.0 as usize * wasmer::WASM_PAGE_SIZE),
@@ -220,41 +827,46 @@ impl Runtime {
         Ok(())
     }
 
-    fn instance_host_mem(store: &mut Store) -> RuntimeResult<Memory> {
-        // todo: max memory assigned for this runtime
-        Ok(Memory::new(store, MemoryType::new(20u32, None, false))?)
+    fn instance_host_mem(store: &mut Store, config: &RuntimeConfig) -> RuntimeResult<Memory> {
+        let max_pages = config.max_memory_pages;
+        Ok(Memory::new(
+            store,
+            MemoryType::new(20u32.min(max_pages), Some(max_pages), false),
+        )?)
     }
 
     #[cfg(not(test))]
-    fn prepare_instance(&mut self, module: &Module) -> RuntimeResult<Instance> {
-        Ok(Instance::new(
-            &mut self.wasm_store,
-            module,
-            &self.top_level_imports,
-        )?)
+    fn prepare_instance(
+        &mut self,
+        module: &Module,
+    ) -> RuntimeResult<(Instance, FunctionEnv<HostEnv>)> {
+        let env = FunctionEnv::new(&mut self.wasm_store, HostEnv::default());
+        let imports = self.instance_imports(&env);
+        let instance = Instance::new(&mut self.wasm_store, module, &imports)?;
+        self.bind_env(&instance, &env)?;
+        Ok((instance, env))
     }
 
     #[cfg(test)]
     // this fn enables WASI env for debuggability
-    fn prepare_instance(&mut self, module: &Module) -> RuntimeResult<Instance> {
-        use wasmer::namespace;
+    fn prepare_instance(
+        &mut self,
+        module: &Module,
+    ) -> RuntimeResult<(Instance, FunctionEnv<HostEnv>)> {
         use wasmer_wasi::WasiState;
 
+        let env = FunctionEnv::new(&mut self.wasm_store, HostEnv::default());
+        let native_imports = self.instance_imports(&env);
         if !self.enable_wasi {
-            return Ok(Instance::new(
-                &mut self.wasm_store,
-                module,
-                &self.top_level_imports,
-            )?);
+            let instance = Instance::new(&mut self.wasm_store, module, &native_imports)?;
+            self.bind_env(&instance, &env)?;
+            return Ok((instance, env));
         }
         let mut wasi_env = WasiState::new("locutus").finalize(&mut self.wasm_store)?;
         let mut imports = wasi_env.import_object(&mut self.wasm_store, module)?;
-        if let Some(mem) = &self.host_memory {
-            imports.register_namespace("env", namespace!("memory" => mem.clone()));
-        }
 
         let mut namespaces = HashMap::new();
-        for ((module, name), import) in self.top_level_imports.into_iter() {
+        for ((module, name), import) in native_imports.into_iter() {
             let namespace: &mut wasmer::Exports = namespaces.entry(module).or_default();
             namespace.insert(name, import);
         }
@@ -264,26 +876,41 @@ impl Runtime {
 
         let instance = Instance::new(&mut self.wasm_store, module, &imports)?;
         wasi_env.initialize(&mut self.wasm_store, &instance)?;
+        self.bind_env(&instance, &env)?;
+
+        Ok((instance, env))
+    }
 
-        Ok(instance)
+    /// Host functions and memory imported by a new instance.
+    fn instance_imports(&mut self, env: &FunctionEnv<HostEnv>) -> Imports {
+        let mut imports = native_api::imports(&mut self.wasm_store, env);
+        if let Some(memory) = &self.host_memory {
+            imports.register_namespace("env", namespace!("memory" => memory.clone()));
+        }
+        imports
+    }
+
+    /// Gives the host functions imported by the instance access to its memory.
+    fn bind_env(&mut self, instance: &Instance, env: &FunctionEnv<HostEnv>) -> RuntimeResult<()> {
+        let memory = match &self.host_memory {
+            Some(memory) => memory.clone(),
+            None => instance.exports.get_memory("memory")?.clone(),
+        };
+        env.as_mut(&mut self.wasm_store).set_memory(memory);
+        Ok(())
     }
 
-    fn instance_store() -> Store {
-        use wasmer::Cranelift;
-        Store::new(Cranelift::new())
+    fn instance_store(config: &RuntimeConfig) -> RuntimeResult<Store> {
+        Ok(Store::new_with_tunables(
+            config
+                .compiler
+                .compiler_config(config.determinism.canonicalize_nans())?,
+            config.tunables(),
+        ))
     }
+}
 
-    // #[cfg(not(test))]
-    // fn instance_store() -> Store {
-    //     use wasmer::Universal;
-    //     if cfg!(target_arch = "aarch64") {
-    //         use wasmer::Cranelift;
-    //         Store::new(&Universal::new(Cranelift::new()).engine())
-    //     } else {
-    //         use wasmer_compiler_llvm::LLVM;
-    //         Store::new(&Universal::new(LLVM::new()).engine())
-    //     }
-    //     // use wasmer::Dylib;
-    //     // Store::new(&Dylib::headless().engine())
-    // }
+/// Cost of executing a single WASM operator.
+fn op_cost(_op: &Operator) -> u64 {
+    1
 }
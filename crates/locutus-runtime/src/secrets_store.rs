@@ -1,8 +1,18 @@
-use chacha20poly1305::{aead::Aead, Error as EncryptionError, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Error as EncryptionError, XChaCha20Poly1305, XNonce,
+};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::io::Write;
-use std::{collections::HashMap, fs, fs::File, iter::FromIterator, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs,
+    fs::File,
+    iter::FromIterator,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::store::{StoreEntriesContainer, StoreFsManagement};
 use crate::RuntimeResult;
@@ -10,6 +20,21 @@ use locutus_stdlib::prelude::*;
 
 type SecretKey = [u8; 32];
 
+/// Version of the format of the stored secrets, written as their first byte.
+///
+/// Secrets stored before the format was versioned are the bare ciphertext of their value,
+/// encrypted with a nonce shared by all the secrets of the component.
+const SECRET_FORMAT_VERSION: u8 = 1;
+/// Size of the nonce stored in front of the ciphertext of each secret.
+const NONCE_SIZE: usize = 24;
+/// Header of the archives with exported secrets.
+const ARCHIVE_HEADER: &[u8; 8] = b"LCTSEC01";
+const ARCHIVE_SALT_SIZE: usize = 16;
+/// Suffix of the directory where a component's secrets are re-encrypted during a key rotation.
+const ROTATING_SUFFIX: &str = "rotating";
+/// Suffix of a component's secrets directory replaced by a key rotation, until removed.
+const REPLACED_SUFFIX: &str = "replaced";
+
 #[derive(Serialize, Deserialize, Default)]
 struct KeyToEncryptionMap(Vec<(ComponentKey, Vec<SecretKey>)>);
 
@@ -37,6 +62,14 @@ impl StoreEntriesContainer for KeyToEncryptionMap {
             container.insert(key, value);
         }
     }
+
+    fn remove(container: &mut Self::MemContainer, key: &Self::Key) {
+        container.remove(key);
+    }
+
+    fn into_entries(self) -> Vec<(Self::Key, Self::Value)> {
+        self.0
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -47,6 +80,31 @@ pub enum SecretStoreError {
     IO(#[from] std::io::Error),
     #[error("missing cipher")]
     MissingCipher,
+    #[error("malformed secret")]
+    MalformedSecret,
+    #[error("failed deriving key from passphrase: {0}")]
+    KeyDerivation(argon2::Error),
+    #[error("malformed secrets archive or wrong passphrase")]
+    InvalidArchive,
+    #[error("the cipher doesn't match the component cipher")]
+    CipherMismatch,
+}
+
+/// Plaintext of a stored secret, the id is kept so the secrets of a component can be listed.
+///
+/// The id of the secrets migrated from the legacy format is unknown until they are read.
+#[derive(Serialize, Deserialize)]
+struct StoredSecret {
+    id: Option<SecretsId>,
+    value: Vec<u8>,
+}
+
+/// Secrets of a component, as exported into an archive.
+#[derive(Serialize, Deserialize)]
+struct ExportedSecrets {
+    component: ComponentKey,
+    /// secret ids and their ciphertext, still encrypted with the component cipher
+    secrets: Vec<(SecretKey, Vec<u8>)>,
 }
 
 impl From<&DashMap<ComponentKey, Vec<SecretKey>>> for KeyToEncryptionMap {
@@ -59,54 +117,66 @@ impl From<&DashMap<ComponentKey, Vec<SecretKey>>> for KeyToEncryptionMap {
     }
 }
 
-#[derive(Clone)]
-struct Encryption {
-    cipher: XChaCha20Poly1305,
-    nonce: XNonce,
-}
-
+/// Stores the secrets of each component encrypted with the component's cipher.
+///
+/// Every secret is encrypted with a fresh random nonce, stored in front of its ciphertext
+/// after the format version.
 #[derive(Default)]
 pub struct SecretsStore {
     base_path: PathBuf,
-    ciphers: HashMap<ComponentKey, Encryption>,
+    ciphers: HashMap<ComponentKey, XChaCha20Poly1305>,
     key_to_secret_part: Arc<DashMap<ComponentKey, Vec<SecretKey>>>,
 }
 
-static LOCK_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
-static KEY_FILE_PATH: once_cell::sync::OnceCell<PathBuf> = once_cell::sync::OnceCell::new();
-
-impl StoreFsManagement<KeyToEncryptionMap> for SecretsStore {}
+impl StoreFsManagement<KeyToEncryptionMap> for SecretsStore {
+    fn scan_blobs(store_dir: &Path) -> RuntimeResult<Vec<(ComponentKey, Vec<SecretKey>)>> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(store_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(Ok(component)) = entry.file_name().to_str().map(ComponentKey::decode) else {
+                continue;
+            };
+            let mut secrets = vec![];
+            for secret in fs::read_dir(entry.path())? {
+                let Some(name) = secret?.file_name().to_str().map(str::to_owned) else {
+                    continue;
+                };
+                let mut secret_key = [0; 32];
+                if bs58::decode(name)
+                    .with_alphabet(bs58::Alphabet::BITCOIN)
+                    .into(&mut secret_key)
+                    .is_ok()
+                {
+                    secrets.push(secret_key);
+                }
+            }
+            entries.push((component, secrets));
+        }
+        Ok(entries)
+    }
+}
 
 impl SecretsStore {
     pub fn new(secrets_dir: PathBuf) -> RuntimeResult<Self> {
         let key_to_secret_part;
-        let _ = LOCK_FILE_PATH.try_insert(secrets_dir.join("__LOCK"));
-        let key_file = match KEY_FILE_PATH
-            .try_insert(secrets_dir.join("KEY_DATA"))
-            .map_err(|(e, _)| e)
-        {
-            Ok(f) => f,
-            Err(f) => f,
-        };
+        let key_file = secrets_dir.join("KEY_DATA");
+        let lock_file = secrets_dir.join("__LOCK");
         if !key_file.exists() {
             std::fs::create_dir_all(&secrets_dir).map_err(|err| {
                 tracing::error!("error creating component dir: {err}");
                 err
             })?;
             key_to_secret_part = Arc::new(DashMap::new());
-            File::create(secrets_dir.join("KEY_DATA"))?;
+            File::create(&key_file)?;
         } else {
-            let map = Self::load_from_file(
-                KEY_FILE_PATH.get().unwrap().as_path(),
-                LOCK_FILE_PATH.get().unwrap().as_path(),
-            )?;
+            let map = Self::load_or_recover(&key_file, &lock_file)?;
             key_to_secret_part = Arc::new(DashMap::from_iter(map.0));
         }
-        Self::watch_changes(
-            key_to_secret_part.clone(),
-            KEY_FILE_PATH.get().unwrap().as_path(),
-            LOCK_FILE_PATH.get().unwrap().as_path(),
-        )?;
+        Self::finish_rotations(&secrets_dir)?;
+        Self::watch_changes(key_to_secret_part.clone(), &key_file, &lock_file)?;
         Ok(Self {
             base_path: secrets_dir,
             ciphers: HashMap::new(),
@@ -118,11 +188,122 @@ impl SecretsStore {
         &mut self,
         component: ComponentKey,
         cipher: XChaCha20Poly1305,
-        nonce: XNonce,
     ) -> Result<(), SecretStoreError> {
         // FIXME: store/initialize the cyphers from disc
-        let encryption = Encryption { cipher, nonce };
-        self.ciphers.insert(component, encryption);
+        self.ciphers.insert(component, cipher);
+        Ok(())
+    }
+
+    /// Re-encrypts the secrets of the component stored in the legacy format, all encrypted
+    /// with the same `nonce`, into the current format. Returns the number of migrated secrets.
+    ///
+    /// Secrets which can't be decrypted are left as they are.
+    pub fn migrate_legacy_secrets(
+        &mut self,
+        component: &ComponentKey,
+        nonce: &XNonce,
+    ) -> Result<usize, SecretStoreError> {
+        let cipher = self
+            .ciphers
+            .get(component)
+            .ok_or(SecretStoreError::MissingCipher)?;
+        let component_path = self.base_path.join(component.encode());
+        if !component_path.exists() {
+            return Ok(0);
+        }
+        let mut migrated = 0;
+        for entry in fs::read_dir(component_path)? {
+            let path = entry?.path();
+            let secret = fs::read(&path)?;
+            if decrypt(cipher, &secret).is_ok() {
+                continue;
+            }
+            let Ok(value) = cipher.decrypt(nonce, secret.as_slice()) else {
+                tracing::warn!("failed to migrate legacy secret {path:?}");
+                continue;
+            };
+            write_secret(cipher, &path, &StoredSecret { id: None, value })?;
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    /// Re-encrypts all the secrets of the component with a new cipher, which replaces the
+    /// current one. Fails unless `current_cipher` is the cipher of the component.
+    ///
+    /// Secrets are re-encrypted into a separate directory which then replaces the current one,
+    /// if the rotation fails or is interrupted the secrets remain encrypted with the old cipher.
+    pub fn rotate_component_key(
+        &mut self,
+        component: &ComponentKey,
+        current_cipher: &XChaCha20Poly1305,
+        new_cipher: XChaCha20Poly1305,
+    ) -> Result<(), SecretStoreError> {
+        let cipher = self
+            .ciphers
+            .get(component)
+            .ok_or(SecretStoreError::MissingCipher)?;
+        if !same_key(cipher, current_cipher) {
+            return Err(SecretStoreError::CipherMismatch);
+        }
+        let component_path = self.base_path.join(component.encode());
+        if !component_path.exists() {
+            self.ciphers.insert(component.clone(), new_cipher);
+            return Ok(());
+        }
+
+        let rotating_path = component_path.with_extension(ROTATING_SUFFIX);
+        let replaced_path = component_path.with_extension(REPLACED_SUFFIX);
+        let reencrypt = || -> Result<(), SecretStoreError> {
+            fs::create_dir_all(&rotating_path)?;
+            for entry in fs::read_dir(&component_path)? {
+                let entry = entry?;
+                let plaintext = decrypt(cipher, &fs::read(entry.path())?)?;
+                let mut file = File::create(rotating_path.join(entry.file_name()))?;
+                file.write_all(&encrypt(&new_cipher, &plaintext)?)?;
+                file.sync_all()?;
+            }
+            Ok(())
+        };
+        if let Err(err) = reencrypt() {
+            let _ = fs::remove_dir_all(&rotating_path);
+            return Err(err);
+        }
+
+        fs::rename(&component_path, &replaced_path)?;
+        if let Err(err) = fs::rename(&rotating_path, &component_path) {
+            fs::rename(&replaced_path, &component_path)?;
+            let _ = fs::remove_dir_all(&rotating_path);
+            return Err(err.into());
+        }
+        self.ciphers.insert(component.clone(), new_cipher);
+        fs::remove_dir_all(&replaced_path)?;
+        Ok(())
+    }
+
+    /// Completes or rolls back the key rotations interrupted by a crash.
+    fn finish_rotations(secrets_dir: &Path) -> std::io::Result<()> {
+        for entry in fs::read_dir(secrets_dir)? {
+            let path = entry?.path();
+            let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+                continue;
+            };
+            let component_path = path.with_extension("");
+            match ext {
+                // the secrets were not completely re-encrypted, keep the old ones
+                ROTATING_SUFFIX if !component_path.exists() => {
+                    let replaced_path = path.with_extension(REPLACED_SUFFIX);
+                    if replaced_path.exists() {
+                        fs::rename(&replaced_path, &component_path)?;
+                    }
+                    fs::remove_dir_all(&path)?;
+                }
+                ROTATING_SUFFIX => fs::remove_dir_all(&path)?,
+                // the re-encrypted secrets already replaced the old ones
+                REPLACED_SUFFIX if component_path.exists() => fs::remove_dir_all(&path)?,
+                _ => {}
+            }
+        }
         Ok(())
     }
 
@@ -136,17 +317,24 @@ impl SecretsStore {
         let secret_file_path = component_path.join(key.encode());
         let secret_key = *key.code_hash();
 
-        let encryption = self
+        let cipher = self
             .ciphers
             .get(component)
             .ok_or(SecretStoreError::MissingCipher)?;
-        let ciphertext = encryption
-            .cipher
-            .encrypt(&encryption.nonce, plaintext.as_ref())
-            .map_err(SecretStoreError::Encryption)?;
+        let stored = bincode::serialize(&StoredSecret {
+            id: Some(key.clone()),
+            value: plaintext,
+        })?;
+        let ciphertext = encrypt(cipher, &stored)?;
 
-        self.key_to_secret_part
-            .insert(component.clone(), vec![secret_key]);
+        let mut secret_keys = self
+            .key_to_secret_part
+            .entry(component.clone())
+            .or_default();
+        if !secret_keys.contains(&secret_key) {
+            secret_keys.push(secret_key);
+        }
+        drop(secret_keys);
 
         fs::create_dir_all(&component_path)?;
         let mut file = File::create(secret_file_path)?;
@@ -160,6 +348,9 @@ impl SecretsStore {
         key: &SecretsId,
     ) -> Result<(), SecretStoreError> {
         let secret_path = self.base_path.join(component.encode()).join(key.encode());
+        if let Some(mut secret_keys) = self.key_to_secret_part.get_mut(component) {
+            secret_keys.retain(|secret_key| secret_key != key.code_hash());
+        }
         match fs::remove_file(secret_path) {
             Ok(_) => Ok(()),
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
@@ -167,29 +358,222 @@ impl SecretsStore {
         }
     }
 
+    /// Removes all the secrets of the component along its cipher.
+    pub fn remove_component(&mut self, component: &ComponentKey) -> Result<(), SecretStoreError> {
+        self.ciphers.remove(component);
+        self.key_to_secret_part.remove(component);
+        match fs::remove_dir_all(self.base_path.join(component.encode())) {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     pub fn get_secret(
         &self,
         component: &ComponentKey,
         key: &SecretsId,
     ) -> Result<Vec<u8>, SecretStoreError> {
         let secret_path = self.base_path.join(component.encode()).join(key.encode());
-        let encryption = self
+        let cipher = self
+            .ciphers
+            .get(component)
+            .ok_or(SecretStoreError::MissingCipher)?;
+        let mut secret = read_secret(cipher, &secret_path)?;
+        if secret.id.is_none() {
+            // migrated from the legacy format, keep the id now it is known
+            secret.id = Some(key.clone());
+            if let Err(err) = write_secret(cipher, &secret_path, &secret) {
+                tracing::warn!("failed to store the id of secret {key}: {err}");
+            }
+        }
+        Ok(secret.value)
+    }
+
+    /// Ids of all the secrets of the component, skipping the ones which can't be read.
+    pub fn list_secrets(
+        &self,
+        component: &ComponentKey,
+    ) -> Result<Vec<SecretsId>, SecretStoreError> {
+        let cipher = self
             .ciphers
             .get(component)
             .ok_or(SecretStoreError::MissingCipher)?;
-        let ciphertext = fs::read(secret_path)?;
-        let plaintext = encryption
-            .cipher
-            .decrypt(&encryption.nonce, ciphertext.as_ref())
-            .map_err(SecretStoreError::Encryption)?;
-        Ok(plaintext)
+        let component_path = self.base_path.join(component.encode());
+        if !component_path.exists() {
+            return Ok(vec![]);
+        }
+        let mut secrets = vec![];
+        for entry in fs::read_dir(component_path)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => {
+                    tracing::warn!("failed to read a secret of component {component}: {err}");
+                    continue;
+                }
+            };
+            match read_secret(cipher, &path) {
+                Ok(StoredSecret { id: Some(id), .. }) => secrets.push(id),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("skipping unreadable secret {path:?}: {err}"),
+            }
+        }
+        Ok(secrets)
+    }
+
+    /// Exports all the secrets of the component into an archive encrypted with the passphrase,
+    /// which can be restored in another store with [`SecretsStore::import_secrets`].
+    ///
+    /// Secrets are exported as stored, so the component cipher is still required to read them
+    /// once imported.
+    pub fn export_secrets(
+        &self,
+        component: &ComponentKey,
+        passphrase: &[u8],
+    ) -> Result<Vec<u8>, SecretStoreError> {
+        let component_path = self.base_path.join(component.encode());
+        let mut secrets = vec![];
+        if component_path.exists() {
+            for entry in fs::read_dir(&component_path)? {
+                let entry = entry?;
+                let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+                    continue;
+                };
+                let mut secret_key = [0; 32];
+                if bs58::decode(name)
+                    .with_alphabet(bs58::Alphabet::BITCOIN)
+                    .into(&mut secret_key)
+                    .is_err()
+                {
+                    continue;
+                }
+                secrets.push((secret_key, fs::read(entry.path())?));
+            }
+        }
+        let exported = bincode::serialize(&ExportedSecrets {
+            component: component.clone(),
+            secrets,
+        })
+        .map_err(|_| SecretStoreError::InvalidArchive)?;
+
+        let mut salt = [0; ARCHIVE_SALT_SIZE];
+        crate::util::generate_random_bytes(&mut salt);
+        let cipher = archive_cipher(passphrase, &salt)?;
+        let mut archive = ARCHIVE_HEADER.to_vec();
+        archive.extend_from_slice(&salt);
+        archive.extend(encrypt(&cipher, &exported)?);
+        Ok(archive)
+    }
+
+    /// Restores the secrets exported with [`SecretsStore::export_secrets`], replacing any
+    /// existing secret with the same id. Returns the component owning the secrets.
+    pub fn import_secrets(
+        &mut self,
+        archive: &[u8],
+        passphrase: &[u8],
+    ) -> Result<ComponentKey, SecretStoreError> {
+        let Some(archive) = archive.strip_prefix(ARCHIVE_HEADER.as_slice()) else {
+            return Err(SecretStoreError::InvalidArchive);
+        };
+        if archive.len() < ARCHIVE_SALT_SIZE {
+            return Err(SecretStoreError::InvalidArchive);
+        }
+        let (salt, encrypted) = archive.split_at(ARCHIVE_SALT_SIZE);
+        let cipher = archive_cipher(passphrase, salt)?;
+        let exported = decrypt(&cipher, encrypted).map_err(|_| SecretStoreError::InvalidArchive)?;
+        let ExportedSecrets { component, secrets } =
+            bincode::deserialize(&exported).map_err(|_| SecretStoreError::InvalidArchive)?;
+
+        let component_path = self.base_path.join(component.encode());
+        fs::create_dir_all(&component_path)?;
+        let mut secret_keys = self
+            .key_to_secret_part
+            .get(&component)
+            .map(|keys| keys.value().clone())
+            .unwrap_or_default();
+        for (secret_key, ciphertext) in secrets {
+            let name = bs58::encode(secret_key)
+                .with_alphabet(bs58::Alphabet::BITCOIN)
+                .into_string();
+            let mut file = File::create(component_path.join(name))?;
+            file.write_all(&ciphertext)?;
+            file.sync_all()?;
+            if !secret_keys.contains(&secret_key) {
+                secret_keys.push(secret_key);
+            }
+        }
+        self.key_to_secret_part
+            .insert(component.clone(), secret_keys);
+        Ok(component)
+    }
+}
+
+fn read_secret(cipher: &XChaCha20Poly1305, path: &Path) -> Result<StoredSecret, SecretStoreError> {
+    let plaintext = decrypt(cipher, &fs::read(path)?)?;
+    bincode::deserialize(&plaintext).map_err(|_| SecretStoreError::MalformedSecret)
+}
+
+fn write_secret(
+    cipher: &XChaCha20Poly1305,
+    path: &Path,
+    secret: &StoredSecret,
+) -> Result<(), SecretStoreError> {
+    let plaintext = bincode::serialize(secret).map_err(|_| SecretStoreError::MalformedSecret)?;
+    let mut file = File::create(path)?;
+    file.write_all(&encrypt(cipher, &plaintext)?)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Whether both ciphers use the same key, compared through the tag of an empty message.
+fn same_key(cipher: &XChaCha20Poly1305, other: &XChaCha20Poly1305) -> bool {
+    let key_tag = |cipher: &XChaCha20Poly1305| cipher.encrypt(&XNonce::default(), [].as_slice());
+    matches!((key_tag(cipher), key_tag(other)), (Ok(tag), Ok(other_tag)) if tag == other_tag)
+}
+
+/// Derives the key encrypting an archive of exported secrets from the passphrase.
+fn archive_cipher(passphrase: &[u8], salt: &[u8]) -> Result<XChaCha20Poly1305, SecretStoreError> {
+    let mut key = [0; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(SecretStoreError::KeyDerivation)?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+/// Encrypts the secret with a random nonce, returned in front of the ciphertext after the
+/// format version.
+fn encrypt(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> Result<Vec<u8>, SecretStoreError> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(SecretStoreError::Encryption)?;
+    let mut secret = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+    secret.push(SECRET_FORMAT_VERSION);
+    secret.extend_from_slice(&nonce);
+    secret.extend(ciphertext);
+    Ok(secret)
+}
+
+fn decrypt(cipher: &XChaCha20Poly1305, secret: &[u8]) -> Result<Vec<u8>, SecretStoreError> {
+    let Some((&SECRET_FORMAT_VERSION, secret)) = secret.split_first() else {
+        return Err(SecretStoreError::MalformedSecret);
+    };
+    if secret.len() < NONCE_SIZE {
+        return Err(SecretStoreError::MalformedSecret);
     }
+    let (nonce, ciphertext) = secret.split_at(NONCE_SIZE);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(SecretStoreError::Encryption)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use chacha20poly1305::aead::{AeadCore, KeyInit, OsRng};
+
+    fn new_cipher() -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
 
     #[test]
     fn store_and_load() -> Result<(), Box<dyn std::error::Error>> {
@@ -202,16 +586,187 @@ mod test {
 
         let component = Component::from(vec![0, 1, 2]);
 
-        let cipher = XChaCha20Poly1305::new(&XChaCha20Poly1305::generate_key(&mut OsRng));
-        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
         let secret_id = SecretsId::new(vec![0, 1, 2]);
         let text = vec![0, 1, 2];
 
-        store.register_component(component.key().clone(), cipher, nonce)?;
+        store.register_component(component.key().clone(), new_cipher())?;
         store.store_secret(component.key(), &secret_id, text)?;
         let f = store.get_secret(component.key(), &secret_id);
 
         assert!(f.is_ok());
         Ok(())
     }
+
+    #[test]
+    fn independent_stores() -> Result<(), Box<dyn std::error::Error>> {
+        let first_dir = crate::tests::test_dir("secrets-store-first");
+        let second_dir = crate::tests::test_dir("secrets-store-second");
+        let mut first = SecretsStore::new(first_dir.clone())?;
+        let mut second = SecretsStore::new(second_dir.clone())?;
+        let component = Component::from(vec![6, 7, 8]);
+        let (first_cipher, second_cipher) = (new_cipher(), new_cipher());
+        first.register_component(component.key().clone(), first_cipher.clone())?;
+        second.register_component(component.key().clone(), second_cipher.clone())?;
+        let secrets = [SecretsId::new(vec![0]), SecretsId::new(vec![1])];
+        first.store_secret(component.key(), &secrets[0], vec![1, 2, 3])?;
+        second.store_secret(component.key(), &secrets[1], vec![4, 5, 6])?;
+        second.remove_component(component.key())?;
+
+        let mut first = SecretsStore::new(first_dir)?;
+        let mut second = SecretsStore::new(second_dir)?;
+        first.register_component(component.key().clone(), first_cipher)?;
+        second.register_component(component.key().clone(), second_cipher)?;
+        assert_eq!(
+            first.get_secret(component.key(), &secrets[0])?,
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            first.list_secrets(component.key())?,
+            vec![secrets[0].clone()]
+        );
+        assert!(second.get_secret(component.key(), &secrets[0]).is_err());
+        assert!(second.list_secrets(component.key())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_key() -> Result<(), Box<dyn std::error::Error>> {
+        let secrets_dir = crate::tests::test_dir("secrets-rotate");
+        let mut store = SecretsStore::new(secrets_dir.clone())?;
+        let component = Component::from(vec![3, 4, 5]);
+        let old_cipher = new_cipher();
+        store.register_component(component.key().clone(), old_cipher.clone())?;
+        let secrets = [SecretsId::new(vec![0]), SecretsId::new(vec![1])];
+        for id in &secrets {
+            store.store_secret(component.key(), id, vec![7; 16])?;
+        }
+        let secret_path =
+            |id: &SecretsId| secrets_dir.join(component.key().encode()).join(id.encode());
+        // the same plaintext is encrypted with a different nonce each time
+        assert_ne!(
+            fs::read(secret_path(&secrets[0]))?,
+            fs::read(secret_path(&secrets[1]))?
+        );
+
+        assert!(matches!(
+            store.rotate_component_key(component.key(), &new_cipher(), new_cipher()),
+            Err(SecretStoreError::CipherMismatch)
+        ));
+        store.rotate_component_key(component.key(), &old_cipher, new_cipher())?;
+        for id in &secrets {
+            assert_eq!(store.get_secret(component.key(), id)?, vec![7; 16]);
+            assert!(decrypt(&old_cipher, &fs::read(secret_path(id))?).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_legacy_secrets() -> Result<(), Box<dyn std::error::Error>> {
+        let secrets_dir = crate::tests::test_dir("secrets-legacy");
+        let mut store = SecretsStore::new(secrets_dir.clone())?;
+        let component = Component::from(vec![15, 16, 17]);
+        let cipher = new_cipher();
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let id = SecretsId::new(vec![0]);
+        let component_path = secrets_dir.join(component.key().encode());
+        fs::create_dir_all(&component_path)?;
+        fs::write(
+            component_path.join(id.encode()),
+            cipher.encrypt(&nonce, [1, 2, 3].as_slice()).unwrap(),
+        )?;
+
+        store.register_component(component.key().clone(), cipher.clone())?;
+        assert!(store.get_secret(component.key(), &id).is_err());
+        assert_eq!(store.migrate_legacy_secrets(component.key(), &nonce)?, 1);
+        assert_eq!(store.migrate_legacy_secrets(component.key(), &nonce)?, 0);
+        // the id of a migrated secret is only known once read
+        assert!(store.list_secrets(component.key())?.is_empty());
+        assert_eq!(store.get_secret(component.key(), &id)?, vec![1, 2, 3]);
+        assert_eq!(store.list_secrets(component.key())?, vec![id]);
+        Ok(())
+    }
+
+    #[test]
+    fn export_and_import() -> Result<(), Box<dyn std::error::Error>> {
+        let mut store = SecretsStore::new(crate::tests::test_dir("secrets-export"))?;
+        let component = Component::from(vec![9, 10, 11]);
+        let cipher = new_cipher();
+        store.register_component(component.key().clone(), cipher.clone())?;
+        let secrets = [SecretsId::new(vec![0]), SecretsId::new(vec![1])];
+        for (i, id) in secrets.iter().enumerate() {
+            store.store_secret(component.key(), id, vec![i as u8; 8])?;
+        }
+        let archive = store.export_secrets(component.key(), b"passphrase")?;
+
+        let mut restored = SecretsStore::new(crate::tests::test_dir("secrets-import"))?;
+        assert!(matches!(
+            restored.import_secrets(&archive, b"wrong passphrase"),
+            Err(SecretStoreError::InvalidArchive)
+        ));
+        assert_eq!(
+            &restored.import_secrets(&archive, b"passphrase")?,
+            component.key()
+        );
+        restored.register_component(component.key().clone(), cipher)?;
+        for (i, id) in secrets.iter().enumerate() {
+            assert_eq!(restored.get_secret(component.key(), id)?, vec![i as u8; 8]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_rotation() -> Result<(), Box<dyn std::error::Error>> {
+        let secrets_dir = crate::tests::test_dir("secrets-interrupted");
+        let mut store = SecretsStore::new(secrets_dir.clone())?;
+        let component = Component::from(vec![6, 7, 8]);
+        let cipher = new_cipher();
+        store.register_component(component.key().clone(), cipher.clone())?;
+        let id = SecretsId::new(vec![0]);
+        store.store_secret(component.key(), &id, vec![1, 2, 3])?;
+
+        // crashed after moving the old secrets, before the re-encrypted ones replaced them
+        let component_path = secrets_dir.join(component.key().encode());
+        fs::rename(
+            &component_path,
+            component_path.with_extension(REPLACED_SUFFIX),
+        )?;
+        fs::create_dir_all(component_path.with_extension(ROTATING_SUFFIX))?;
+
+        let mut store = SecretsStore::new(secrets_dir)?;
+        store.register_component(component.key().clone(), cipher)?;
+        assert_eq!(store.get_secret(component.key(), &id)?, vec![1, 2, 3]);
+        assert!(!component_path.with_extension(ROTATING_SUFFIX).exists());
+        assert!(!component_path.with_extension(REPLACED_SUFFIX).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn list_and_remove() -> Result<(), Box<dyn std::error::Error>> {
+        let secrets_dir = crate::tests::test_dir("secrets-list");
+        let mut store = SecretsStore::new(secrets_dir.clone())?;
+        let component = Component::from(vec![12, 13, 14]);
+        store.register_component(component.key().clone(), new_cipher())?;
+        assert!(store.list_secrets(component.key())?.is_empty());
+        let secrets = [SecretsId::new(vec![0]), SecretsId::new(vec![1])];
+        for id in &secrets {
+            store.store_secret(component.key(), id, vec![1, 2, 3])?;
+        }
+        store.remove_secret(component.key(), &secrets[0])?;
+        // unreadable entries are skipped
+        let component_dir = secrets_dir.join(component.key().encode());
+        fs::write(component_dir.join("corrupted"), [0, 1, 2])?;
+        fs::create_dir(component_dir.join("subdir"))?;
+        assert_eq!(
+            store.list_secrets(component.key())?,
+            vec![secrets[1].clone()]
+        );
+
+        store.remove_component(component.key())?;
+        assert!(!secrets_dir.join(component.key().encode()).exists());
+        assert!(matches!(
+            store.list_secrets(component.key()),
+            Err(SecretStoreError::MissingCipher)
+        ));
+        Ok(())
+    }
 }
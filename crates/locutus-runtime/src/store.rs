@@ -1,31 +1,196 @@
+use blake2::{Blake2s256, Digest};
+use dashmap::DashMap;
+use fs2::FileExt;
 use notify::Watcher;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use std::io::Write;
-use std::path::Path;
-use std::{
-    fs::{self, File},
-    io::Read,
-    thread,
-    time::Duration,
-};
-
-use crate::{error::RuntimeInnerError, DynError, RuntimeResult};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::{DynError, RuntimeResult};
+
+/// Header of the index files, followed by the records of the log.
+const LOG_HEADER: &[u8; 8] = b"LCTLOG01";
+/// Size of the length and checksum preceding each record.
+const RECORD_PREFIX_SIZE: usize = 8;
+/// Logs over this size are compacted once they are twice the size of their live entries.
+const COMPACTION_MIN_SIZE: u64 = 64 * 1024;
 
 pub(crate) trait StoreEntriesContainer: Serialize + DeserializeOwned + Default {
-    type MemContainer: Send + Sync + 'static;
-    type Key;
-    type Value;
+    type MemContainer: Default + Send + Sync + 'static;
+    type Key: Serialize + DeserializeOwned;
+    type Value: Serialize + DeserializeOwned;
 
     fn update(self, container: &mut Self::MemContainer);
     fn replace(container: &Self::MemContainer) -> Self;
     fn insert(container: &mut Self::MemContainer, key: Self::Key, value: Self::Value);
+    fn remove(container: &mut Self::MemContainer, key: &Self::Key);
+    fn into_entries(self) -> Vec<(Self::Key, Self::Value)>;
+}
+
+/// Exclusive advisory lock over the index of a store, released when dropped.
+struct IndexLock(File);
+
+impl IndexLock {
+    fn acquire(lock_file_path: &Path) -> RuntimeResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_file_path)?;
+        file.lock_exclusive()?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+/// Max disk space used by the code blobs of a store.
+#[derive(Clone, Copy, Debug)]
+pub struct DiskQuota {
+    /// Max size in bytes of the stored blobs.
+    pub max_size: u64,
+    /// Order in which unreferenced blobs are evicted once over the quota.
+    pub policy: EvictionPolicy,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evicts first the blobs which have not been used for the longest time.
+    #[default]
+    LeastRecentlyUsed,
+    /// Evicts first the blobs used the fewest times, ties are broken by last use.
+    ///
+    /// Use counts are only kept in memory: after a restart every blob starts from zero
+    /// uses, so blobs are evicted by their last modification time until used again.
+    LeastFrequentlyUsed,
+}
+
+impl Display for EvictionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LeastRecentlyUsed => write!(f, "lru"),
+            Self::LeastFrequentlyUsed => write!(f, "lfu"),
+        }
+    }
 }
 
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lru" => Ok(Self::LeastRecentlyUsed),
+            "lfu" => Ok(Self::LeastFrequentlyUsed),
+            other => Err(format!("unknown eviction policy: {other}")),
+        }
+    }
+}
+
+/// Tracks the size and use of the blobs of a store.
+///
+/// Usage is only kept in memory, after a restart blobs are ordered by their last
+/// modification time and their use counts start from zero.
+#[derive(Default)]
+pub(crate) struct BlobUsage {
+    blobs: DashMap<[u8; 32], BlobAccess>,
+}
+
+struct BlobAccess {
+    size: u64,
+    last_access: SystemTime,
+    accesses: u64,
+}
+
+impl BlobUsage {
+    /// Starts tracking a stored blob, unless already tracked.
+    pub fn track(&self, blob: [u8; 32], path: &Path) {
+        if self.blobs.contains_key(&blob) {
+            return;
+        }
+        let Ok(metadata) = fs::metadata(path) else {
+            return;
+        };
+        let last_access = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+        self.blobs.insert(
+            blob,
+            BlobAccess {
+                size: metadata.len(),
+                last_access,
+                accesses: 0,
+            },
+        );
+    }
+
+    pub fn touch(&self, blob: &[u8; 32]) {
+        if let Some(mut access) = self.blobs.get_mut(blob) {
+            access.last_access = SystemTime::now();
+            access.accesses += 1;
+        }
+    }
+
+    pub fn remove(&self, blob: &[u8; 32]) {
+        self.blobs.remove(blob);
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.blobs.iter().map(|access| access.size).sum()
+    }
+
+    /// Tracked blobs in the order they should be evicted.
+    pub fn eviction_order(&self, policy: EvictionPolicy) -> Vec<[u8; 32]> {
+        let mut blobs: Vec<_> = self
+            .blobs
+            .iter()
+            .map(|access| (*access.key(), access.accesses, access.last_access))
+            .collect();
+        match policy {
+            EvictionPolicy::LeastRecentlyUsed => blobs.sort_by_key(|(_, _, last)| *last),
+            EvictionPolicy::LeastFrequentlyUsed => {
+                blobs.sort_by_key(|(_, accesses, last)| (*accesses, *last))
+            }
+        }
+        blobs.into_iter().map(|(blob, _, _)| blob).collect()
+    }
+}
+
+/// A change to the index, as persisted in the log.
+#[derive(Serialize, Deserialize)]
+enum Record<K, V> {
+    Insert(K, V),
+    Remove(K),
+}
+
+/// Entries read from an index log, and whether part of the log was lost.
+struct LogContents<C> {
+    entries: C,
+    damaged: bool,
+}
+
+/// Persists the index of a store as an append-only log of insertions and removals.
+///
+/// Each record is prefixed by its length and checksum, so a record torn by a crash is
+/// detected and dropped on load instead of corrupting the whole index. The log is
+/// periodically compacted by writing its live entries to a temporary file which then
+/// replaces it.
 pub(crate) trait StoreFsManagement<C>
 where
     C: StoreEntriesContainer,
 {
+    /// Entries found by scanning the blobs in the store directory, used to rebuild
+    /// the records lost from a damaged index.
+    fn scan_blobs(_store_dir: &Path) -> RuntimeResult<Vec<(C::Key, C::Value)>> {
+        Ok(vec![])
+    }
+
     fn watch_changes(
         mut container: C::MemContainer,
         key_file_path: &Path,
@@ -36,7 +201,13 @@ where
         let mut watcher = notify::recommended_watcher(
             move |res: Result<notify::Event, notify::Error>| match res {
                 Ok(ev) => {
-                    if let notify::EventKind::Modify(notify::event::ModifyKind::Data(_)) = ev.kind {
+                    // compaction replaces the file, so modifications of its name count too
+                    let modified = matches!(
+                        ev.kind,
+                        notify::EventKind::Modify(notify::event::ModifyKind::Data(_))
+                            | notify::EventKind::Modify(notify::event::ModifyKind::Name(_))
+                    );
+                    if modified && ev.paths.iter().any(|p| p == &key_path) {
                         match Self::load_from_file(key_path.as_path(), lock_path.as_path()) {
                             Err(err) => tracing::error!("{err}"),
                             Ok(map) => {
@@ -48,7 +219,8 @@ where
                 Err(e) => tracing::error!("{e}"),
             },
         )?;
-        watcher.watch(key_file_path, notify::RecursiveMode::NonRecursive)?;
+        let watched_dir = key_file_path.parent().unwrap_or(key_file_path);
+        watcher.watch(watched_dir, notify::RecursiveMode::NonRecursive)?;
         Ok(())
     }
 
@@ -59,44 +231,366 @@ where
         key_file_path: &Path,
         lock_file_path: &Path,
     ) -> RuntimeResult<()> {
-        Self::acquire_ls_lock(lock_file_path)?;
+        let _lock = IndexLock::acquire(lock_file_path)?;
+        let record = encode_record(&Record::Insert(&key, &value))?;
         C::insert(mem_containter, key, value);
-        let container = C::replace(mem_containter);
-        let serialized = bincode::serialize(&container).map_err(|e| RuntimeInnerError::Any(e))?;
-        // FIXME: make this more reliable, append to the file instead of truncating it
-        let mut f = File::create(key_file_path)?;
-        f.write_all(&serialized)?;
-        Self::release_ls_lock(lock_file_path)?;
-        Ok(())
+        append_record::<C>(mem_containter, &record, key_file_path)
+    }
+
+    fn remove(
+        mem_containter: &mut C::MemContainer,
+        key: &C::Key,
+        key_file_path: &Path,
+        lock_file_path: &Path,
+    ) -> RuntimeResult<()> {
+        let _lock = IndexLock::acquire(lock_file_path)?;
+        let record = encode_record(&Record::<_, &C::Value>::Remove(key))?;
+        C::remove(mem_containter, key);
+        append_record::<C>(mem_containter, &record, key_file_path)
     }
 
     fn load_from_file(key_file_path: &Path, lock_file_path: &Path) -> RuntimeResult<C> {
-        let mut buf = vec![];
-        Self::acquire_ls_lock(lock_file_path)?;
-        let mut f = File::open(key_file_path)?;
-        f.read_to_end(&mut buf)?;
-        Self::release_ls_lock(lock_file_path)?;
-        let value = if buf.is_empty() {
-            C::default()
-        } else {
-            bincode::deserialize(&buf).map_err(|e| RuntimeInnerError::Any(e))?
+        let _lock = IndexLock::acquire(lock_file_path)?;
+        Ok(read_log::<C>(key_file_path)?.entries)
+    }
+
+    /// Loads the index, dropping any damaged records and restoring the entries which can be
+    /// recovered from the blobs in the store directory.
+    fn load_or_recover(key_file_path: &Path, lock_file_path: &Path) -> RuntimeResult<C> {
+        let _lock = IndexLock::acquire(lock_file_path)?;
+        let LogContents { entries, damaged } = match read_log::<C>(key_file_path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                tracing::error!("failed to read store index {key_file_path:?}: {err}");
+                LogContents {
+                    entries: C::default(),
+                    damaged: true,
+                }
+            }
         };
-        Ok(value)
+        if !damaged {
+            return Ok(entries);
+        }
+        tracing::warn!("recovering damaged store index {key_file_path:?}");
+        let mut container = C::MemContainer::default();
+        entries.update(&mut container);
+        let store_dir = key_file_path.parent().unwrap_or(Path::new("."));
+        for (key, value) in Self::scan_blobs(store_dir)? {
+            C::insert(&mut container, key, value);
+        }
+        // replaces the damaged log
+        write_snapshot(key_file_path, &encode_snapshot::<C>(&container)?)?;
+        Ok(C::replace(&container))
+    }
+}
+
+/// Reads all the intact records of the log, converting indexes written
+/// in the previous format. Damaged records at the end of the log are reported but left
+/// in place, they are dropped once the index is recovered.
+fn read_log<C: StoreEntriesContainer>(key_file_path: &Path) -> RuntimeResult<LogContents<C>> {
+    let mut buf = vec![];
+    File::open(key_file_path)?.read_to_end(&mut buf)?;
+    if buf.is_empty() {
+        return Ok(LogContents {
+            entries: C::default(),
+            damaged: false,
+        });
+    }
+    if !buf.starts_with(LOG_HEADER) {
+        // indexes used to be rewritten as a whole on every update
+        let entries: C = bincode::deserialize(&buf)?;
+        let mut container = C::MemContainer::default();
+        entries.update(&mut container);
+        write_snapshot(key_file_path, &encode_snapshot::<C>(&container)?)?;
+        return Ok(LogContents {
+            entries: C::replace(&container),
+            damaged: false,
+        });
     }
 
-    fn acquire_ls_lock(lock_file_path: &Path) -> RuntimeResult<()> {
-        while lock_file_path.exists() {
-            thread::sleep(Duration::from_micros(5));
+    let mut records = &buf[LOG_HEADER.len()..];
+    let mut entries = vec![];
+    while !records.is_empty() {
+        match decode_record(records) {
+            Some((entry, read)) => {
+                entries.push(entry);
+                records = &records[read..];
+            }
+            None => break,
+        }
+    }
+    let damaged = !records.is_empty();
+    if damaged {
+        tracing::warn!(
+            "ignoring {} damaged bytes at the end of store index {key_file_path:?}",
+            records.len()
+        );
+    }
+    // records are replayed in the order they were applied
+    let mut container = C::MemContainer::default();
+    for record in entries {
+        match record {
+            Record::Insert(key, value) => C::insert(&mut container, key, value),
+            Record::Remove(key) => C::remove(&mut container, &key),
         }
-        File::create(lock_file_path)?;
+    }
+    Ok(LogContents {
+        entries: C::replace(&container),
+        damaged,
+    })
+}
+
+/// Appends the record to the log, compacting it if most of its records are stale.
+fn append_record<C: StoreEntriesContainer>(
+    container: &C::MemContainer,
+    record: &[u8],
+    key_file_path: &Path,
+) -> RuntimeResult<()> {
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(key_file_path)?;
+    if f.metadata()?.len() == 0 {
+        f.write_all(LOG_HEADER)?;
+    }
+    f.write_all(record)?;
+    f.sync_data()?;
+
+    let log_size = f.metadata()?.len();
+    if log_size > COMPACTION_MIN_SIZE {
+        let snapshot = encode_snapshot::<C>(container)?;
+        if log_size > 2 * snapshot.len() as u64 {
+            write_snapshot(key_file_path, &snapshot)?;
+        }
+    }
+    Ok(())
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Blake2s256::digest(data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn encode_record<T: Serialize>(entry: &T) -> RuntimeResult<Vec<u8>> {
+    let payload = bincode::serialize(entry)?;
+    let mut record = Vec::with_capacity(RECORD_PREFIX_SIZE + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&checksum(&payload));
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// Decodes the first record, returning it and the number of bytes read, or none if
+/// the record is incomplete or corrupted.
+fn decode_record<T: DeserializeOwned>(buf: &[u8]) -> Option<(T, usize)> {
+    let len = u32::from_le_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+    let sum = buf.get(4..RECORD_PREFIX_SIZE)?;
+    let payload = buf.get(RECORD_PREFIX_SIZE..RECORD_PREFIX_SIZE + len)?;
+    if sum != checksum(payload) {
+        return None;
+    }
+    let entry = bincode::deserialize(payload).ok()?;
+    Some((entry, RECORD_PREFIX_SIZE + len))
+}
+
+fn encode_snapshot<C: StoreEntriesContainer>(
+    container: &C::MemContainer,
+) -> RuntimeResult<Vec<u8>> {
+    let mut snapshot = LOG_HEADER.to_vec();
+    for (key, value) in C::replace(container).into_entries() {
+        snapshot.extend(encode_record(&Record::Insert(key, value))?);
+    }
+    Ok(snapshot)
+}
+
+/// Atomically replaces the log with the snapshot.
+fn write_snapshot(key_file_path: &Path, snapshot: &[u8]) -> RuntimeResult<()> {
+    let tmp_path = PathBuf::from(format!("{}.tmp", key_file_path.display()));
+    let mut tmp = File::create(&tmp_path)?;
+    tmp.write_all(snapshot)?;
+    tmp.sync_all()?;
+    fs::rename(&tmp_path, key_file_path)?;
+    if let Some(dir) = key_file_path.parent() {
+        // persist the rename
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use dashmap::DashMap;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct TestMap(Vec<(u32, u32)>);
+
+    impl StoreEntriesContainer for TestMap {
+        type MemContainer = Arc<DashMap<u32, u32>>;
+        type Key = u32;
+        type Value = u32;
+
+        fn update(self, container: &mut Self::MemContainer) {
+            for (k, v) in self.0 {
+                container.insert(k, v);
+            }
+        }
+
+        fn replace(container: &Self::MemContainer) -> Self {
+            TestMap(container.iter().map(|r| (*r.key(), *r.value())).collect())
+        }
+
+        fn insert(container: &mut Self::MemContainer, key: Self::Key, value: Self::Value) {
+            container.insert(key, value);
+        }
+
+        fn remove(container: &mut Self::MemContainer, key: &Self::Key) {
+            container.remove(key);
+        }
+
+        fn into_entries(self) -> Vec<(Self::Key, Self::Value)> {
+            self.0
+        }
+    }
+
+    const BLOB_KEY: u32 = 100;
+
+    struct TestStore;
+
+    impl StoreFsManagement<TestMap> for TestStore {
+        fn scan_blobs(_store_dir: &Path) -> RuntimeResult<Vec<(u32, u32)>> {
+            Ok(vec![(BLOB_KEY, BLOB_KEY)])
+        }
+    }
+
+    fn index_paths(name: &str) -> (PathBuf, PathBuf) {
+        let dir = crate::tests::test_dir(name);
+        (dir.join("KEY_DATA"), dir.join("__LOCK"))
+    }
+
+    fn sorted(map: TestMap) -> Vec<(u32, u32)> {
+        let mut entries = map.into_entries();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn recover_torn_record() -> Result<(), Box<dyn std::error::Error>> {
+        let (key_file, lock_file) = index_paths("store-torn");
+        let mut container = Arc::new(DashMap::new());
+        for i in 0..3 {
+            TestStore::update(&mut container, i, i, &key_file, &lock_file)?;
+        }
+        // a record interrupted half way through
+        let mut f = OpenOptions::new().append(true).open(&key_file)?;
+        f.write_all(&encode_record(&Record::Insert(3u32, 3u32))?[..6])?;
+        let damaged_len = fs::metadata(&key_file)?.len();
+
+        // reading the index doesn't modify it, the damaged record is dropped on recovery
+        let loaded = TestStore::load_from_file(&key_file, &lock_file)?;
+        assert_eq!(sorted(loaded), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(fs::metadata(&key_file)?.len(), damaged_len);
+
+        let recovered = TestStore::load_or_recover(&key_file, &lock_file)?;
+        assert_eq!(
+            sorted(recovered),
+            vec![(0, 0), (1, 1), (2, 2), (BLOB_KEY, BLOB_KEY)]
+        );
+        let reloaded = read_log::<TestMap>(&key_file)?;
+        assert!(!reloaded.damaged);
+        assert_eq!(sorted(reloaded.entries).len(), 4);
         Ok(())
     }
 
-    fn release_ls_lock(lock_file_path: &Path) -> RuntimeResult<()> {
-        match fs::remove_file(lock_file_path) {
-            Ok(_) => Ok(()),
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
-            Err(other) => Err(other.into()),
+    #[test]
+    fn replay_removals() -> Result<(), Box<dyn std::error::Error>> {
+        let (key_file, lock_file) = index_paths("store-remove");
+        let mut container = Arc::new(DashMap::new());
+        TestStore::update(&mut container, 1, 1, &key_file, &lock_file)?;
+        TestStore::update(&mut container, 2, 2, &key_file, &lock_file)?;
+        TestStore::remove(&mut container, &1, &key_file, &lock_file)?;
+        TestStore::update(&mut container, 2, 3, &key_file, &lock_file)?;
+        let loaded = TestStore::load_from_file(&key_file, &lock_file)?;
+        assert_eq!(sorted(loaded), vec![(2, 3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn eviction_order() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = crate::tests::test_dir("store-usage");
+        let usage = BlobUsage::default();
+        for blob in 0..3u8 {
+            let path = dir.join(blob.to_string());
+            fs::write(&path, [0; 10])?;
+            usage.track([blob; 32], &path);
+        }
+        assert_eq!(usage.total_size(), 30);
+        usage.touch(&[0; 32]);
+        usage.touch(&[0; 32]);
+        usage.touch(&[2; 32]);
+        usage.touch(&[1; 32]);
+        assert_eq!(
+            usage.eviction_order(EvictionPolicy::LeastRecentlyUsed),
+            vec![[0; 32], [2; 32], [1; 32]]
+        );
+        assert_eq!(
+            usage.eviction_order(EvictionPolicy::LeastFrequentlyUsed),
+            vec![[2; 32], [1; 32], [0; 32]]
+        );
+        usage.remove(&[2; 32]);
+        assert_eq!(usage.total_size(), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn convert_previous_format() -> Result<(), Box<dyn std::error::Error>> {
+        let (key_file, lock_file) = index_paths("store-convert");
+        fs::write(&key_file, bincode::serialize(&TestMap(vec![(1, 2)]))?)?;
+        let loaded = TestStore::load_or_recover(&key_file, &lock_file)?;
+        assert_eq!(sorted(loaded), vec![(1, 2)]);
+        assert!(fs::read(&key_file)?.starts_with(LOG_HEADER));
+        Ok(())
+    }
+
+    #[test]
+    fn compact_log() -> Result<(), Box<dyn std::error::Error>> {
+        let (key_file, lock_file) = index_paths("store-compact");
+        let mut container = Arc::new(DashMap::new());
+        let record_size = encode_record(&Record::Insert(0u32, 0u32))?.len() as u64;
+        for i in 0..(2 * COMPACTION_MIN_SIZE / record_size) as u32 {
+            TestStore::update(&mut container, i % 4, i, &key_file, &lock_file)?;
+        }
+        assert!(fs::metadata(&key_file)?.len() <= COMPACTION_MIN_SIZE + record_size);
+        let loaded = TestStore::load_from_file(&key_file, &lock_file)?;
+        assert_eq!(sorted(loaded), sorted(TestMap::replace(&container)));
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_updates() -> Result<(), Box<dyn std::error::Error>> {
+        let (key_file, lock_file) = index_paths("store-concurrent");
+        let writers: Vec<_> = (0..4u32)
+            .map(|writer| {
+                let (key_file, lock_file) = (key_file.clone(), lock_file.clone());
+                std::thread::spawn(move || -> RuntimeResult<()> {
+                    let mut container = Arc::new(DashMap::new());
+                    for i in 0..50 {
+                        let key = writer * 100 + i;
+                        TestStore::update(&mut container, key, key, &key_file, &lock_file)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap()?;
         }
+        let loaded = read_log::<TestMap>(&key_file)?;
+        assert!(!loaded.damaged);
+        assert_eq!(loaded.entries.into_entries().len(), 200);
+        Ok(())
     }
 }
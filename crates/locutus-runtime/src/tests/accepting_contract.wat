@@ -0,0 +1,12 @@
+;; A contract which accepts any delta.
+(module
+  (memory (export "memory") 1)
+  ;; ContractInterfaceResult { ptr: 2048, kind: ValidateDelta, size: 5 }
+  (data (i32.const 1024) "\00\08\00\00\00\00\00\00\01\00\00\00\05\00\00\00")
+  ;; bincode serialized Ok(true)
+  (data (i32.const 2048) "\00\00\00\00\01")
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_delta") (param i64 i64) (result i64)
+    i64.const 1024))
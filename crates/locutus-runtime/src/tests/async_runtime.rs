@@ -0,0 +1,52 @@
+//! Checks that calls through the async runtime are executed by the worker and time out.
+
+use std::time::Duration;
+
+use locutus_stdlib::prelude::*;
+
+use crate::{AsyncRuntime, RuntimeConfig};
+
+const CALL_TIMEOUT: Duration = Duration::from_millis(100);
+
+fn setup_runtime() -> Result<(AsyncRuntime, ContractKey), Box<dyn std::error::Error>> {
+    let (runtime, key) = super::setup_wat_runtime(
+        "async-runtime",
+        super::ACCEPTING_CONTRACT,
+        RuntimeConfig {
+            call_timeout: CALL_TIMEOUT,
+            call_queue_size: 1,
+            ..Default::default()
+        },
+    )?;
+    Ok((AsyncRuntime::new(runtime)?, key))
+}
+
+#[tokio::test]
+async fn validate_delta() -> Result<(), Box<dyn std::error::Error>> {
+    let (runtime, key) = setup_runtime()?;
+    let valid = runtime
+        .validate_delta(key, Parameters::from(vec![]), StateDelta::from(vec![]))
+        .await?;
+    assert!(valid);
+    Ok(())
+}
+
+#[tokio::test]
+async fn call_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let (runtime, key) = setup_runtime()?;
+    let slow_call = runtime
+        .run(|_| {
+            std::thread::sleep(CALL_TIMEOUT * 2);
+            Ok(())
+        })
+        .await;
+    assert!(matches!(slow_call, Err(err) if err.is_timeout()));
+
+    // the worker is still available once the slow call returns
+    tokio::time::sleep(CALL_TIMEOUT * 2).await;
+    let valid = runtime
+        .validate_delta(key, Parameters::from(vec![]), StateDelta::from(vec![]))
+        .await?;
+    assert!(valid);
+    Ok(())
+}
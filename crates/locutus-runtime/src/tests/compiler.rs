@@ -0,0 +1,51 @@
+//! Checks that contracts can be compiled with each of the supported compiler backends.
+
+use locutus_stdlib::prelude::*;
+
+use crate::{
+    CompilerBackend, ComponentStore, ContractRuntimeInterface, Runtime, RuntimeConfig,
+    RuntimeResult, SecretsStore,
+};
+
+fn setup_runtime(
+    compiler: CompilerBackend,
+) -> Result<RuntimeResult<(Runtime, ContractKey)>, Box<dyn std::error::Error>> {
+    let (store, key) = super::setup_wat_contract("compiler", super::ACCEPTING_CONTRACT)?;
+    let runtime = Runtime::build_with_config(
+        store,
+        ComponentStore::default(),
+        SecretsStore::default(),
+        false,
+        RuntimeConfig {
+            compiler,
+            ..Default::default()
+        },
+    );
+    Ok(runtime.map(|runtime| (runtime, key)))
+}
+
+fn validate_with(compiler: CompilerBackend) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(compiler)??;
+    assert!(runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?);
+    assert!(runtime.gas_used() > 0);
+    Ok(())
+}
+
+#[cfg(feature = "cranelift")]
+#[test]
+fn cranelift() -> Result<(), Box<dyn std::error::Error>> {
+    validate_with(CompilerBackend::Cranelift)
+}
+
+#[cfg(feature = "singlepass")]
+#[test]
+fn singlepass() -> Result<(), Box<dyn std::error::Error>> {
+    validate_with(CompilerBackend::Singlepass)
+}
+
+#[cfg(not(feature = "singlepass"))]
+#[test]
+fn singlepass_not_enabled() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(setup_runtime(CompilerBackend::Singlepass)?.is_err());
+    Ok(())
+}
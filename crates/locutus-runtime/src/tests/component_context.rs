@@ -0,0 +1,109 @@
+//! Checks the access of components to the context kept by the host.
+
+use locutus_stdlib::prelude::*;
+use wasmer::TypedFunction;
+
+use crate::{runtime::RunningInstance, ComponentStore, ContractStore, Runtime, SecretsStore};
+
+/// A component which sets its context from the bytes at the start of its memory, and reads
+/// it back at the given address.
+const CONTEXT_COMPONENT: &str = r#"
+(module
+  (import "locutus_component" "get_context" (func $get_context (param i64 i64 i32) (result i32)))
+  (import "locutus_component" "set_context" (func $set_context (param i64 i64 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "context")
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "set") (param $len i32)
+    i64.const 0
+    i64.const 0
+    local.get $len
+    call $set_context)
+  (func (export "get") (param $ptr i64) (param $len i32) (result i32)
+    i64.const 0
+    local.get $ptr
+    local.get $len
+    call $get_context))
+"#;
+
+fn setup_runtime() -> Result<(Runtime, RunningInstance), Box<dyn std::error::Error>> {
+    let code = wasmer::wat2wasm(CONTEXT_COMPONENT.as_bytes())?.into_owned();
+    let component = Component::from(code);
+    let mut runtime = Runtime::build(
+        ContractStore::new(super::test_dir("component-context"), 10_000)?,
+        ComponentStore::new(super::test_dir("component-context"), 10_000)?,
+        SecretsStore::default(),
+        false,
+    )?;
+    runtime.component_store.store_component(component.clone())?;
+    let running = runtime.prepare_component_call(component.key(), 0)?;
+    running
+        .env
+        .as_mut(&mut runtime.wasm_store)
+        .component_context = Some(ComponentContext::default());
+    Ok((runtime, running))
+}
+
+#[test]
+fn set_and_get_context() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, running) = setup_runtime()?;
+    let set: TypedFunction<i32, ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "set")?;
+    let get: TypedFunction<(i64, i32), i32> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "get")?;
+
+    set.call(&mut runtime.wasm_store, 7)?;
+    let context = running
+        .env
+        .as_ref(&runtime.wasm_store)
+        .component_context
+        .clone();
+    assert_eq!(context.map(|ctx| ctx.0), Some(b"context".to_vec()));
+
+    // the length is returned without writing when the buffer is too small
+    assert_eq!(get.call(&mut runtime.wasm_store, 1024, 0)?, 7);
+    assert_eq!(get.call(&mut runtime.wasm_store, 1024, 7)?, 7);
+    let mut written = vec![0; 7];
+    running
+        .instance
+        .exports
+        .get_memory("memory")?
+        .view(&runtime.wasm_store)
+        .read(1024, &mut written)?;
+    assert_eq!(written, b"context");
+    Ok(())
+}
+
+#[test]
+fn context_out_of_bounds() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, running) = setup_runtime()?;
+    let set: TypedFunction<i32, ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "set")?;
+    let get: TypedFunction<(i64, i32), i32> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "get")?;
+
+    set.call(&mut runtime.wasm_store, 7)?;
+    let page_size = wasmer::WASM_PAGE_SIZE as i64;
+    assert!(get.call(&mut runtime.wasm_store, page_size - 4, 7).is_err());
+    assert!(set
+        .call(&mut runtime.wasm_store, ComponentContext::MAX_SIZE as i32)
+        .is_err());
+
+    // there is no context outside of component calls
+    running
+        .env
+        .as_mut(&mut runtime.wasm_store)
+        .component_context = None;
+    assert!(get.call(&mut runtime.wasm_store, 1024, 7).is_err());
+    Ok(())
+}
@@ -0,0 +1,392 @@
+//! Checks the access of components to the contracts of the host.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use locutus_stdlib::prelude::*;
+
+use crate::{
+    ComponentRuntimeInterface, ComponentStore, ContractAccess, ContractStore, DynError, Runtime,
+    SecretsStore, SessionId,
+};
+
+/// BufferBuilder { start: 32768, capacity: len, last_read: 544, last_write: 548 }
+const INITIATE_BUFFER: &str = r#"
+  (func (export "initiate_buffer") (param $len i32) (result i64)
+    (i64.store (i32.const 512) (i64.const 32768))
+    (i32.store (i32.const 520) (local.get $len))
+    (i64.store (i32.const 528) (i64.const 544))
+    (i64.store (i32.const 536) (i64.const 548))
+    (i64.store (i32.const 544) (i64.const 0))
+    i64.const 512)
+"#;
+
+/// Start of the results returned by the modules.
+const RESULTS_PTR: usize = 1024;
+
+/// Start of the serialized values the results point to.
+const VALUES_PTR: usize = 4096;
+
+fn wat_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("\\{b:02x}")).collect()
+}
+
+/// Data segment of the result at `result_ptr`, pointing to the serialized value at `value_ptr`.
+fn result_data(result_ptr: usize, header: &[u8], value_ptr: usize, value: &[u8]) -> String {
+    format!(
+        "  (data (i32.const {result_ptr}) \"{}\")\n  (data (i32.const {value_ptr}) \"{}\")\n",
+        wat_bytes(header),
+        wat_bytes(value)
+    )
+}
+
+/// A component which answers each call to `process` with the next of the scripted messages,
+/// and keeps the last message received as its context.
+pub(super) fn scripted_component(
+    script: &[Vec<OutboundComponentMsg>],
+) -> Result<Component<'static>, Box<dyn std::error::Error>> {
+    let mut data = String::new();
+    let mut value_ptr = VALUES_PTR;
+    for (call, msgs) in script.iter().enumerate() {
+        let value = bincode::serialize(&Ok::<_, ComponentError>(msgs))?;
+        // ComponentInterfaceResult { ptr, size }
+        let mut header = (value_ptr as i64).to_le_bytes().to_vec();
+        header.extend((value.len() as u32).to_le_bytes());
+        header.extend([0; 4]);
+        data += &result_data(RESULTS_PTR + 16 * call, &header, value_ptr, &value);
+        value_ptr += value.len();
+    }
+    let wat = format!(
+        r#"
+(module
+  (import "locutus_component" "set_context" (func $set_context (param i64 i64 i32)))
+  (memory (export "memory") 1)
+  (global $calls (mut i32) (i32.const 0))
+{data}
+  (func (export "__locutus_set_id") (param i64))
+{INITIATE_BUFFER}
+  (func (export "process") (param i64) (result i64)
+    (call $set_context (i64.const 0) (i64.const 32768) (i32.load (i32.const 548)))
+    (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+    (i64.extend_i32_u
+      (i32.add (i32.const {RESULTS_PTR})
+        (i32.mul (i32.sub (global.get $calls) (i32.const 1)) (i32.const 16)))))
+)"#
+    );
+    Ok(Component::from(
+        wasmer::wat2wasm(wat.as_bytes())?.into_owned(),
+    ))
+}
+
+/// A contract which answers any update with the modification, and any state validation with
+/// the validation result.
+fn update_contract(
+    modification: UpdateModification<'static>,
+    validation: ValidateResult,
+) -> Result<ContractContainer, Box<dyn std::error::Error>> {
+    let mut data = String::new();
+    let mut value_ptr = VALUES_PTR;
+    let results = [
+        // UpdateState
+        (
+            2i32,
+            bincode::serialize(&Ok::<_, ContractError>(modification))?,
+        ),
+        // ValidateState
+        (
+            0i32,
+            bincode::serialize(&Ok::<_, ContractError>(validation))?,
+        ),
+    ];
+    for (i, (kind, value)) in results.iter().enumerate() {
+        // ContractInterfaceResult { ptr, kind, size }
+        let mut header = (value_ptr as i64).to_le_bytes().to_vec();
+        header.extend(kind.to_le_bytes());
+        header.extend((value.len() as u32).to_le_bytes());
+        data += &result_data(RESULTS_PTR + 16 * i, &header, value_ptr, value);
+        value_ptr += value.len();
+    }
+    let wat = format!(
+        r#"
+(module
+  (memory (export "memory") 1)
+{data}
+  (func (export "__locutus_set_id") (param i64))
+{INITIATE_BUFFER}
+  (func (export "update_state") (param i64 i64 i64) (result i64)
+    i64.const {RESULTS_PTR})
+  (func (export "validate_state") (param i64 i64 i64) (result i64)
+    i64.const {VALIDATE_PTR})
+)"#,
+        VALIDATE_PTR = RESULTS_PTR + 16
+    );
+    let code = wasmer::wat2wasm(wat.as_bytes())?.into_owned();
+    Ok(ContractContainer::Wasm(WasmAPIVersion::V1(
+        WrappedContract::new(Arc::new(ContractCode::from(code)), vec![].into()),
+    )))
+}
+
+/// Contracts of the host, recording the requests of the components.
+#[derive(Clone, Default)]
+struct StubContracts(Arc<Mutex<StubState>>);
+
+#[derive(Default)]
+struct StubState {
+    contracts: HashMap<ContractKey, (Parameters<'static>, WrappedState)>,
+    gets: Vec<ContractKey>,
+    stored: Vec<(ContractKey, WrappedState)>,
+    subscriptions: Vec<(ComponentKey, ContractKey)>,
+}
+
+impl ContractAccess for StubContracts {
+    fn get(
+        &mut self,
+        key: &ContractKey,
+    ) -> Result<Option<(Parameters<'static>, WrappedState)>, DynError> {
+        let mut state = self.0.lock().unwrap();
+        state.gets.push(key.clone());
+        Ok(state.contracts.get(key).cloned())
+    }
+
+    fn store(&mut self, key: &ContractKey, state: WrappedState) -> Result<(), DynError> {
+        self.0.lock().unwrap().stored.push((key.clone(), state));
+        Ok(())
+    }
+
+    fn subscribe(&mut self, component: &ComponentKey, key: &ContractKey) -> Result<(), DynError> {
+        self.0
+            .lock()
+            .unwrap()
+            .subscriptions
+            .push((component.clone(), key.clone()));
+        Ok(())
+    }
+}
+
+fn setup_runtime(
+    component: &Component,
+    contracts: Vec<ContractContainer>,
+) -> Result<(Runtime, StubContracts), Box<dyn std::error::Error>> {
+    let mut contract_store = ContractStore::new(super::test_dir("component-contracts"), 10_000)?;
+    let stub = StubContracts::default();
+    for contract in contracts {
+        let state = WrappedState::new(b"state".to_vec());
+        stub.0
+            .lock()
+            .unwrap()
+            .contracts
+            .insert(contract.key(), (contract.params(), state));
+        contract_store.store_contract(contract)?;
+    }
+    let mut runtime = Runtime::build(
+        contract_store,
+        ComponentStore::new(super::test_dir("component-contracts"), 10_000)?,
+        SecretsStore::default(),
+        false,
+    )?;
+    runtime.component_store.store_component(component.clone())?;
+    runtime.set_contract_access(stub.clone());
+    Ok((runtime, stub))
+}
+
+fn instance_id(seed: u8) -> ContractInstanceId {
+    ContractInstanceId::from((Parameters::from(vec![seed]), ContractCode::from(vec![])))
+}
+
+pub(super) fn app_message(processed: bool) -> ApplicationMessage {
+    ApplicationMessage::new(instance_id(1), vec![], processed)
+}
+
+pub(super) fn send_message(
+    runtime: &mut Runtime,
+    component: &Component,
+) -> crate::RuntimeResult<Vec<OutboundComponentMsg>> {
+    runtime.inbound_app_message(
+        component.key(),
+        SessionId(0),
+        vec![InboundComponentMsg::ApplicationMessage(app_message(false))],
+    )
+}
+
+fn missing_key() -> ContractKey {
+    ContractKey::from(instance_id(2))
+}
+
+#[test]
+fn get_contract() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::valid(State::from(vec![])),
+        ValidateResult::Valid,
+    )?;
+    let key = contract.key();
+    let component = scripted_component(&[
+        vec![OutboundComponentMsg::GetContractRequest(
+            GetContractRequest {
+                key: key.clone(),
+                context: ComponentContext::default(),
+                processed: false,
+            },
+        )],
+        vec![OutboundComponentMsg::ApplicationMessage(app_message(true))],
+    ])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let outbound = send_message(&mut runtime, &component)?;
+    assert!(matches!(
+        outbound.as_slice(),
+        [OutboundComponentMsg::ApplicationMessage(msg)] if msg.processed
+    ));
+    assert_eq!(stub.0.lock().unwrap().gets, vec![key]);
+    Ok(())
+}
+
+#[test]
+fn subscribe_contract() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::valid(State::from(vec![])),
+        ValidateResult::Valid,
+    )?;
+    let key = contract.key();
+    let component = scripted_component(&[
+        vec![OutboundComponentMsg::SubscribeContractRequest(
+            SubscribeContractRequest {
+                key: key.clone(),
+                context: ComponentContext::default(),
+                processed: false,
+            },
+        )],
+        vec![OutboundComponentMsg::ApplicationMessage(app_message(true))],
+    ])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let outbound = send_message(&mut runtime, &component)?;
+    assert_eq!(outbound.len(), 1);
+    let stub = stub.0.lock().unwrap();
+    assert_eq!(
+        stub.subscriptions,
+        vec![(component.key().clone(), key.clone())]
+    );
+    assert_eq!(stub.gets, vec![key]);
+    Ok(())
+}
+
+#[test]
+fn update_contract_state() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::valid(State::from(b"new".to_vec())),
+        ValidateResult::Valid,
+    )?;
+    let key = contract.key();
+    let component = scripted_component(&[vec![
+        OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+            key: key.clone(),
+            data: UpdateData::Delta(StateDelta::from(b"delta".to_vec())),
+        }),
+        OutboundComponentMsg::ApplicationMessage(app_message(true)),
+    ]])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let outbound = send_message(&mut runtime, &component)?;
+    assert_eq!(outbound.len(), 1);
+    assert_eq!(
+        stub.0.lock().unwrap().stored,
+        vec![(key, WrappedState::new(b"new".to_vec()))]
+    );
+    Ok(())
+}
+
+#[test]
+fn update_missing_contract() -> Result<(), Box<dyn std::error::Error>> {
+    let component = scripted_component(&[vec![
+        OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+            key: missing_key(),
+            data: UpdateData::Delta(StateDelta::from(b"delta".to_vec())),
+        }),
+        OutboundComponentMsg::ApplicationMessage(app_message(true)),
+    ]])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![])?;
+
+    let err = send_message(&mut runtime, &component).unwrap_err();
+    assert!(err.to_string().contains("not found in store"), "{err}");
+    assert!(stub.0.lock().unwrap().stored.is_empty());
+    Ok(())
+}
+
+#[test]
+fn update_requiring_related_contracts() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::requires(vec![RelatedContract {
+            contract_instance_id: *missing_key().id(),
+            mode: RelatedMode::StateOnce,
+        }]),
+        ValidateResult::Valid,
+    )?;
+    let key = contract.key();
+    let component = scripted_component(&[vec![
+        OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+            key,
+            data: UpdateData::Delta(StateDelta::from(b"delta".to_vec())),
+        }),
+        OutboundComponentMsg::ApplicationMessage(app_message(true)),
+    ]])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let err = send_message(&mut runtime, &component).unwrap_err();
+    assert!(
+        err.to_string().contains("requires related contracts"),
+        "{err}"
+    );
+    assert!(stub.0.lock().unwrap().stored.is_empty());
+    Ok(())
+}
+
+#[test]
+fn update_to_invalid_state() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::valid(State::from(b"new".to_vec())),
+        ValidateResult::Invalid,
+    )?;
+    let key = contract.key();
+    let component = scripted_component(&[vec![
+        OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+            key,
+            data: UpdateData::Delta(StateDelta::from(b"delta".to_vec())),
+        }),
+        OutboundComponentMsg::ApplicationMessage(app_message(true)),
+    ]])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let err = send_message(&mut runtime, &component).unwrap_err();
+    assert!(err.to_string().contains("invalid state"), "{err}");
+    assert!(stub.0.lock().unwrap().stored.is_empty());
+    Ok(())
+}
+
+#[test]
+fn bounded_updates() -> Result<(), Box<dyn std::error::Error>> {
+    let contract = update_contract(
+        UpdateModification::valid(State::from(b"new".to_vec())),
+        ValidateResult::Valid,
+    )?;
+    let key = contract.key();
+    let updates = (0..=100)
+        .map(|_| {
+            OutboundComponentMsg::UpdateContractRequest(UpdateContractRequest {
+                key: key.clone(),
+                data: UpdateData::Delta(StateDelta::from(vec![])),
+            })
+        })
+        .collect();
+    let component = scripted_component(&[updates])?;
+    let (mut runtime, stub) = setup_runtime(&component, vec![contract])?;
+
+    let err = send_message(&mut runtime, &component).unwrap_err();
+    assert!(
+        err.to_string().contains("attempts to update the contract"),
+        "{err}"
+    );
+    assert_eq!(stub.0.lock().unwrap().stored.len(), 100);
+    Ok(())
+}
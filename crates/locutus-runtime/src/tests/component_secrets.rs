@@ -0,0 +1,51 @@
+//! Checks the access of components to their secrets.
+
+use chacha20poly1305::{aead::OsRng, KeyInit, XChaCha20Poly1305};
+use locutus_stdlib::prelude::*;
+
+use super::component_contracts::{app_message, scripted_component, send_message};
+use crate::{ComponentStore, ContractStore, Runtime, SecretsStore, SessionId};
+
+#[test]
+fn list_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    let component = scripted_component(&[
+        vec![OutboundComponentMsg::ListSecretsRequest(
+            ListSecretsRequest {
+                context: ComponentContext::default(),
+                processed: false,
+            },
+        )],
+        vec![OutboundComponentMsg::ApplicationMessage(app_message(true))],
+    ])?;
+    let secrets_dir = super::test_dir("component-secrets");
+    let mut runtime = Runtime::build(
+        ContractStore::new(super::test_dir("component-secrets"), 10_000)?,
+        ComponentStore::new(super::test_dir("component-secrets"), 10_000)?,
+        SecretsStore::new(secrets_dir.clone())?,
+        false,
+    )?;
+    runtime.component_store.store_component(component.clone())?;
+    let cipher = XChaCha20Poly1305::new(&XChaCha20Poly1305::generate_key(&mut OsRng));
+    runtime
+        .secret_store
+        .register_component(component.key().clone(), cipher)?;
+    let id = SecretsId::new(vec![1, 2, 3]);
+    runtime
+        .secret_store
+        .store_secret(component.key(), &id, vec![4, 5, 6])?;
+    // an unreadable secret doesn't fail the listing
+    std::fs::write(
+        secrets_dir.join(component.key().encode()).join("corrupted"),
+        [0, 1, 2],
+    )?;
+
+    let outbound = send_message(&mut runtime, &component)?;
+    assert_eq!(outbound.len(), 1);
+    let received = &runtime.component_contexts[&(component.key().clone(), SessionId(0))];
+    assert!(matches!(
+        bincode::deserialize(&received.0)?,
+        InboundComponentMsg::ListSecretsResponse(ListSecretsResponse { secrets, .. })
+            if secrets == vec![id]
+    ));
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Checks that contracts can't observe non-deterministic inputs in deterministic mode.
+
+use chrono::{DateTime, TimeZone, Utc};
+use locutus_stdlib::prelude::*;
+use wasmer::TypedFunction;
+
+use crate::{Determinism, Runtime, RuntimeConfig, RuntimeResult};
+
+/// A contract which reads the current time and performs float operations.
+const CLOCK_CONTRACT: &str = r#"
+(module
+  (import "locutus_time" "utc_now" (func $utc_now (param i64 i64)))
+  (memory (export "memory") 1)
+  (global $id (mut i64) (i64.const -1))
+  (func (export "__locutus_set_id") (param i64)
+    local.get 0
+    global.set $id)
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "now")
+    global.get $id
+    i64.const 4096
+    call $utc_now)
+  (func (export "div") (param f64 f64) (result i64)
+    local.get 0
+    local.get 1
+    f64.div
+    i64.reinterpret_f64))
+"#;
+
+fn setup_runtime(
+    determinism: Determinism,
+) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (runtime, key) = super::setup_wat_runtime(
+        "determinism",
+        CLOCK_CONTRACT,
+        RuntimeConfig {
+            determinism,
+            ..Default::default()
+        },
+    )?;
+    Ok((runtime, key))
+}
+
+fn call_now(runtime: &mut Runtime, key: &ContractKey) -> RuntimeResult<DateTime<Utc>> {
+    let running = runtime.prepare_contract_call(key, &vec![].into(), 0)?;
+    let now: TypedFunction<(), ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "now")?;
+    now.call(&mut runtime.wasm_store)?;
+    let mut buf = vec![0; std::mem::size_of::<DateTime<Utc>>()];
+    running
+        .instance
+        .exports
+        .get_memory("memory")?
+        .view(&runtime.wasm_store)
+        .read(4096, &mut buf)?;
+    // Safety: the host wrote a `DateTime<Utc>` at this address
+    Ok(unsafe { std::ptr::read_unaligned(buf.as_ptr() as *const DateTime<Utc>) })
+}
+
+#[test]
+fn reject_non_deterministic_imports() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(Determinism::Strict)?;
+    let Err(err) = runtime.prepare_contract_call(&key, &vec![].into(), 0) else {
+        panic!("contract importing utc_now was loaded");
+    };
+    assert!(err.is_contract_exec_error(), "{err}");
+    Ok(())
+}
+
+#[test]
+fn fixed_call_time() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(Determinism::HostInputs)?;
+    assert!(call_now(&mut runtime, &key).is_err());
+
+    let time = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    runtime.set_call_time(time);
+    assert_eq!(call_now(&mut runtime, &key)?, time);
+    assert_eq!(call_now(&mut runtime, &key)?, time);
+    Ok(())
+}
+
+#[test]
+fn canonicalize_nans() -> Result<(), Box<dyn std::error::Error>> {
+    const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+    let (mut runtime, key) = setup_runtime(Determinism::HostInputs)?;
+    runtime.set_call_time(Utc::now());
+    let running = runtime.prepare_contract_call(&key, &vec![].into(), 0)?;
+    let div: TypedFunction<(f64, f64), i64> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "div")?;
+    let nan = div.call(&mut runtime.wasm_store, 0.0, 0.0)?;
+    assert_eq!(nan as u64, CANONICAL_NAN);
+    Ok(())
+}
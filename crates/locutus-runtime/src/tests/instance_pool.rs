@@ -0,0 +1,134 @@
+//! Checks that pooled instances are reset before being reused.
+
+use locutus_stdlib::prelude::*;
+
+use crate::{ContractRuntimeInterface, Runtime, RuntimeConfig};
+
+/// A contract which accepts any delta, but traps if its memory was already written
+/// by a previous call.
+const STATEFUL_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $calls (export "calls") (mut i32) (i32.const 0))
+  ;; ContractInterfaceResult { ptr: 2048, kind: ValidateDelta, size: 5 }
+  (data (i32.const 1024) "\00\08\00\00\00\00\00\00\01\00\00\00\05\00\00\00")
+  ;; bincode serialized Ok(true)
+  (data (i32.const 2048) "\00\00\00\00\01")
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_delta") (param i64 i64) (result i64)
+    (if (i32.load8_u (i32.const 4096))
+      (then unreachable))
+    (if (global.get $calls)
+      (then unreachable))
+    (i32.store8 (i32.const 4096) (i32.const 1))
+    (global.set $calls (i32.const 1))
+    i64.const 1024))
+"#;
+
+/// Like [`STATEFUL_CONTRACT`], but grows its memory to allocate the buffers past the
+/// initial memory.
+const GROWING_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $calls (export "calls") (mut i32) (i32.const 0))
+  ;; ContractInterfaceResult { ptr: 2048, kind: ValidateDelta, size: 5 }
+  (data (i32.const 1024) "\00\08\00\00\00\00\00\00\01\00\00\00\05\00\00\00")
+  ;; bincode serialized Ok(true)
+  (data (i32.const 2048) "\00\00\00\00\01")
+  (func (export "__locutus_set_id") (param i64))
+  ;; BufferBuilder { start: 65536, capacity: len, last_read: 544, last_write: 548 }
+  (func (export "initiate_buffer") (param $len i32) (result i64)
+    (if (i32.lt_u (memory.size) (i32.const 3))
+      (then (drop (memory.grow (i32.const 2)))))
+    (i64.store (i32.const 512) (i64.const 65536))
+    (i32.store (i32.const 520) (local.get $len))
+    (i64.store (i32.const 528) (i64.const 544))
+    (i64.store (i32.const 536) (i64.const 548))
+    (i64.store (i32.const 544) (i64.const 0))
+    i64.const 512)
+  (func (export "validate_delta") (param i64 i64) (result i64)
+    (if (i32.load8_u (i32.const 4096))
+      (then unreachable))
+    (if (global.get $calls)
+      (then unreachable))
+    (i32.store8 (i32.const 4096) (i32.const 1))
+    (global.set $calls (i32.const 1))
+    i64.const 1024))
+"#;
+
+fn setup_runtime(pool_size: usize) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    setup_runtime_with(STATEFUL_CONTRACT, pool_size)
+}
+
+fn setup_runtime_with(
+    wat: &str,
+    pool_size: usize,
+) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (runtime, key) = super::setup_wat_runtime(
+        "pool",
+        wat,
+        RuntimeConfig {
+            instance_pool_size: pool_size,
+            ..Default::default()
+        },
+    )?;
+    Ok((runtime, key))
+}
+
+#[test]
+fn reuse_reset_instances() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(1)?;
+    for _ in 0..3 {
+        let valid =
+            runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?;
+        assert!(valid);
+    }
+    assert!(runtime.contract_instances.take(&key).is_some());
+    assert!(runtime.contract_instances.take(&key).is_none());
+    Ok(())
+}
+
+#[test]
+fn pooling_disabled() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(0)?;
+    for _ in 0..2 {
+        let valid =
+            runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?;
+        assert!(valid);
+    }
+    assert!(runtime.contract_instances.take(&key).is_none());
+    Ok(())
+}
+
+#[test]
+fn reuse_grown_instances() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime_with(GROWING_CONTRACT, 1)?;
+    let delta = StateDelta::from(vec![1; 70_000]);
+    assert!(runtime.validate_delta(&key, &Parameters::from(vec![]), &delta)?);
+
+    let (instance, env) = runtime
+        .contract_instances
+        .take(&key)
+        .expect("pooled instance");
+    let view = instance
+        .exports
+        .get_memory("memory")?
+        .view(&runtime.wasm_store);
+    assert_eq!(view.size(), wasmer::Pages(3));
+    let mut written = vec![1; 70_000];
+    view.read(65_536, &mut written)?;
+    assert!(written.iter().all(|b| *b == 0));
+    runtime
+        .contract_instances
+        .release(&mut runtime.wasm_store, &key, instance, env);
+
+    for _ in 0..2 {
+        let valid =
+            runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?;
+        assert!(valid);
+    }
+    assert!(runtime.contract_instances.take(&key).is_some());
+    Ok(())
+}
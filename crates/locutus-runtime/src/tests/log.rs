@@ -0,0 +1,66 @@
+//! Checks that events logged by contracts reach the host and are rate limited.
+
+use locutus_stdlib::{
+    log::{LogEvent, LogLevel},
+    prelude::*,
+};
+use wasmer::TypedFunction;
+
+use crate::{native_api::log::LogTag, RuntimeConfig};
+
+/// A contract which logs the event serialized at address 4096 each time `log` is called.
+fn logging_contract(event: &LogEvent) -> Result<String, Box<dyn std::error::Error>> {
+    let serialized = bincode::serialize(event)?;
+    let data: String = serialized.iter().map(|b| format!("\\{b:02x}")).collect();
+    Ok(format!(
+        r#"
+(module
+  (import "locutus_log" "log_event" (func $log_event (param i64 i64 i32)))
+  (memory (export "memory") 1)
+  (global $id (mut i64) (i64.const -1))
+  (data (i32.const 4096) "{data}")
+  (func (export "__locutus_set_id") (param i64)
+    local.get 0
+    global.set $id)
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "log")
+    global.get $id
+    i64.const 4096
+    i32.const {len}
+    call $log_event))
+"#,
+        len = serialized.len()
+    ))
+}
+
+#[test]
+fn rate_limit_logs() -> Result<(), Box<dyn std::error::Error>> {
+    const MAX_LOG_EVENTS: u32 = 2;
+    let event = LogEvent {
+        level: LogLevel::Info,
+        target: "logging_contract".to_owned(),
+        message: "hello from the contract".to_owned(),
+        fields: vec![("calls".to_owned(), "1".to_owned())],
+    };
+    let (mut runtime, key) = super::setup_wat_runtime(
+        "log",
+        &logging_contract(&event)?,
+        RuntimeConfig {
+            max_log_events: MAX_LOG_EVENTS,
+            ..Default::default()
+        },
+    )?;
+
+    let running = runtime.prepare_contract_call(&key, &vec![].into(), 0)?;
+    let log: TypedFunction<(), ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "log")?;
+    for _ in 0..MAX_LOG_EVENTS + 1 {
+        log.call(&mut runtime.wasm_store)?;
+    }
+    let limit = runtime.log_limits[&LogTag::Contract(key)].clone();
+    assert_eq!(limit.lock().unwrap().dropped, 1);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Checks that instances can't allocate more linear memory than the runtime allows.
+
+use locutus_stdlib::prelude::*;
+
+use crate::{ComponentStore, ContractRuntimeInterface, Runtime, RuntimeConfig, SecretsStore};
+
+/// A contract which would accept any state, if it ever got to validate it.
+const ACCEPTING_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_state") (param i64 i64 i64) (result i64)
+    unreachable))
+"#;
+
+/// A contract which asks for more memory upfront than allowed.
+const GREEDY_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 32)
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_state") (param i64 i64 i64) (result i64)
+    unreachable))
+"#;
+
+const MAX_MEMORY_PAGES: u32 = 16;
+
+fn setup_runtime(
+    contract: &str,
+    host_mem: bool,
+) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (store, key) = super::setup_wat_contract("memory", contract)?;
+    let runtime = Runtime::build_with_config(
+        store,
+        ComponentStore::default(),
+        SecretsStore::default(),
+        host_mem,
+        RuntimeConfig {
+            max_memory_pages: MAX_MEMORY_PAGES,
+            ..Default::default()
+        },
+    )?;
+    Ok((runtime, key))
+}
+
+fn oversize_state() -> WrappedState {
+    WrappedState::new(vec![
+        0;
+        (MAX_MEMORY_PAGES as usize + 1) * wasmer::WASM_PAGE_SIZE
+    ])
+}
+
+#[test]
+fn oversize_state_in_instance_mem() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(ACCEPTING_CONTRACT, false)?;
+    let err = runtime
+        .validate_state(
+            &key,
+            &Parameters::from(vec![]),
+            &oversize_state(),
+            RelatedContracts::new(),
+        )
+        .unwrap_err();
+    assert!(err.is_contract_exec_error(), "{err}");
+    assert!(err.to_string().contains("insufficient memory"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn oversize_state_in_host_mem() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(ACCEPTING_CONTRACT, true)?;
+    let err = runtime
+        .validate_state(
+            &key,
+            &Parameters::from(vec![]),
+            &oversize_state(),
+            RelatedContracts::new(),
+        )
+        .unwrap_err();
+    assert!(err.is_contract_exec_error(), "{err}");
+    assert!(err.to_string().contains("insufficient memory"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn declared_memory_over_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(GREEDY_CONTRACT, false)?;
+    let err = runtime
+        .validate_state(
+            &key,
+            &Parameters::from(vec![]),
+            &WrappedState::new(vec![]),
+            RelatedContracts::new(),
+        )
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("exceeds the allowed limit"),
+        "{err}"
+    );
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! Checks that calls to contracts are aborted once they exhaust their gas budget.
+
+use locutus_stdlib::prelude::*;
+
+use crate::{ContractRuntimeInterface, RuntimeConfig};
+
+/// A contract which never returns from `validate_delta`.
+const LOOPING_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_delta") (param i64 i64) (result i64)
+    (loop $spin
+      br $spin)
+    i64.const 0))
+"#;
+
+#[test]
+fn out_of_gas() -> Result<(), Box<dyn std::error::Error>> {
+    const MAX_GAS: u64 = 10_000;
+    let (mut runtime, key) = super::setup_wat_runtime(
+        "metering",
+        LOOPING_CONTRACT,
+        RuntimeConfig {
+            max_gas: MAX_GAS,
+            ..Default::default()
+        },
+    )?;
+    let err = runtime
+        .validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))
+        .unwrap_err();
+    assert!(err.is_contract_exec_error(), "{err}");
+    assert_eq!(runtime.gas_used(), MAX_GAS);
+    Ok(())
+}
@@ -8,10 +8,26 @@ use locutus_stdlib::prelude::{
     ContractCode, ContractContainer, ContractKey, WasmAPIVersion, WrappedContract,
 };
 
-use crate::ContractStore;
+use crate::{ComponentStore, ContractStore, Runtime, RuntimeConfig, SecretsStore};
 
+mod async_runtime;
+mod compiler;
+mod component_context;
+mod component_contracts;
+mod component_secrets;
+mod determinism;
+mod instance_pool;
+mod log;
+mod memory;
+mod metering;
+mod module_cache;
+mod native_api;
+mod profiling;
 mod time;
 
+/// A contract which accepts any delta.
+pub(crate) const ACCEPTING_CONTRACT: &str = include_str!("accepting_contract.wat");
+
 static TEST_NO: AtomicUsize = AtomicUsize::new(0);
 
 pub(crate) fn test_dir(prefix: &str) -> PathBuf {
@@ -79,3 +95,36 @@ pub fn setup_test_contract(
     store.store_contract(contract)?;
     Ok((store, key))
 }
+
+/// Stores the contract compiled from the WAT module.
+pub(crate) fn setup_wat_contract(
+    name: &str,
+    wat: &str,
+) -> Result<(ContractStore, ContractKey), Box<dyn std::error::Error>> {
+    let mut store = ContractStore::new(test_dir(name), 10_000)?;
+    let code = wasmer::wat2wasm(wat.as_bytes())?.into_owned();
+    let contract = ContractContainer::Wasm(WasmAPIVersion::V1(WrappedContract::new(
+        Arc::new(ContractCode::from(code)),
+        vec![].into(),
+    )));
+    let key = contract.key();
+    store.store_contract(contract)?;
+    Ok((store, key))
+}
+
+/// A runtime with the contract compiled from the WAT module stored.
+pub(crate) fn setup_wat_runtime(
+    name: &str,
+    wat: &str,
+    config: RuntimeConfig,
+) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (store, key) = setup_wat_contract(name, wat)?;
+    let runtime = Runtime::build_with_config(
+        store,
+        ComponentStore::default(),
+        SecretsStore::default(),
+        false,
+        config,
+    )?;
+    Ok((runtime, key))
+}
@@ -0,0 +1,31 @@
+//! Checks that compiled modules are persisted and reused across runtimes.
+
+use std::path::Path;
+
+use locutus_stdlib::prelude::*;
+
+use crate::{ContractRuntimeInterface, Runtime};
+
+fn setup_runtime(cache_dir: &Path) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = super::setup_wat_runtime(
+        "module-cache",
+        super::ACCEPTING_CONTRACT,
+        Default::default(),
+    )?;
+    runtime.enable_module_cache(cache_dir, 1024 * 1024 * 1024)?;
+    Ok((runtime, key))
+}
+
+#[test]
+fn reuse_cached_modules() -> Result<(), Box<dyn std::error::Error>> {
+    let cache_dir = super::test_dir("module-cache");
+    let (mut runtime, _) = setup_runtime(&cache_dir)?;
+    assert_eq!(runtime.prewarm_module_cache()?, 1);
+    assert_eq!(runtime.prewarm_module_cache()?, 0);
+    drop(runtime);
+
+    let (mut runtime, key) = setup_runtime(&cache_dir)?;
+    assert_eq!(runtime.prewarm_module_cache()?, 0);
+    assert!(runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?);
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Checks that host functions only access the memory of the calling instance within bounds.
+
+use locutus_stdlib::prelude::*;
+use wasmer::TypedFunction;
+
+use crate::Runtime;
+
+/// A contract which asks the host to write the current time at the given address,
+/// optionally growing its memory first.
+const CLOCK_CONTRACT: &str = r#"
+(module
+  (import "locutus_time" "utc_now" (func $utc_now (param i64 i64)))
+  (memory (export "memory") 1)
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "now") (param $ptr i64) (param $grow i32)
+    (drop (memory.grow (local.get $grow)))
+    i64.const 0
+    local.get $ptr
+    call $utc_now))
+"#;
+
+fn setup_runtime() -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    super::setup_wat_runtime("native-api", CLOCK_CONTRACT, Default::default())
+}
+
+#[test]
+fn write_out_of_bounds() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime()?;
+    let running = runtime.prepare_contract_call(&key, &vec![].into(), 0)?;
+    let now: TypedFunction<(i64, i32), ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "now")?;
+    let page_size = wasmer::WASM_PAGE_SIZE as i64;
+    assert!(now.call(&mut runtime.wasm_store, page_size - 4, 0).is_err());
+    assert!(now.call(&mut runtime.wasm_store, -1, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn write_after_memory_growth() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime()?;
+    let running = runtime.prepare_contract_call(&key, &vec![].into(), 0)?;
+    let now: TypedFunction<(i64, i32), ()> = running
+        .instance
+        .exports
+        .get_typed_function(&runtime.wasm_store, "now")?;
+    let page_size = wasmer::WASM_PAGE_SIZE as i64;
+    now.call(&mut runtime.wasm_store, page_size * 4, 4)?;
+    Ok(())
+}
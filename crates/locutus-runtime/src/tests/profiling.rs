@@ -0,0 +1,75 @@
+//! Checks that the stats of contract calls are recorded when profiling is enabled.
+
+use locutus_stdlib::prelude::*;
+
+use crate::{ContractFunction, ContractRuntimeInterface, Runtime, RuntimeConfig};
+
+/// A contract which accepts any delta but traps when summarizing a state.
+const PROFILED_CONTRACT: &str = r#"
+(module
+  (memory (export "memory") 1)
+  ;; ContractInterfaceResult { ptr: 2048, kind: ValidateDelta, size: 5 }
+  (data (i32.const 1024) "\00\08\00\00\00\00\00\00\01\00\00\00\05\00\00\00")
+  ;; bincode serialized Ok(true)
+  (data (i32.const 2048) "\00\00\00\00\01")
+  (func (export "__locutus_set_id") (param i64))
+  (func (export "initiate_buffer") (param i32) (result i64)
+    i64.const 0)
+  (func (export "validate_delta") (param i64 i64) (result i64)
+    i64.const 1024)
+  (func (export "summarize_state") (param i64 i64) (result i64)
+    unreachable))
+"#;
+
+fn setup_runtime(profiling: bool) -> Result<(Runtime, ContractKey), Box<dyn std::error::Error>> {
+    let (runtime, key) = super::setup_wat_runtime(
+        "profiling",
+        PROFILED_CONTRACT,
+        RuntimeConfig {
+            profiling,
+            ..Default::default()
+        },
+    )?;
+    Ok((runtime, key))
+}
+
+#[test]
+fn record_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(true)?;
+    for _ in 0..2 {
+        runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?;
+    }
+    let summary =
+        runtime.summarize_state(&key, &Parameters::from(vec![]), &WrappedState::new(vec![]));
+    assert!(summary.is_err());
+
+    let profile = runtime.profile().expect("profiling enabled");
+    let stats = profile
+        .get(&key, ContractFunction::ValidateDelta)
+        .expect("recorded calls");
+    assert_eq!(stats.calls, 2);
+    assert_eq!(stats.failed_calls, 0);
+    // the second call reuses the pooled instance
+    assert_eq!(stats.instantiations, 1);
+    assert_eq!(stats.bytes_out, 10);
+    assert_eq!(stats.max_memory_pages, 1);
+    assert!(stats.gas > 0);
+
+    let stats = profile
+        .get(&key, ContractFunction::SummarizeState)
+        .expect("recorded calls");
+    assert_eq!(stats.calls, 1);
+    assert_eq!(stats.failed_calls, 1);
+
+    let json: serde_json::Value = serde_json::from_str(&profile.to_json()?)?;
+    assert_eq!(json[key.to_string()]["validate_delta"]["calls"], 2);
+    Ok(())
+}
+
+#[test]
+fn profiling_disabled() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut runtime, key) = setup_runtime(false)?;
+    runtime.validate_delta(&key, &Parameters::from(vec![]), &StateDelta::from(vec![]))?;
+    assert!(runtime.profile().is_none());
+    Ok(())
+}
@@ -1,9 +1,7 @@
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::OsRng, RngCore};
 
+/// Fills the output with bytes from the OS CSPRNG.
 #[inline]
 pub fn generate_random_bytes(output: &mut [u8]) {
-    let mut rng = ThreadRng::default();
-    for element in output {
-        *element = rng.gen();
-    }
+    OsRng.fill_bytes(output);
 }
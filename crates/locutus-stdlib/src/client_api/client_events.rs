@@ -3,7 +3,10 @@ use std::{collections::HashMap, fmt::Display, io::Cursor};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
-    component_interface::{Component, ComponentKey, InboundComponentMsg, OutboundComponentMsg},
+    component_interface::{
+        ClientResponse, Component, ComponentKey, InboundComponentMsg, OutboundComponentMsg,
+        UserInputRequest,
+    },
     prelude::{
         ContractKey, RelatedContracts, StateSummary, TryFromTsStd, UpdateData, WrappedState,
         WsApiError,
@@ -93,9 +96,18 @@ impl ClientRequest<'_> {
                             related_contracts,
                         }
                     }
-                    ContractRequest::Update { key, data } => {
-                        let data = data.into_owned();
-                        ContractRequest::Update { key, data }
+                    ContractRequest::Update {
+                        key,
+                        data,
+                        expected_summary,
+                    } => ContractRequest::Update {
+                        key,
+                        data: data.into_owned(),
+                        expected_summary: expected_summary.map(StateSummary::into_owned),
+                    },
+                    ContractRequest::UpdateBatch { key, data } => {
+                        let data = data.into_iter().map(UpdateData::into_owned).collect();
+                        ContractRequest::UpdateBatch { key, data }
                     }
                     ContractRequest::Get {
                         key,
@@ -105,6 +117,7 @@ impl ClientRequest<'_> {
                         fetch_contract,
                     },
                     ContractRequest::Subscribe { key } => ContractRequest::Subscribe { key },
+                    ContractRequest::Unsubscribe { key } => ContractRequest::Unsubscribe { key },
                 };
                 owned.into()
             }
@@ -138,6 +151,17 @@ pub enum ContractRequest<'a> {
         key: ContractKey,
         #[serde(borrow)]
         data: UpdateData<'a>,
+        /// Summary of the state the update is meant for, if the current state has
+        /// a different summary the update is rejected.
+        #[serde(borrow, default)]
+        expected_summary: Option<StateSummary<'a>>,
+    },
+    /// Update an existing contract corresponding with the provided key, applying all the
+    /// updates in a single contract execution.
+    UpdateBatch {
+        key: ContractKey,
+        #[serde(borrow)]
+        data: Vec<UpdateData<'a>>,
     },
     /// Fetch the current state from a contract corresponding to the provided key.
     Get {
@@ -149,6 +173,8 @@ pub enum ContractRequest<'a> {
     /// Subscribe to the changes in a given contract. Implicitly starts a get operation
     /// if the contract is not present yet.
     Subscribe { key: ContractKey },
+    /// Stop receiving the changes in a given contract the client subscribed to.
+    Unsubscribe { key: ContractKey },
 }
 
 impl<'a> From<ContractRequest<'a>> for ClientRequest<'a> {
@@ -193,12 +219,40 @@ impl<'a> TryFromTsStd<&[u8]> for ContractRequest<'a> {
                             .into_owned(),
                         }
                     }
+                    ["data", "key"] if value_map.get("data").unwrap().is_array() => {
+                        let data = value_map.get("data").unwrap().as_array().unwrap();
+                        ContractRequest::UpdateBatch {
+                            key: ContractKey::try_decode(*value_map.get("key").unwrap())
+                                .map_err(|err| WsApiError::deserialization(err.to_string()))?,
+                            data: data
+                                .iter()
+                                .map(|update| {
+                                    UpdateData::try_decode(update)
+                                        .map(UpdateData::into_owned)
+                                        .map_err(|err| WsApiError::deserialization(err.to_string()))
+                                })
+                                .collect::<Result<_, _>>()?,
+                        }
+                    }
                     ["data", "key"] => ContractRequest::Update {
                         key: ContractKey::try_decode(*value_map.get("key").unwrap())
                             .map_err(|err| WsApiError::deserialization(err.to_string()))?,
                         data: UpdateData::try_decode(*value_map.get("data").unwrap())
                             .map_err(|err| WsApiError::deserialization(err.to_string()))?
                             .into_owned(),
+                        expected_summary: None,
+                    },
+                    ["data", "expectedSummary", "key"] => ContractRequest::Update {
+                        key: ContractKey::try_decode(*value_map.get("key").unwrap())
+                            .map_err(|err| WsApiError::deserialization(err.to_string()))?,
+                        data: UpdateData::try_decode(*value_map.get("data").unwrap())
+                            .map_err(|err| WsApiError::deserialization(err.to_string()))?
+                            .into_owned(),
+                        expected_summary: value_map
+                            .get("expectedSummary")
+                            .unwrap()
+                            .as_slice()
+                            .map(|summary| StateSummary::from(summary.to_vec())),
                     },
                     ["fetchContract", "key"] => ContractRequest::Get {
                         key: ContractKey::try_decode(*value_map.get("key").unwrap())
@@ -209,6 +263,10 @@ impl<'a> TryFromTsStd<&[u8]> for ContractRequest<'a> {
                         key: ContractKey::try_decode(*value_map.get("key").unwrap())
                             .map_err(|err| WsApiError::deserialization(err.to_string()))?,
                     },
+                    ["key", "unsubscribe"] => ContractRequest::Unsubscribe {
+                        key: ContractKey::try_decode(*value_map.get("key").unwrap())
+                            .map_err(|err| WsApiError::deserialization(err.to_string()))?,
+                    },
                     _ => unreachable!(),
                 }
             } else {
@@ -237,10 +295,29 @@ pub enum ComponentRequest<'a> {
     RegisterComponent {
         #[serde(borrow)]
         component: Component<'a>,
-        cipher: [u8; 24],
-        nonce: [u8; 24],
+        /// key of the cipher used to encrypt the component secrets
+        cipher: [u8; 32],
+        /// nonce shared by the secrets stored before each secret had its own, if given those
+        /// secrets are re-encrypted in the current format
+        #[serde(default)]
+        legacy_nonce: Option<[u8; 24]>,
+    },
+    /// Re-encrypts the secrets of the component with a new cipher key.
+    RotateComponentKey {
+        key: ComponentKey,
+        /// key of the cipher the component secrets are currently encrypted with
+        current_cipher: [u8; 32],
+        cipher: [u8; 32],
     },
     UnregisterComponent(ComponentKey),
+    /// Answer of the user to a [`HostResponse::UserInputRequest`], the component is resumed
+    /// with the context it had when the input was requested.
+    UserInputResponse {
+        key: ComponentKey,
+        request_id: u32,
+        #[serde(borrow)]
+        response: ClientResponse<'a>,
+    },
 }
 
 impl ComponentRequest<'_> {
@@ -255,18 +332,36 @@ impl ComponentRequest<'_> {
             ComponentRequest::RegisterComponent {
                 component,
                 cipher,
-                nonce,
+                legacy_nonce,
             } => {
                 let component = component.into_owned();
                 ComponentRequest::RegisterComponent {
                     component,
                     cipher,
-                    nonce,
+                    legacy_nonce,
                 }
             }
+            ComponentRequest::RotateComponentKey {
+                key,
+                current_cipher,
+                cipher,
+            } => ComponentRequest::RotateComponentKey {
+                key,
+                current_cipher,
+                cipher,
+            },
             ComponentRequest::UnregisterComponent(key) => {
                 ComponentRequest::UnregisterComponent(key)
             }
+            ComponentRequest::UserInputResponse {
+                key,
+                request_id,
+                response,
+            } => ComponentRequest::UserInputResponse {
+                key,
+                request_id,
+                response: response.into_owned(),
+            },
         }
     }
 }
@@ -281,6 +376,9 @@ impl Display for ClientRequest<'_> {
                     write!(f, "put request for contract {contract} with state {state}")
                 }
                 ContractRequest::Update { key, .. } => write!(f, "Update request for {key}"),
+                ContractRequest::UpdateBatch { key, data } => {
+                    write!(f, "Update request for {key} with {} updates", data.len())
+                }
                 ContractRequest::Get {
                     key,
                     fetch_contract: contract,
@@ -289,6 +387,7 @@ impl Display for ClientRequest<'_> {
                     write!(f, "get request for {key} (fetch full contract: {contract})")
                 }
                 ContractRequest::Subscribe { key, .. } => write!(f, "subscribe request for {key}"),
+                ContractRequest::Unsubscribe { key } => write!(f, "unsubscribe request for {key}"),
             },
             ClientRequest::ComponentOp(_op) => write!(f, "component request"),
             ClientRequest::Disconnect { .. } => write!(f, "client disconnected"),
@@ -306,10 +405,25 @@ pub enum HostResponse<T = WrappedState, U = Vec<u8>> {
         values: Vec<OutboundComponentMsg>,
     },
     GenerateRandData(U),
+    /// Input requested from the user by a component, to answer with a
+    /// [`ComponentRequest::UserInputResponse`].
+    UserInputRequest {
+        key: ComponentKey,
+        #[serde(deserialize_with = "deser_user_input_request")]
+        request: UserInputRequest<'static>,
+    },
     /// A requested action which doesn't require an answer was performed successfully.
     Ok,
 }
 
+fn deser_user_input_request<'de, D>(deser: D) -> Result<UserInputRequest<'static>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <UserInputRequest as Deserialize>::deserialize(deser)?;
+    Ok(value.into_owned())
+}
+
 impl HostResponse {
     pub fn unwrap_put(self) -> ContractKey {
         if let Self::ContractResponse(ContractResponse::PutResponse { key }) = self {
@@ -349,6 +463,11 @@ impl std::fmt::Display for HostResponse {
                 }
             },
             HostResponse::ComponentResponse { .. } => write!(f, "component responses"),
+            HostResponse::UserInputRequest { key, request } => write!(
+                f,
+                "user input request {} of component {key}",
+                request.request_id
+            ),
             HostResponse::Ok => write!(f, "ok response"),
             HostResponse::GenerateRandData(_) => write!(f, "random bytes"),
         }
@@ -403,3 +522,54 @@ impl<T> From<ContractResponse<T>> for HostResponse<T> {
         HostResponse::ContractResponse(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::{ContractCode, ContractInstanceId, Parameters, StateDelta};
+
+    #[test]
+    fn decode_update_without_expected_summary() -> Result<(), Box<dyn std::error::Error>> {
+        /// Updates as encoded by the clients before `expected_summary` was added.
+        #[derive(Serialize)]
+        enum PreviousContractRequest {
+            #[allow(dead_code)]
+            Put {},
+            Update {
+                key: ContractKey,
+                data: UpdateData<'static>,
+            },
+        }
+        #[derive(Serialize)]
+        enum PreviousClientRequest {
+            #[allow(dead_code)]
+            ComponentOp {},
+            ContractOp(PreviousContractRequest),
+        }
+
+        let key = ContractKey::from(ContractInstanceId::from((
+            Parameters::from(vec![]),
+            ContractCode::from(vec![1, 2, 3]),
+        )));
+        let data = UpdateData::Delta(StateDelta::from(vec![4, 5, 6]));
+        let encoded = rmp_serde::to_vec(&PreviousClientRequest::ContractOp(
+            PreviousContractRequest::Update {
+                key: key.clone(),
+                data: data.clone(),
+            },
+        ))?;
+        let decoded: ClientRequest = rmp_serde::from_slice(&encoded)?;
+        let ClientRequest::ContractOp(decoded) = decoded else {
+            panic!("expected a contract request");
+        };
+        assert_eq!(
+            decoded,
+            ContractRequest::Update {
+                key,
+                data,
+                expected_summary: None,
+            }
+        );
+        Ok(())
+    }
+}
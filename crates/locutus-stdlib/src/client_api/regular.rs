@@ -89,6 +89,8 @@ impl Sink<ClientRequest<'static>> for WebApi {
 }
 
 impl WebApi {
+    /// Starts handling the requests through the connection, which has to be opened with the
+    /// `encodingProtocol=native` query parameter for the node to decode the requests.
     pub fn start(connection: Connection) -> Self {
         let (request_tx, request_rx) = mpsc::channel(1);
         let (response_tx, response_rx) = mpsc::channel(1);
@@ -9,7 +9,7 @@ use blake2::{Blake2s256, Digest};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::prelude::ContractInstanceId;
+use crate::prelude::{ContractInstanceId, ContractKey, UpdateData, WrappedState};
 
 const APPLICATION_HASH_SIZE: usize = 32;
 const COMPONENT_HASH_LENGTH: usize = 32;
@@ -144,6 +144,15 @@ impl ComponentKey {
             .into_string()
     }
 
+    /// Returns the decoded component key from its `Base58` encoded representation.
+    pub fn decode(encoded: impl Into<String>) -> Result<Self, bs58::decode::Error> {
+        let mut key = [0; COMPONENT_HASH_LENGTH];
+        bs58::decode(encoded.into())
+            .with_alphabet(bs58::Alphabet::BITCOIN)
+            .into(&mut key)?;
+        Ok(Self(key))
+    }
+
     /// Returns the hash of the contract key only.
     pub fn code_hash(&self) -> &[u8; COMPONENT_HASH_LENGTH] {
         &self.0
@@ -177,6 +186,37 @@ impl ComponentContext {
         assert!(bytes.len() < Self::MAX_SIZE);
         let _ = std::mem::replace(&mut self.0, bytes);
     }
+
+    /// Context of the client session the component is processing messages for, kept by the
+    /// host across calls.
+    pub fn current() -> Self {
+        let id = unsafe { crate::global::INSTANCE_ID };
+        let len = unsafe { get_context(id, 0, 0) };
+        let mut bytes = vec![0; len as usize];
+        unsafe { get_context(id, bytes.as_mut_ptr() as usize as i64, len) };
+        Self(bytes)
+    }
+
+    /// Replaces the context of the current client session. The host ignores the contexts in
+    /// the messages returned by components which set their context this way.
+    pub fn set_current(&self) {
+        assert!(self.0.len() < Self::MAX_SIZE);
+        unsafe {
+            set_context(
+                crate::global::INSTANCE_ID,
+                self.0.as_ptr() as usize as i64,
+                self.0.len() as i32,
+            )
+        };
+    }
+}
+
+#[link(wasm_import_module = "locutus_component")]
+extern "C" {
+    #[doc(hidden)]
+    fn get_context(id: i64, ptr: i64, len: i32) -> i32;
+    #[doc(hidden)]
+    fn set_context(id: i64, ptr: i64, len: i32);
 }
 
 #[serde_as]
@@ -189,12 +229,8 @@ pub enum InboundComponentMsg<'a> {
     GetSecretResponse(GetSecretResponse),
     RandomBytes(Vec<u8>),
     UserResponse(#[serde(borrow)] UserInputResponse<'a>),
-    // GetContractResponse {
-    //     contract_id: ContractInstanceId,
-    //     #[serde(borrow)]
-    //     update_data: UpdateData<'static>,
-    //     context: Context
-    // },
+    GetContractResponse(GetContractResponse),
+    ListSecretsResponse(ListSecretsResponse),
 }
 
 impl InboundComponentMsg<'_> {
@@ -204,10 +240,16 @@ impl InboundComponentMsg<'_> {
                 InboundComponentMsg::ApplicationMessage(r)
             }
             InboundComponentMsg::GetSecretResponse(r) => InboundComponentMsg::GetSecretResponse(r),
+            InboundComponentMsg::ListSecretsResponse(r) => {
+                InboundComponentMsg::ListSecretsResponse(r)
+            }
             InboundComponentMsg::RandomBytes(b) => InboundComponentMsg::RandomBytes(b),
             InboundComponentMsg::UserResponse(r) => {
                 InboundComponentMsg::UserResponse(r.into_owned())
             }
+            InboundComponentMsg::GetContractResponse(r) => {
+                InboundComponentMsg::GetContractResponse(r)
+            }
         }
     }
 
@@ -219,6 +261,13 @@ impl InboundComponentMsg<'_> {
             InboundComponentMsg::GetSecretResponse(GetSecretResponse { context, .. }) => {
                 Some(context)
             }
+            InboundComponentMsg::ListSecretsResponse(ListSecretsResponse { context, .. }) => {
+                Some(context)
+            }
+            InboundComponentMsg::GetContractResponse(GetContractResponse { context, .. }) => {
+                Some(context)
+            }
+            InboundComponentMsg::UserResponse(UserInputResponse { context, .. }) => Some(context),
             _ => None,
         }
     }
@@ -231,6 +280,13 @@ impl InboundComponentMsg<'_> {
             InboundComponentMsg::GetSecretResponse(GetSecretResponse { context, .. }) => {
                 Some(context)
             }
+            InboundComponentMsg::ListSecretsResponse(ListSecretsResponse { context, .. }) => {
+                Some(context)
+            }
+            InboundComponentMsg::GetContractResponse(GetContractResponse { context, .. }) => {
+                Some(context)
+            }
+            InboundComponentMsg::UserResponse(UserInputResponse { context, .. }) => Some(context),
             _ => None,
         }
     }
@@ -244,6 +300,27 @@ pub struct GetSecretResponse {
     pub context: ComponentContext,
 }
 
+/// Ids of all the secrets stored by the component.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListSecretsResponse {
+    pub secrets: Vec<SecretsId>,
+    #[serde(skip)]
+    pub context: ComponentContext,
+}
+
+/// State of a contract, answering a [`GetContractRequest`] or a [`SubscribeContractRequest`].
+///
+/// Subscribed components receive it again, with a default context, each time the contract
+/// is updated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetContractResponse {
+    pub key: ContractKey,
+    /// None if the host doesn't have the state of the contract.
+    pub state: Option<WrappedState>,
+    #[serde(skip)]
+    pub context: ComponentContext,
+}
+
 #[non_exhaustive]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApplicationMessage {
@@ -292,16 +369,16 @@ pub enum OutboundComponentMsg {
     // for the apps
     ApplicationMessage(ApplicationMessage),
     RequestUserInput(#[serde(deserialize_with = "deser_func")] UserInputRequest<'static>),
-    // todo: remove when context can be accessed from the component environment and we pass it as reference
+    // for components not accessing the context through `ComponentContext::current`
     ContextUpdated(ComponentContext),
     // from the node
     GetSecretRequest(GetSecretRequest),
     SetSecretRequest(SetSecretRequest),
     RandomBytesRequest(usize),
-    // GetContractRequest {
-    //     mode: RelatedMode,
-    //     contract_id: ContractInstanceId,
-    // },
+    GetContractRequest(GetContractRequest),
+    UpdateContractRequest(UpdateContractRequest),
+    SubscribeContractRequest(SubscribeContractRequest),
+    ListSecretsRequest(ListSecretsRequest),
 }
 
 impl From<GetSecretRequest> for OutboundComponentMsg {
@@ -310,6 +387,30 @@ impl From<GetSecretRequest> for OutboundComponentMsg {
     }
 }
 
+impl From<ListSecretsRequest> for OutboundComponentMsg {
+    fn from(req: ListSecretsRequest) -> Self {
+        Self::ListSecretsRequest(req)
+    }
+}
+
+impl From<GetContractRequest> for OutboundComponentMsg {
+    fn from(req: GetContractRequest) -> Self {
+        Self::GetContractRequest(req)
+    }
+}
+
+impl From<UpdateContractRequest> for OutboundComponentMsg {
+    fn from(req: UpdateContractRequest) -> Self {
+        Self::UpdateContractRequest(req)
+    }
+}
+
+impl From<SubscribeContractRequest> for OutboundComponentMsg {
+    fn from(req: SubscribeContractRequest) -> Self {
+        Self::SubscribeContractRequest(req)
+    }
+}
+
 impl From<ApplicationMessage> for OutboundComponentMsg {
     fn from(req: ApplicationMessage) -> Self {
         Self::ApplicationMessage(req)
@@ -321,8 +422,12 @@ impl OutboundComponentMsg {
         match self {
             OutboundComponentMsg::ApplicationMessage(msg) => msg.processed,
             OutboundComponentMsg::GetSecretRequest(msg) => msg.processed,
+            OutboundComponentMsg::ListSecretsRequest(msg) => msg.processed,
             OutboundComponentMsg::RandomBytesRequest(_) => false,
             OutboundComponentMsg::SetSecretRequest(_) => false,
+            OutboundComponentMsg::GetContractRequest(msg) => msg.processed,
+            OutboundComponentMsg::UpdateContractRequest(_) => false,
+            OutboundComponentMsg::SubscribeContractRequest(msg) => msg.processed,
             OutboundComponentMsg::RequestUserInput(_) => true,
             OutboundComponentMsg::ContextUpdated(_) => true,
         }
@@ -336,6 +441,16 @@ impl OutboundComponentMsg {
             OutboundComponentMsg::GetSecretRequest(GetSecretRequest { context, .. }) => {
                 Some(context)
             }
+            OutboundComponentMsg::ListSecretsRequest(ListSecretsRequest { context, .. }) => {
+                Some(context)
+            }
+            OutboundComponentMsg::GetContractRequest(GetContractRequest { context, .. }) => {
+                Some(context)
+            }
+            OutboundComponentMsg::SubscribeContractRequest(SubscribeContractRequest {
+                context,
+                ..
+            }) => Some(context),
             _ => None,
         }
     }
@@ -348,6 +463,16 @@ impl OutboundComponentMsg {
             OutboundComponentMsg::GetSecretRequest(GetSecretRequest { context, .. }) => {
                 Some(context)
             }
+            OutboundComponentMsg::ListSecretsRequest(ListSecretsRequest { context, .. }) => {
+                Some(context)
+            }
+            OutboundComponentMsg::GetContractRequest(GetContractRequest { context, .. }) => {
+                Some(context)
+            }
+            OutboundComponentMsg::SubscribeContractRequest(SubscribeContractRequest {
+                context,
+                ..
+            }) => Some(context),
             _ => None,
         }
     }
@@ -368,6 +493,49 @@ pub struct GetSecretRequest {
     pub processed: bool,
 }
 
+/// Requests the ids of all the secrets stored by the component, answered with
+/// an [`InboundComponentMsg::ListSecretsResponse`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListSecretsRequest {
+    pub context: ComponentContext,
+    pub processed: bool,
+}
+
+/// Requests the current state of a contract, answered with an
+/// [`InboundComponentMsg::GetContractResponse`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetContractRequest {
+    pub key: ContractKey,
+    pub context: ComponentContext,
+    pub processed: bool,
+}
+
+/// Updates the state of a contract, the update is applied by the contract as if it came
+/// from a client.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateContractRequest {
+    pub key: ContractKey,
+    #[serde(deserialize_with = "deser_update_data")]
+    pub data: UpdateData<'static>,
+}
+
+/// Subscribes the component to the updates of a contract. Answered with the current state
+/// of the contract and, after each update, with its new state.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubscribeContractRequest {
+    pub key: ContractKey,
+    pub context: ComponentContext,
+    pub processed: bool,
+}
+
+fn deser_update_data<'de, D>(deser: D) -> Result<UpdateData<'static>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <UpdateData<'de> as Deserialize>::deserialize(deser)?;
+    Ok(value.into_owned())
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SetSecretRequest {
     pub key: SecretsId,
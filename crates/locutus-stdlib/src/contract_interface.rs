@@ -150,6 +150,19 @@ impl<'a> RelatedContracts<'a> {
     ) -> impl Iterator<Item = (&ContractInstanceId, &mut Option<State<'a>>)> + 'b {
         self.map.iter_mut()
     }
+
+    /// Adds the contracts which states are missing, returns whether any of them was not
+    /// requested yet.
+    pub fn missing(&mut self, contracts: Vec<ContractInstanceId>) -> bool {
+        let mut added = false;
+        for id in contracts {
+            if let std::collections::hash_map::Entry::Vacant(entry) = self.map.entry(id) {
+                entry.insert(None);
+                added = true;
+            }
+        }
+        added
+    }
 }
 
 impl<'a> TryFrom<&'a rmpv::Value> for RelatedContracts<'a> {
@@ -191,8 +204,9 @@ impl<'a> From<HashMap<ContractInstanceId, Option<State<'a>>>> for RelatedContrac
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RelatedContract {
     pub contract_instance_id: ContractInstanceId,
+    /// With [`RelatedMode::StateThenSubscribe`] the host stops notifying the changes of the
+    /// related contract after a timeout, requesting it again renews the subscription.
     pub mode: RelatedMode,
-    // todo: add a timeout so we stop listening/subscribing eventually
 }
 
 /// Specification of the notifications of interest from a related contract.
@@ -769,7 +783,7 @@ impl<'a> DerefMut for StateDelta<'a> {
 /// between two contracts as part of the state synchronization mechanism. The format of a state
 /// summary is determined by the state's contract.
 #[serde_as]
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct StateSummary<'a>(
     #[serde_as(as = "serde_with::Bytes")]
     #[serde(borrow)]
@@ -1078,6 +1092,11 @@ impl ContractKey {
         })
     }
 
+    /// Gets the instance id of the contract.
+    pub fn id(&self) -> &ContractInstanceId {
+        &self.instance
+    }
+
     /// Gets the whole spec key hash.
     pub fn bytes(&self) -> &[u8] {
         self.instance.0.as_ref()
@@ -1504,6 +1523,11 @@ pub(crate) mod wasm_interface {
     }
 
     impl ContractInterfaceResult {
+        /// Size in bytes of the serialized result.
+        pub fn size(&self) -> u32 {
+            self.size
+        }
+
         pub unsafe fn unwrap_validate_state_res(
             self,
             mem: WasmLinearMem,
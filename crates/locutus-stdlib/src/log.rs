@@ -0,0 +1,112 @@
+//! Forwarding of `tracing` events emitted by contracts and components to the node.
+
+use serde::{Deserialize, Serialize};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, EnvFilter, Layer};
+
+#[doc(hidden)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<&Level> for LogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::TRACE => LogLevel::Trace,
+            Level::DEBUG => LogLevel::Debug,
+            Level::INFO => LogLevel::Info,
+            Level::WARN => LogLevel::Warn,
+            Level::ERROR => LogLevel::Error,
+        }
+    }
+}
+
+/// A log event as sent to the host.
+#[doc(hidden)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+/// A [`Layer`] which forwards events to the host, where they are logged by the node
+/// tagged with the key of the contract or component which emitted them.
+pub struct HostLogger;
+
+impl<S: Subscriber> Layer<S> for HostLogger {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let event = LogEvent {
+            level: event.metadata().level().into(),
+            target: event.metadata().target().to_owned(),
+            message: visitor.message,
+            fields: visitor.fields,
+        };
+        let Ok(serialized) = bincode::serialize(&event) else {
+            return;
+        };
+        unsafe {
+            log_event(
+                crate::global::INSTANCE_ID,
+                serialized.as_ptr() as usize as i64,
+                serialized.len() as i32,
+            );
+        }
+    }
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_owned();
+        } else {
+            self.fields
+                .push((field.name().to_owned(), value.to_owned()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields
+                .push((field.name().to_owned(), format!("{value:?}")));
+        }
+    }
+}
+
+/// Sets the [`HostLogger`] as the global subscriber, only forwarding the events enabled
+/// by the filter directives. Does nothing if a global subscriber was already set.
+pub fn init(filter: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if tracing::dispatcher::has_been_set() {
+        return Ok(());
+    }
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_new(filter)?)
+        .with(HostLogger)
+        .try_init()?;
+    Ok(())
+}
+
+#[link(wasm_import_module = "locutus_log")]
+extern "C" {
+    #[doc(hidden)]
+    fn log_event(id: i64, ptr: i64, len: i32);
+}
@@ -74,6 +74,12 @@ impl ComponentInterface for TokenComponent {
             InboundComponentMsg::RandomBytes(_) => Err(ComponentError::Other(
                 "unexpected message type: radom bytes".into(),
             )),
+            InboundComponentMsg::ListSecretsResponse(_) => Err(ComponentError::Other(
+                "unexpected message type: list secrets response".into(),
+            )),
+            InboundComponentMsg::GetContractResponse(_) => Err(ComponentError::Other(
+                "unexpected message type: get contract response".into(),
+            )),
         }
     }
 }
@@ -0,0 +1,56 @@
+use locutus_stdlib::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+enum InboundAppMessage {
+    RandomBytes(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum OutboundAppMessage {
+    RandomBytes(Vec<u8>),
+}
+
+struct Component;
+
+#[component]
+impl ComponentInterface for Component {
+    fn process(messages: InboundComponentMsg) -> Result<Vec<OutboundComponentMsg>, ComponentError> {
+        match messages {
+            InboundComponentMsg::ApplicationMessage(incoming_app) => {
+                let message: InboundAppMessage =
+                    bincode::deserialize(incoming_app.payload.as_slice())
+                        .map_err(|err| ComponentError::Other(format!("{err}")))?;
+                let InboundAppMessage::RandomBytes(len) = message;
+
+                let context = ComponentContext::current();
+                if context.0.is_empty() {
+                    // Request the bytes and retry the message once they are in the context
+                    let retry =
+                        ApplicationMessage::new(incoming_app.app, incoming_app.payload, false);
+                    return Ok(vec![
+                        OutboundComponentMsg::RandomBytesRequest(len),
+                        OutboundComponentMsg::ApplicationMessage(retry),
+                    ]);
+                }
+                ComponentContext::default().set_current();
+
+                // Response with the random bytes to the application
+                let response_msg_content = OutboundAppMessage::RandomBytes(context.0);
+                let payload: Vec<u8> = bincode::serialize(&response_msg_content)
+                    .map_err(|err| ComponentError::Other(format!("{err}")))?;
+                let response_app_msg = ApplicationMessage::new(incoming_app.app, payload, true);
+                Ok(vec![OutboundComponentMsg::ApplicationMessage(
+                    response_app_msg,
+                )])
+            }
+            InboundComponentMsg::RandomBytes(bytes) => {
+                ComponentContext::new(bytes).set_current();
+                Ok(vec![])
+            }
+            _inbound_component_msg => Err(ComponentError::Other(
+                "Unexpected app inbound message".to_string(),
+            )),
+        }
+    }
+}